@@ -9,6 +9,7 @@ use oxihtml::html5lib::{
 };
 use oxihtml::serialize::{normalize_tree_text, to_test_format};
 use oxihtml::html5lib::Json;
+use oxihtml::tokenizer::{State as TokenizerState, Token, Tokenizer, TokenizerOpts};
 use oxihtml::{FragmentContext, Options, Parser};
 
 #[derive(Clone, Debug)]
@@ -25,6 +26,7 @@ struct Config {
     max_failures: usize,
     fail_fast: bool,
     filter: Option<String>,
+    customizable_select: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -42,10 +44,10 @@ struct ShowSpec {
 }
 
 fn expand_tilde(path: &str) -> PathBuf {
-    if let Some(rest) = path.strip_prefix("~/") {
-        if let Ok(home) = env::var("HOME") {
-            return PathBuf::from(home).join(rest);
-        }
+    if let Some(rest) = path.strip_prefix("~/")
+        && let Ok(home) = env::var("HOME")
+    {
+        return PathBuf::from(home).join(rest);
     }
     PathBuf::from(path)
 }
@@ -63,6 +65,7 @@ fn parse_args() -> Result<Config, String> {
     let mut max_failures = 20usize;
     let mut fail_fast = false;
     let mut filter = None::<String>;
+    let mut customizable_select = false;
 
     let mut args = env::args().skip(1);
     while let Some(arg) = args.next() {
@@ -110,9 +113,10 @@ fn parse_args() -> Result<Config, String> {
             "--filter" => {
                 filter = Some(args.next().ok_or("--filter needs a string")?);
             }
+            "--customizable-select" => customizable_select = true,
             "--help" | "-h" => {
                 return Err(
-                    "Usage: html5lib-runner --tests ~/html5lib-tests [--tree|--tokenizer|--serializer|--all] [--list] [--list-cases] [--show tree|tokenizer|serializer <file> <case_index>] [--smoke] [--threads N] [--max-failures N] [--fail-fast] [--filter SUBSTR]"
+                    "Usage: html5lib-runner --tests ~/html5lib-tests [--tree|--tokenizer|--serializer|--all] [--list] [--list-cases] [--show tree|tokenizer|serializer <file> <case_index>] [--smoke] [--threads N] [--max-failures N] [--fail-fast] [--filter SUBSTR] [--customizable-select]"
                         .to_string(),
                 );
             }
@@ -144,6 +148,7 @@ fn parse_args() -> Result<Config, String> {
         max_failures: max_failures.max(1),
         fail_fast,
         filter,
+        customizable_select,
     })
 }
 
@@ -180,7 +185,13 @@ fn unimplemented_failure(file: PathBuf, case_index: usize, label: &'static str,
     }
 }
 
-fn run_tree_file(path: &Path, tests_root: &Path, max_failures: usize, fail_fast: bool) -> Summary {
+fn run_tree_file(
+    path: &Path,
+    tests_root: &Path,
+    max_failures: usize,
+    fail_fast: bool,
+    customizable_select: bool,
+) -> Summary {
     let mut summary = Summary::default();
     let cases = match parse_tree_construction_dat(path) {
         Ok(c) => c,
@@ -213,6 +224,8 @@ fn run_tree_file(path: &Path, tests_root: &Path, max_failures: usize, fail_fast:
                 scripting_enabled: *scripting_enabled,
                 iframe_srcdoc: false,
                 collect_errors: false,
+                customizable_select,
+                ..Options::default()
             });
 
             let actual = if let Some(ctx) = &case.fragment_context {
@@ -242,7 +255,7 @@ fn run_tree_file(path: &Path, tests_root: &Path, max_failures: usize, fail_fast:
                 summary.failures.push(Failure {
                     file: rel,
                     case_index: i,
-                    script: *script_label,
+                    script: script_label,
                     input: case.data.clone(),
                     expected: expected_norm,
                     actual: actual_norm,
@@ -340,25 +353,64 @@ fn run_tokenizer_suite(config: &Config) -> Summary {
         };
 
         for (i, test) in tests.iter().enumerate() {
-            let (input, variants) = match test {
-                Json::Object(obj) => {
-                    let input = match json_obj_get(obj, "input") {
-                        Some(Json::String(s)) => s.clone(),
-                        _ => String::new(),
-                    };
-                    let variants = match json_obj_get(obj, "initialStates") {
-                        Some(Json::Array(a)) if !a.is_empty() => a.len(),
-                        _ => 1,
-                    };
-                    (input, variants)
-                }
-                _ => (String::new(), 1),
+            let Json::Object(obj) = test else {
+                continue;
+            };
+            let double_escaped = matches!(json_obj_get(obj, "doubleEscaped"), Some(Json::Bool(true)));
+            let mut input = match json_obj_get(obj, "input") {
+                Some(Json::String(s)) => s.clone(),
+                _ => String::new(),
             };
-            for _ in 0..variants {
+            if double_escaped {
+                input = unescape_double_escaped(&input);
+            }
+            let expected = match json_obj_get(obj, "output") {
+                Some(Json::Array(a)) => normalize_expected_tokens(a, double_escaped),
+                _ => Vec::new(),
+            };
+            let states: Vec<String> = match json_obj_get(obj, "initialStates") {
+                Some(Json::Array(a)) if !a.is_empty() => a
+                    .iter()
+                    .filter_map(|s| match s {
+                        Json::String(s) => Some(s.clone()),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => vec!["Data state".to_string()],
+            };
+
+            let last_start_tag = match json_obj_get(obj, "lastStartTag") {
+                Some(Json::String(s)) => Some(s.clone()),
+                _ => None,
+            };
+
+            for state_name in &states {
                 summary.total += 1;
+                let actual = match tokenizer_state_from_name(state_name) {
+                    Some(initial_state) => {
+                        let opts = TokenizerOpts {
+                            initial_state,
+                            last_start_tag: last_start_tag.clone(),
+                            cdata_allowed: false,
+                        };
+                        tokens_to_json(Tokenizer::with_opts(&input, opts))
+                    }
+                    None => Vec::new(),
+                };
+                if actual == expected {
+                    summary.passed += 1;
+                    continue;
+                }
                 summary.failed += 1;
                 if summary.failures.len() < config.max_failures {
-                    summary.failures.push(unimplemented_failure(rel.clone(), i, "n/a", input.clone()));
+                    summary.failures.push(Failure {
+                        file: rel.clone(),
+                        case_index: i,
+                        script: "n/a",
+                        input: input.clone(),
+                        expected: format!("{expected:?}"),
+                        actual: format!("{actual:?}"),
+                    });
                 }
                 if config.fail_fast {
                     return summary;
@@ -370,6 +422,166 @@ fn run_tokenizer_suite(config: &Config) -> Summary {
     summary
 }
 
+fn tokenizer_state_from_name(name: &str) -> Option<TokenizerState> {
+    match name {
+        "Data state" => Some(TokenizerState::Data),
+        "PLAINTEXT state" => Some(TokenizerState::Plaintext),
+        "RCDATA state" => Some(TokenizerState::Rcdata),
+        "RAWTEXT state" => Some(TokenizerState::Rawtext),
+        "Script data state" => Some(TokenizerState::ScriptData),
+        "CDATA section state" => Some(TokenizerState::CdataSection),
+        _ => None,
+    }
+}
+
+fn unescape_double_escaped(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut units: Vec<u16> = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' && chars.peek() == Some(&'u') {
+            chars.next();
+            let hex: String = chars.by_ref().take(4).collect();
+            if let Ok(unit) = u16::from_str_radix(&hex, 16) {
+                units.push(unit);
+                continue;
+            }
+            out.push_str("\\u");
+            out.push_str(&hex);
+            continue;
+        }
+        if !units.is_empty() {
+            out.extend(char::decode_utf16(units.drain(..)).map(|r| r.unwrap_or('\u{FFFD}')));
+        }
+        out.push(c);
+    }
+    out.extend(char::decode_utf16(units.drain(..)).map(|r| r.unwrap_or('\u{FFFD}')));
+    out
+}
+
+fn normalize_expected_tokens(output: &[Json], double_escaped: bool) -> Vec<Json> {
+    let unescape = |s: &str| {
+        if double_escaped {
+            unescape_double_escaped(s)
+        } else {
+            s.to_string()
+        }
+    };
+    let mut out: Vec<Json> = Vec::new();
+    for item in output {
+        let Json::Array(parts) = item else {
+            continue;
+        };
+        let kind = match parts.first() {
+            Some(Json::String(k)) => k.as_str(),
+            _ => continue,
+        };
+        let token = match kind {
+            "Character" => {
+                let data = match parts.get(1) {
+                    Some(Json::String(s)) => unescape(s),
+                    _ => String::new(),
+                };
+                push_character(&mut out, data);
+                continue;
+            }
+            "Comment" => {
+                let data = match parts.get(1) {
+                    Some(Json::String(s)) => unescape(s),
+                    _ => String::new(),
+                };
+                Json::Array(vec![Json::String("Comment".to_string()), Json::String(data)])
+            }
+            "StartTag" => {
+                let mut attrs = match parts.get(2) {
+                    Some(Json::Object(a)) => a
+                        .iter()
+                        .map(|(k, v)| {
+                            let v = match v {
+                                Json::String(s) => Json::String(unescape(s)),
+                                other => other.clone(),
+                            };
+                            (unescape(k), v)
+                        })
+                        .collect::<Vec<_>>(),
+                    _ => Vec::new(),
+                };
+                attrs.sort_by(|a, b| a.0.cmp(&b.0));
+                let mut tok = vec![
+                    Json::String("StartTag".to_string()),
+                    parts.get(1).cloned().unwrap_or(Json::Null),
+                    Json::Object(attrs),
+                ];
+                if parts.get(3) == Some(&Json::Bool(true)) {
+                    tok.push(Json::Bool(true));
+                }
+                Json::Array(tok)
+            }
+            "EndTag" => Json::Array(vec![
+                Json::String("EndTag".to_string()),
+                parts.get(1).cloned().unwrap_or(Json::Null),
+            ]),
+            "DOCTYPE" => Json::Array(parts.clone()),
+            _ => continue,
+        };
+        out.push(token);
+    }
+    out
+}
+
+fn push_character(out: &mut Vec<Json>, data: String) {
+    if let Some(Json::Array(prev)) = out.last_mut()
+        && prev.first() == Some(&Json::String("Character".to_string()))
+        && let Some(Json::String(prev_data)) = prev.get_mut(1)
+    {
+        prev_data.push_str(&data);
+        return;
+    }
+    out.push(Json::Array(vec![Json::String("Character".to_string()), Json::String(data)]));
+}
+
+fn tokens_to_json(tokens: impl Iterator<Item = Token>) -> Vec<Json> {
+    let opt = |s: Option<String>| s.map(Json::String).unwrap_or(Json::Null);
+    let mut out: Vec<Json> = Vec::new();
+    for token in tokens {
+        let json = match token {
+            Token::Doctype(d) => Json::Array(vec![
+                Json::String("DOCTYPE".to_string()),
+                opt(d.name),
+                opt(d.public_id),
+                opt(d.system_id),
+                Json::Bool(!d.force_quirks),
+            ]),
+            Token::StartTag(tag) => {
+                let mut attrs: Vec<(String, Json)> = tag
+                    .attrs
+                    .into_iter()
+                    .map(|a| (a.name, Json::String(a.value)))
+                    .collect();
+                attrs.sort_by(|a, b| a.0.cmp(&b.0));
+                let mut tok = vec![
+                    Json::String("StartTag".to_string()),
+                    Json::String(tag.name),
+                    Json::Object(attrs),
+                ];
+                if tag.self_closing {
+                    tok.push(Json::Bool(true));
+                }
+                Json::Array(tok)
+            }
+            Token::EndTag(tag) => Json::Array(vec![Json::String("EndTag".to_string()), Json::String(tag.name)]),
+            Token::Comment(data) => Json::Array(vec![Json::String("Comment".to_string()), Json::String(data)]),
+            Token::Character(data) => {
+                push_character(&mut out, data);
+                continue;
+            }
+            Token::Eof => continue,
+        };
+        out.push(json);
+    }
+    out
+}
+
 fn run_serializer_suite(config: &Config) -> Summary {
     let mut summary = Summary::default();
     let mut files = match discover_serializer_files(&config.tests_root) {
@@ -584,8 +796,8 @@ fn main() -> std::process::ExitCode {
         return show_case(&config, show);
     }
 
-    if (config.mode_tokenizer || config.mode_serializer) && !config.list_only && !config.smoke {
-        eprintln!("note: tokenizer/serializer execution is not implemented yet; use --smoke to validate fixture parsing");
+    if config.mode_serializer && !config.list_only && !config.smoke {
+        eprintln!("note: serializer execution is not implemented yet; use --smoke to validate fixture parsing");
     }
 
     if config.smoke {
@@ -660,11 +872,9 @@ fn main() -> std::process::ExitCode {
         };
     }
 
-    if !config.mode_tree {
-        if !config.list_only {
-            eprintln!("no runnable mode selected (only --tree execution is implemented currently)");
-            return std::process::ExitCode::from(2);
-        }
+    if !(config.mode_tree || config.mode_tokenizer || config.list_only) {
+        eprintln!("no runnable mode selected (only --tree and --tokenizer execution are implemented currently)");
+        return std::process::ExitCode::from(2);
     }
 
     let mut files = match discover_tree_construction_files(&config.tests_root) {
@@ -697,16 +907,17 @@ fn main() -> std::process::ExitCode {
     }
 
     let mut all = Summary::default();
-    if !files.is_empty() {
+    if config.mode_tree && !files.is_empty() {
         let threads = config.threads.min(files.len());
         let (tx, rx) = mpsc::channel::<Summary>();
 
-        let chunk_size = (files.len() + threads - 1) / threads;
+        let chunk_size = files.len().div_ceil(threads);
         for chunk in files.chunks(chunk_size) {
             let tx = tx.clone();
             let tests_root = config.tests_root.clone();
             let max_failures = config.max_failures;
             let fail_fast = config.fail_fast;
+            let customizable_select = config.customizable_select;
             let paths = chunk.to_vec();
             thread::spawn(move || {
                 let mut summary = Summary::default();
@@ -716,6 +927,7 @@ fn main() -> std::process::ExitCode {
                         &tests_root,
                         max_failures.saturating_sub(summary.failures.len()),
                         fail_fast,
+                        customizable_select,
                     );
                     summary.total += s.total;
                     summary.passed += s.passed;
@@ -746,7 +958,9 @@ fn main() -> std::process::ExitCode {
 
     let mut exit_fail = all.failed > 0;
 
-    println!("tree-construction: {}/{} passed ({} failed)", all.passed, all.total, all.failed);
+    if config.mode_tree {
+        println!("tree-construction: {}/{} passed ({} failed)", all.passed, all.total, all.failed);
+    }
 
     if config.mode_tokenizer {
         let tok = run_tokenizer_suite(&config);
@@ -767,6 +981,9 @@ fn main() -> std::process::ExitCode {
         println!("failures (showing up to {}):", config.max_failures);
         for f in &all.failures {
             println!("- {} case={} mode={}", f.file.display(), f.case_index, f.script);
+            println!("  input:    {:?}", f.input);
+            println!("  expected: {}", f.expected.replace('\n', "\n            "));
+            println!("  actual:   {}", f.actual.replace('\n', "\n            "));
         }
     }
 
@@ -1,3 +1,7 @@
+use std::collections::HashMap;
+
+use crate::{AttributeSpan, Span};
+
 pub type NodeId = usize;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -27,20 +31,50 @@ pub struct Doctype {
     pub system_id: String,
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ShadowRootMode {
+    #[default]
+    Open,
+    Closed,
+}
+
+// A shadow root declared with `<template shadowrootmode>`; the flags come
+// from the template's `shadowrootdelegatesfocus`, `shadowrootclonable` and
+// `shadowrootserializable` attributes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ShadowRoot {
+    pub mode: ShadowRootMode,
+    pub delegates_focus: bool,
+    pub clonable: bool,
+    pub serializable: bool,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum NodeData {
     Document,
     DocumentFragment,
+    // Like a fragment, the root of its own tree: it is not among its host's
+    // children and has no parent.
+    ShadowRoot(ShadowRoot),
     Element {
         name: QualName,
         attrs: Vec<Attr>,
         template_contents: Option<NodeId>,
+        shadow_root: Option<NodeId>,
     },
     Text(String),
     Comment(String),
     Doctype(Doctype),
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QuirksMode {
+    #[default]
+    NoQuirks,
+    LimitedQuirks,
+    Quirks,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Node {
     pub data: NodeData,
@@ -48,16 +82,50 @@ pub struct Node {
     pub children: Vec<NodeId>,
 }
 
+// Where a node came from, recorded when `Options::source_spans` is set.
+// Spans index the `&str` that was parsed (after decoding, for byte input).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NodeSource {
+    pub start_tag: Option<Span>,
+    // The end tag that closed the element, if it was closed by one.
+    pub end_tag: Option<Span>,
+    // Parallel to the element's `attrs`; attributes merged in from a later
+    // `<html>` or `<body>` tag point into that tag.
+    pub attributes: Vec<AttributeSpan>,
+    // The whole comment or doctype, or for a text node everything from its
+    // first to its last character token.
+    pub data: Option<Span>,
+    // Created by the tree builder rather than written in the source: implied
+    // `html`, `head`, `body`, `tbody` and the like, and formatting elements
+    // cloned by the adoption agency or reconstruction.
+    pub synthetic: bool,
+}
+
+// The input and the tree exactly as parsed, kept with
+// `Options::preserve_source`. `serialize::to_html_preserving` compares the
+// arena against `nodes` to tell edited nodes from untouched ones.
+#[derive(Clone, Debug)]
+pub struct Original {
+    pub text: String,
+    pub nodes: Vec<Node>,
+}
+
 #[derive(Clone, Debug)]
 pub struct Document {
     pub arena: Vec<Node>,
     pub root: NodeId,
+    pub quirks_mode: QuirksMode,
+    pub sources: HashMap<NodeId, NodeSource>,
+    pub original: Option<Original>,
 }
 
 #[derive(Clone, Debug)]
 pub struct DocumentFragment {
     pub arena: Vec<Node>,
     pub root: NodeId,
+    // The context element a parsed fragment was parsed for. It is not part of
+    // the fragment, but a declarative shadow root can be attached to it.
+    pub context: Option<NodeId>,
 }
 
 impl Document {
@@ -69,7 +137,55 @@ impl Document {
             parent: None,
             children: Vec::new(),
         });
-        Self { arena, root }
+        Self {
+            arena,
+            root,
+            quirks_mode: QuirksMode::NoQuirks,
+            sources: HashMap::new(),
+            original: None,
+        }
+    }
+
+    pub fn source(&self, node: NodeId) -> Option<&NodeSource> {
+        self.sources.get(&node)
+    }
+
+    // Merges `source` into what is already recorded for `node`: spans fill
+    // in once, attributes append, and text data grows to cover both.
+    pub fn record_source(&mut self, node: NodeId, source: NodeSource) {
+        let entry = self.sources.entry(node).or_default();
+        entry.start_tag = entry.start_tag.or(source.start_tag);
+        entry.end_tag = entry.end_tag.or(source.end_tag);
+        entry.attributes.extend(source.attributes);
+        entry.data = cover(entry.data, source.data);
+        entry.synthetic |= source.synthetic;
+    }
+
+    // The innermost node whose source contains byte `offset`, for mapping a
+    // cursor position back to the tree. Builds an `OffsetIndex` each time, so
+    // callers looking up many offsets should build one and keep it.
+    pub fn node_at_offset(&self, offset: usize) -> Option<NodeId> {
+        self.offset_index().node_at(offset)
+    }
+
+    pub fn offset_index(&self) -> OffsetIndex<'_> {
+        OffsetIndex::new(self)
+    }
+
+    // Children in source order, with a template's contents standing in for
+    // the template's own children and a shadow root ahead of its host's.
+    fn source_children(&self, node: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        let contents = match &self.arena[node].data {
+            NodeData::Element {
+                template_contents,
+                shadow_root,
+                ..
+            } => template_contents.or(*shadow_root),
+            _ => None,
+        };
+        contents
+            .into_iter()
+            .chain(self.arena[node].children.iter().copied())
     }
 
     pub fn create_element(&mut self, name: QualName) -> NodeId {
@@ -79,6 +195,7 @@ impl Document {
                 name,
                 attrs: Vec::new(),
                 template_contents: None,
+                shadow_root: None,
             },
             parent: None,
             children: Vec::new(),
@@ -126,7 +243,11 @@ impl DocumentFragment {
             parent: None,
             children: Vec::new(),
         });
-        Self { arena, root }
+        Self {
+            arena,
+            root,
+            context: None,
+        }
     }
 
     pub fn create_element(&mut self, name: QualName) -> NodeId {
@@ -136,6 +257,7 @@ impl DocumentFragment {
                 name,
                 attrs: Vec::new(),
                 template_contents: None,
+                shadow_root: None,
             },
             parent: None,
             children: Vec::new(),
@@ -164,14 +286,85 @@ impl DocumentFragment {
     }
 }
 
-pub fn append_child(arena: &mut Vec<Node>, parent: NodeId, child: NodeId) {
+// The source each node of a document covers, for mapping offsets back to the
+// tree. Elements span from their start tag to their end tag, or to the end of
+// their last descendant when they have none; synthetic elements cover exactly
+// their descendants. It borrows the document, so it cannot outlive an edit.
+#[derive(Clone, Debug)]
+pub struct OffsetIndex<'a> {
+    doc: &'a Document,
+    // Per node, its children in source order with the source they cover, and
+    // whether those follow one another without overlapping, so that the one
+    // holding an offset can be found by binary search.
+    children: Vec<Vec<(Span, NodeId)>>,
+    ordered: Vec<bool>,
+}
+
+impl<'a> OffsetIndex<'a> {
+    fn new(doc: &'a Document) -> Self {
+        let len = doc.arena.len();
+        let mut extents: Vec<Option<Span>> = vec![None; len];
+        let mut children = vec![Vec::new(); len];
+        let mut ordered = vec![true; len];
+        let mut stack = vec![(doc.root, false)];
+        while let Some((id, done)) = stack.pop() {
+            if !done {
+                stack.push((id, true));
+                stack.extend(doc.source_children(id).map(|c| (c, false)));
+                continue;
+            }
+            let mut extent = doc.sources.get(&id).and_then(|s| cover(cover(s.start_tag, s.end_tag), s.data));
+            let kids: Vec<(Span, NodeId)> = doc
+                .source_children(id)
+                .filter_map(|c| extents[c].map(|e| (e, c)))
+                .collect();
+            for &(child, _) in &kids {
+                extent = cover(extent, Some(child));
+            }
+            ordered[id] = kids.windows(2).all(|w| w[0].0.end <= w[1].0.start);
+            extents[id] = extent;
+            children[id] = kids;
+        }
+        Self { doc, children, ordered }
+    }
+
+    // The innermost node whose source contains byte `offset`.
+    pub fn node_at(&self, offset: usize) -> Option<NodeId> {
+        let contains = |span: &Span| span.start <= offset && offset < span.end;
+        let mut node = self.doc.root;
+        loop {
+            let kids = &self.children[node];
+            let found = if self.ordered[node] {
+                kids.get(kids.partition_point(|(span, _)| span.end <= offset))
+                    .filter(|(span, _)| contains(span))
+            } else {
+                kids.iter().find(|(span, _)| contains(span))
+            };
+            match found {
+                Some(&(_, child)) => node = child,
+                None => return (node != self.doc.root).then_some(node),
+            }
+        }
+    }
+}
+
+fn cover(a: Option<Span>, b: Option<Span>) -> Option<Span> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.cover(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+pub fn append_child(arena: &mut [Node], parent: NodeId, child: NodeId) {
+    detach(arena, child);
     arena[child].parent = Some(parent);
     arena[parent].children.push(child);
 }
 
-pub fn insert_before(arena: &mut Vec<Node>, parent: NodeId, new_child: NodeId, reference: Option<NodeId>) {
+pub fn insert_before(arena: &mut [Node], parent: NodeId, new_child: NodeId, reference: Option<NodeId>) {
+    detach(arena, new_child);
     if let Some(r) = reference {
-        let pos = arena[parent].children.iter().position(|&c| c == r);
+        let pos = arena[parent].children.iter().rposition(|&c| c == r);
         if let Some(i) = pos {
             arena[new_child].parent = Some(parent);
             arena[parent].children.insert(i, new_child);
@@ -181,17 +374,94 @@ pub fn insert_before(arena: &mut Vec<Node>, parent: NodeId, new_child: NodeId, r
     append_child(arena, parent, new_child);
 }
 
-pub fn detach(arena: &mut Vec<Node>, node: NodeId) {
+pub fn detach(arena: &mut [Node], node: NodeId) {
     let Some(parent) = arena[node].parent else {
         return;
     };
-    if let Some(pos) = arena[parent].children.iter().position(|&c| c == node) {
+    // The parser mostly moves and inserts next to the last children of wide
+    // parents, so search from the end.
+    if let Some(pos) = arena[parent].children.iter().rposition(|&c| c == node) {
         arena[parent].children.remove(pos);
     }
     arena[node].parent = None;
 }
 
-pub fn set_attr(arena: &mut Vec<Node>, element: NodeId, attr: Attr) {
+pub fn reparent_children(arena: &mut [Node], from: NodeId, to: NodeId) {
+    let children = std::mem::take(&mut arena[from].children);
+    for &child in &children {
+        arena[child].parent = Some(to);
+    }
+    arena[to].children.extend(children);
+}
+
+pub fn clone_node(arena: &mut Vec<Node>, node: NodeId) -> NodeId {
+    let mut data = arena[node].data.clone();
+    if let NodeData::Element {
+        template_contents,
+        shadow_root,
+        ..
+    } = &mut data
+    {
+        *template_contents = None;
+        *shadow_root = None;
+    }
+    let id = arena.len();
+    arena.push(Node {
+        data,
+        parent: None,
+        children: Vec::new(),
+    });
+    id
+}
+
+// How many nodes `clone_subtree` would create for `node`.
+pub fn subtree_len(arena: &[Node], node: NodeId) -> usize {
+    let mut len = 0;
+    let mut stack = vec![node];
+    while let Some(id) = stack.pop() {
+        len += 1;
+        if let NodeData::Element {
+            template_contents: Some(contents),
+            ..
+        } = arena[id].data
+        {
+            stack.push(contents);
+        }
+        stack.extend(&arena[id].children);
+    }
+    len
+}
+
+// Copies `node` with its descendants and any template contents, unattached.
+pub fn clone_subtree(arena: &mut Vec<Node>, node: NodeId) -> NodeId {
+    let root = clone_node(arena, node);
+    let mut stack = vec![(node, root)];
+    while let Some((from, to)) = stack.pop() {
+        if let NodeData::Element {
+            template_contents: Some(contents),
+            ..
+        } = arena[from].data
+        {
+            let copy = clone_node(arena, contents);
+            if let NodeData::Element {
+                template_contents, ..
+            } = &mut arena[to].data
+            {
+                *template_contents = Some(copy);
+            }
+            stack.push((contents, copy));
+        }
+        for i in 0..arena[from].children.len() {
+            let child = arena[from].children[i];
+            let copy = clone_node(arena, child);
+            append_child(arena, to, copy);
+            stack.push((child, copy));
+        }
+    }
+    root
+}
+
+pub fn set_attr(arena: &mut [Node], element: NodeId, attr: Attr) {
     let NodeData::Element { attrs, .. } = &mut arena[element].data else {
         return;
     };
@@ -228,3 +498,21 @@ pub fn ensure_template_contents(arena: &mut Vec<Node>, template: NodeId) -> Node
     }
     id
 }
+
+// Gives `host` a new, empty shadow root. Returns `None`, changing nothing, if
+// `host` is not an element or already has one.
+pub fn attach_shadow_root(arena: &mut Vec<Node>, host: NodeId, shadow: ShadowRoot) -> Option<NodeId> {
+    if !matches!(arena[host].data, NodeData::Element { shadow_root: None, .. }) {
+        return None;
+    }
+    let id = arena.len();
+    arena.push(Node {
+        data: NodeData::ShadowRoot(shadow),
+        parent: None,
+        children: Vec::new(),
+    });
+    if let NodeData::Element { shadow_root, .. } = &mut arena[host].data {
+        *shadow_root = Some(id);
+    }
+    Some(id)
+}
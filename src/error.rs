@@ -0,0 +1,382 @@
+use std::fmt;
+use std::str::FromStr;
+
+// `line` is 1-based. `col` counts Unicode scalar values (Rust `char`s) since
+// the start of the line and `col_utf16` counts UTF-16 code units, which is
+// what JavaScript strings and LSP positions use; both are 0 at the start of a
+// line and point just past the last consumed character. CRLF and lone CR count
+// as a single line break. `offset` is a byte offset into the parser's input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub line: u32,
+    pub col: u32,
+    pub col_utf16: u32,
+    pub offset: usize,
+}
+
+impl Location {
+    // Recomputes the location just before byte `offset` of `input`, using the
+    // same line and column rules as the tokenizer. Handy for turning
+    // `span.start` into a line/column pair.
+    pub fn at(input: &str, offset: usize) -> Location {
+        let mut loc = Location {
+            line: 1,
+            col: 0,
+            col_utf16: 0,
+            offset: 0,
+        };
+        let mut chars = input[..offset].chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\r' || c == '\n' {
+                if c == '\r' && chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+                loc.line += 1;
+                loc.col = 0;
+                loc.col_utf16 = 0;
+            } else {
+                loc.col += 1;
+                loc.col_utf16 += c.len_utf16() as u32;
+            }
+        }
+        loc.offset = offset;
+        loc
+    }
+}
+
+// Half-open byte range into the `&str` handed to the parser, so
+// `&input[span.start..span.end]` is the source text it covers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    // The smallest span containing both.
+    pub(crate) fn cover(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+// Where one attribute of a start tag was written. The value span excludes
+// any quotes and is `None` for an attribute written without `=`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AttributeSpan {
+    pub name: Span,
+    pub value: Option<Span>,
+}
+
+macro_rules! error_codes {
+    ($($variant:ident => $code:literal,)*) => {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum ErrorCode {
+            $($variant,)*
+        }
+
+        impl ErrorCode {
+            pub const ALL: &'static [ErrorCode] = &[$(ErrorCode::$variant,)*];
+
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $(ErrorCode::$variant => $code,)*
+                }
+            }
+        }
+
+        impl FromStr for ErrorCode {
+            type Err = UnknownErrorCode;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $($code => Ok(ErrorCode::$variant),)*
+                    _ => Err(UnknownErrorCode(s.to_string())),
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
+    // Tokenizer and input stream errors defined by the WHATWG HTML standard.
+    AbruptClosingOfEmptyComment => "abrupt-closing-of-empty-comment",
+    AbruptDoctypePublicIdentifier => "abrupt-doctype-public-identifier",
+    AbruptDoctypeSystemIdentifier => "abrupt-doctype-system-identifier",
+    AbsenceOfDigitsInNumericCharacterReference => "absence-of-digits-in-numeric-character-reference",
+    CdataInHtmlContent => "cdata-in-html-content",
+    CharacterReferenceOutsideUnicodeRange => "character-reference-outside-unicode-range",
+    ControlCharacterInInputStream => "control-character-in-input-stream",
+    ControlCharacterReference => "control-character-reference",
+    DuplicateAttribute => "duplicate-attribute",
+    EndTagWithAttributes => "end-tag-with-attributes",
+    EndTagWithTrailingSolidus => "end-tag-with-trailing-solidus",
+    EofBeforeTagName => "eof-before-tag-name",
+    EofInCdata => "eof-in-cdata",
+    EofInComment => "eof-in-comment",
+    EofInDoctype => "eof-in-doctype",
+    EofInScriptHtmlCommentLikeText => "eof-in-script-html-comment-like-text",
+    EofInTag => "eof-in-tag",
+    IncorrectlyClosedComment => "incorrectly-closed-comment",
+    IncorrectlyOpenedComment => "incorrectly-opened-comment",
+    InvalidCharacterSequenceAfterDoctypeName => "invalid-character-sequence-after-doctype-name",
+    InvalidFirstCharacterOfTagName => "invalid-first-character-of-tag-name",
+    MissingAttributeValue => "missing-attribute-value",
+    MissingDoctypeName => "missing-doctype-name",
+    MissingDoctypePublicIdentifier => "missing-doctype-public-identifier",
+    MissingDoctypeSystemIdentifier => "missing-doctype-system-identifier",
+    MissingEndTagName => "missing-end-tag-name",
+    MissingQuoteBeforeDoctypePublicIdentifier => "missing-quote-before-doctype-public-identifier",
+    MissingQuoteBeforeDoctypeSystemIdentifier => "missing-quote-before-doctype-system-identifier",
+    MissingSemicolonAfterCharacterReference => "missing-semicolon-after-character-reference",
+    MissingWhitespaceAfterDoctypePublicKeyword => "missing-whitespace-after-doctype-public-keyword",
+    MissingWhitespaceAfterDoctypeSystemKeyword => "missing-whitespace-after-doctype-system-keyword",
+    MissingWhitespaceBeforeDoctypeName => "missing-whitespace-before-doctype-name",
+    MissingWhitespaceBetweenAttributes => "missing-whitespace-between-attributes",
+    MissingWhitespaceBetweenDoctypePublicAndSystemIdentifiers => "missing-whitespace-between-doctype-public-and-system-identifiers",
+    NestedComment => "nested-comment",
+    NoncharacterCharacterReference => "noncharacter-character-reference",
+    NoncharacterInInputStream => "noncharacter-in-input-stream",
+    NonVoidHtmlElementStartTagWithTrailingSolidus => "non-void-html-element-start-tag-with-trailing-solidus",
+    NullCharacterReference => "null-character-reference",
+    SurrogateCharacterReference => "surrogate-character-reference",
+    SurrogateInInputStream => "surrogate-in-input-stream",
+    UnexpectedCharacterAfterDoctypeSystemIdentifier => "unexpected-character-after-doctype-system-identifier",
+    UnexpectedCharacterInAttributeName => "unexpected-character-in-attribute-name",
+    UnexpectedCharacterInUnquotedAttributeValue => "unexpected-character-in-unquoted-attribute-value",
+    UnexpectedEqualsSignBeforeAttributeName => "unexpected-equals-sign-before-attribute-name",
+    UnexpectedNullCharacter => "unexpected-null-character",
+    UnexpectedQuestionMarkInsteadOfTagName => "unexpected-question-mark-instead-of-tag-name",
+    UnexpectedSolidusInTag => "unexpected-solidus-in-tag",
+    UnknownNamedCharacterReference => "unknown-named-character-reference",
+    // Tree-construction errors, named as in html5lib-tests.
+    AdoptionAgency1_1 => "adoption-agency-1.1",
+    AdoptionAgency1_2 => "adoption-agency-1.2",
+    AdoptionAgency1_3 => "adoption-agency-1.3",
+    AdoptionAgency4_4 => "adoption-agency-4.4",
+    AttributesInEndTag => "attributes-in-end-tag",
+    CantConvertNumericEntity => "cant-convert-numeric-entity",
+    CharInHeadNoscript => "char-in-head-noscript",
+    DeprecatedTag => "deprecated-tag",
+    EndTagAfterImpliedRoot => "end-tag-after-implied-root",
+    EndTagTooEarly => "end-tag-too-early",
+    EndTagTooEarlyIgnored => "end-tag-too-early-ignored",
+    EndTagTooEarlyNamed => "end-tag-too-early-named",
+    EofInAttributeName => "eof-in-attribute-name",
+    EofInAttributeValueDoubleQuote => "eof-in-attribute-value-double-quote",
+    EofInAttributeValueNoQuotes => "eof-in-attribute-value-no-quotes",
+    EofInAttributeValueSingleQuote => "eof-in-attribute-value-single-quote",
+    EofInCommentDoubleDash => "eof-in-comment-double-dash",
+    EofInCommentEndBangState => "eof-in-comment-end-bang-state",
+    EofInCommentEndDash => "eof-in-comment-end-dash",
+    EofInCommentEndSpaceState => "eof-in-comment-end-space-state",
+    EofInDoctypeName => "eof-in-doctype-name",
+    EofInForeignLands => "eof-in-foreign-lands",
+    EofInFrameset => "eof-in-frameset",
+    EofInHeadNoscript => "eof-in-head-noscript",
+    EofInInnerhtml => "eof-in-innerhtml",
+    EofInScriptInScript => "eof-in-script-in-script",
+    EofInSelect => "eof-in-select",
+    EofInTable => "eof-in-table",
+    EofInTagName => "eof-in-tag-name",
+    EqualsInUnquotedAttributeValue => "equals-in-unquoted-attribute-value",
+    ExpectedAttributeNameButGotEof => "expected-attribute-name-but-got-eof",
+    ExpectedAttributeValueButGotEof => "expected-attribute-value-but-got-eof",
+    ExpectedAttributeValueButGotRightBracket => "expected-attribute-value-but-got-right-bracket",
+    ExpectedClosingTagButGotChar => "expected-closing-tag-but-got-char",
+    ExpectedClosingTagButGotEof => "expected-closing-tag-but-got-eof",
+    ExpectedClosingTagButGotRightBracket => "expected-closing-tag-but-got-right-bracket",
+    ExpectedDashesOrDoctype => "expected-dashes-or-doctype",
+    ExpectedDoctypeButGotChars => "expected-doctype-but-got-chars",
+    ExpectedDoctypeButGotEndTag => "expected-doctype-but-got-end-tag",
+    ExpectedDoctypeButGotEof => "expected-doctype-but-got-eof",
+    ExpectedDoctypeButGotStartTag => "expected-doctype-but-got-start-tag",
+    ExpectedDoctypeNameButGotEof => "expected-doctype-name-but-got-eof",
+    ExpectedDoctypeNameButGotRightBracket => "expected-doctype-name-but-got-right-bracket",
+    ExpectedEndOfTagNameButGotEof => "expected-end-of-tag-name-but-got-eof",
+    ExpectedEofButGotChar => "expected-eof-but-got-char",
+    ExpectedEofButGotEndTag => "expected-eof-but-got-end-tag",
+    ExpectedEofButGotStartTag => "expected-eof-but-got-start-tag",
+    ExpectedNamedClosingTagButGotEof => "expected-named-closing-tag-but-got-eof",
+    ExpectedNamedEntity => "expected-named-entity",
+    ExpectedNumericEntity => "expected-numeric-entity",
+    ExpectedNumericEntityButGotEof => "expected-numeric-entity-but-got-eof",
+    ExpectedOneEndTagButGotAnother => "expected-one-end-tag-but-got-another",
+    ExpectedSpaceOrRightBracketInDoctype => "expected-space-or-right-bracket-in-doctype",
+    ExpectedTagName => "expected-tag-name",
+    ExpectedTagNameButGotQuestionMark => "expected-tag-name-but-got-question-mark",
+    ExpectedTagNameButGotRightBracket => "expected-tag-name-but-got-right-bracket",
+    IllegalCodepointForNumericEntity => "illegal-codepoint-for-numeric-entity",
+    IllegalWindows1252Entity => "illegal-windows-1252-entity",
+    IncorrectComment => "incorrect-comment",
+    IncorrectCrNewlineEntity => "incorrect-cr-newline-entity",
+    IncorrectlyPlacedSolidus => "incorrectly-placed-solidus",
+    InvalidCharacterAfterAttributeName => "invalid-character-after-attribute-name",
+    InvalidCharacterInAttributeName => "invalid-character-in-attribute-name",
+    InvalidCodepoint => "invalid-codepoint",
+    MissingEndTag => "missing-end-tag",
+    MissingEndTags => "missing-end-tags",
+    NamedEntityWithoutSemicolon => "named-entity-without-semicolon",
+    NeedSpaceAfterDoctype => "need-space-after-doctype",
+    NoEndTag => "no-end-tag",
+    NonHtmlRoot => "non-html-root",
+    NonVoidElementWithTrailingSolidus => "non-void-element-with-trailing-solidus",
+    NullCharacter => "null-character",
+    NumericEntityWithoutSemicolon => "numeric-entity-without-semicolon",
+    SelfClosingFlagOnEndTag => "self-closing-flag-on-end-tag",
+    TwoHeadsAreNotBetterThanOne => "two-heads-are-not-better-than-one",
+    UnexpectedEofAfterSolidusInTag => "unexpected-EOF-after-solidus-in-tag",
+    UnexpectedBangAfterDoubleDashInComment => "unexpected-bang-after-double-dash-in-comment",
+    UnexpectedCellEndTag => "unexpected-cell-end-tag",
+    UnexpectedCellInTableBody => "unexpected-cell-in-table-body",
+    UnexpectedCharAfterBody => "unexpected-char-after-body",
+    UnexpectedCharAfterFrameset => "unexpected-char-after-frameset",
+    UnexpectedCharImpliesTableVoodoo => "unexpected-char-implies-table-voodoo",
+    UnexpectedCharInComment => "unexpected-char-in-comment",
+    UnexpectedCharInDoctype => "unexpected-char-in-doctype",
+    UnexpectedCharInFrameset => "unexpected-char-in-frameset",
+    UnexpectedCharacterAfterAttributeValue => "unexpected-character-after-attribute-value",
+    UnexpectedCharacterAfterSolidusInTag => "unexpected-character-after-solidus-in-tag",
+    UnexpectedDashAfterDoubleDashInComment => "unexpected-dash-after-double-dash-in-comment",
+    UnexpectedDoctype => "unexpected-doctype",
+    UnexpectedEndOfDoctype => "unexpected-end-of-doctype",
+    UnexpectedEndTag => "unexpected-end-tag",
+    UnexpectedEndTagAfterBody => "unexpected-end-tag-after-body",
+    UnexpectedEndTagAfterBodyInnerhtml => "unexpected-end-tag-after-body-innerhtml",
+    UnexpectedEndTagAfterFrameset => "unexpected-end-tag-after-frameset",
+    UnexpectedEndTagBeforeHtml => "unexpected-end-tag-before-html",
+    UnexpectedEndTagImpliesTableVoodoo => "unexpected-end-tag-implies-table-voodoo",
+    UnexpectedEndTagInFrameset => "unexpected-end-tag-in-frameset",
+    UnexpectedEndTagInSelect => "unexpected-end-tag-in-select",
+    UnexpectedEndTagInTableBody => "unexpected-end-tag-in-table-body",
+    UnexpectedEndTagInTableRow => "unexpected-end-tag-in-table-row",
+    UnexpectedEndTagTreatedAs => "unexpected-end-tag-treated-as",
+    UnexpectedFormInTable => "unexpected-form-in-table",
+    UnexpectedFramesetInFramesetInnerhtml => "unexpected-frameset-in-frameset-innerhtml",
+    UnexpectedHiddenInputInTable => "unexpected-hidden-input-in-table",
+    UnexpectedHtmlElementInForeignContent => "unexpected-html-element-in-foreign-content",
+    UnexpectedImpliedEndTagInTable => "unexpected-implied-end-tag-in-table",
+    UnexpectedImpliedEndTagInTableBody => "unexpected-implied-end-tag-in-table-body",
+    UnexpectedImpliedEndTagInTableRow => "unexpected-implied-end-tag-in-table-row",
+    UnexpectedInheadNoscriptTag => "unexpected-inhead-noscript-tag",
+    UnexpectedInputInSelect => "unexpected-input-in-select",
+    UnexpectedSelectInSelect => "unexpected-select-in-select",
+    UnexpectedSpaceAfterDoubleDashInComment => "unexpected-space-after-double-dash-in-comment",
+    UnexpectedStartTag => "unexpected-start-tag",
+    UnexpectedStartTagAfterBody => "unexpected-start-tag-after-body",
+    UnexpectedStartTagAfterFrameset => "unexpected-start-tag-after-frameset",
+    UnexpectedStartTagIgnored => "unexpected-start-tag-ignored",
+    UnexpectedStartTagImpliesEndTag => "unexpected-start-tag-implies-end-tag",
+    UnexpectedStartTagImpliesTableVoodoo => "unexpected-start-tag-implies-table-voodoo",
+    UnexpectedStartTagInFrameset => "unexpected-start-tag-in-frameset",
+    UnexpectedStartTagInSelect => "unexpected-start-tag-in-select",
+    UnexpectedStartTagOutOfMyHead => "unexpected-start-tag-out-of-my-head",
+    UnexpectedStartTagTreatedAs => "unexpected-start-tag-treated-as",
+    UnexpectedTableElementEndTagInSelectInTable => "unexpected-table-element-end-tag-in-select-in-table",
+    UnexpectedTableElementStartTagInSelectInTable => "unexpected-table-element-start-tag-in-select-in-table",
+    UnknownDoctype => "unknown-doctype",
+    // Tree-construction errors for steps html5lib does not name.
+    EofInTemplate => "eof-in-template",
+    UnexpectedTokenInColumnGroup => "unexpected-token-in-column-group",
+    // Input truncated by `Options::limits`.
+    AttributeTooLong => "attribute-too-long",
+    InputTooLarge => "input-too-large",
+    TextTooLong => "text-too-long",
+    TooManyAttributes => "too-many-attributes",
+    TooManyNodes => "too-many-nodes",
+    TreeTooDeep => "tree-too-deep",
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownErrorCode(pub String);
+
+impl fmt::Display for UnknownErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown parse error code `{}`", self.0)
+    }
+}
+
+impl std::error::Error for UnknownErrorCode {}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub code: ErrorCode,
+    pub location: Location,
+    pub span: Span,
+    // A related region, e.g. the start tag of the element left unclosed.
+    pub secondary: Option<Span>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.location.line, self.location.col, self.code)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Which of the `Limits` a parse ran into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Limit {
+    Depth,
+    Nodes,
+    Attributes,
+    AttributeLength,
+    TextLength,
+    InputSize,
+}
+
+impl Limit {
+    // The error reported when the parse truncates instead of aborting.
+    pub fn error_code(self) -> ErrorCode {
+        match self {
+            Limit::Depth => ErrorCode::TreeTooDeep,
+            Limit::Nodes => ErrorCode::TooManyNodes,
+            Limit::Attributes => ErrorCode::TooManyAttributes,
+            Limit::AttributeLength => ErrorCode::AttributeTooLong,
+            Limit::TextLength => ErrorCode::TextTooLong,
+            Limit::InputSize => ErrorCode::InputTooLarge,
+        }
+    }
+}
+
+// Why a parse with `LimitAction::Abort` stopped. `location` and `span` point
+// at the token that went over the limit, or at the end of the accepted input
+// for `Limit::InputSize`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LimitExceeded {
+    pub limit: Limit,
+    pub location: Location,
+    pub span: Span,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: parse aborted: {}",
+            self.location.line,
+            self.location.col,
+            self.limit.error_code()
+        )
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
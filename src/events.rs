@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+
+use crate::dom::{Attr, Doctype, Namespace, NodeId, QualName, QuirksMode};
+use crate::sink::TreeSink;
+
+// A document-order view of the tree the parser builds. Start and end events
+// nest, and together describe the same tree `parse_document` would return;
+// template contents appear as the template's children.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    Doctype(Doctype),
+    StartElement { name: QualName, attrs: Vec<Attr> },
+    EndElement(QualName),
+    Text(String),
+    Comment(String),
+    // A later `<html>` or `<body>` tag added attributes to an element that has
+    // already been started. Only ever reported for those two elements.
+    ExtraAttributes { name: QualName, attrs: Vec<Attr> },
+}
+
+#[derive(Debug)]
+enum Kind {
+    Document,
+    Element { name: QualName, attrs: Vec<Attr> },
+    Text(String),
+    Comment(String),
+    Doctype(Doctype),
+}
+
+#[derive(Debug)]
+struct EventNode {
+    kind: Kind,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    started: bool,
+}
+
+// A `TreeSink` that hands events to a callback as soon as the parser can no
+// longer change the nodes involved, and then forgets them. Only the open
+// elements and their unsettled children stay in memory; input that keeps
+// formatting elements or tables open holds back everything inside them.
+pub struct EventSink<F: FnMut(Event)> {
+    on_event: F,
+    nodes: HashMap<NodeId, EventNode>,
+    next_id: NodeId,
+    quirks_mode: QuirksMode,
+}
+
+impl<F: FnMut(Event)> EventSink<F> {
+    pub fn new(on_event: F) -> Self {
+        let mut nodes = HashMap::new();
+        nodes.insert(
+            0,
+            EventNode {
+                kind: Kind::Document,
+                parent: None,
+                children: Vec::new(),
+                started: true,
+            },
+        );
+        Self {
+            on_event,
+            nodes,
+            next_id: 1,
+            quirks_mode: QuirksMode::NoQuirks,
+        }
+    }
+
+    pub fn quirks_mode(&self) -> QuirksMode {
+        self.quirks_mode
+    }
+
+    // Nodes currently held, including the document.
+    pub fn live_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn create(&mut self, kind: Kind) -> NodeId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.nodes.insert(
+            id,
+            EventNode {
+                kind,
+                parent: None,
+                children: Vec::new(),
+                started: false,
+            },
+        );
+        id
+    }
+
+    fn children(&self, node: NodeId) -> &[NodeId] {
+        self.nodes.get(&node).map_or(&[], |n| &n.children)
+    }
+
+    fn preceding_sibling(&self, parent: NodeId, reference: Option<NodeId>) -> Option<NodeId> {
+        let children = self.children(parent);
+        match reference {
+            Some(r) => children
+                .iter()
+                .position(|&c| c == r)
+                .and_then(|i| i.checked_sub(1))
+                .map(|i| children[i]),
+            None => children.last().copied(),
+        }
+    }
+
+    fn is_html_element(&self, node: NodeId, local: &str) -> bool {
+        self.element_name(node).is_some_and(|n| n.ns == Namespace::Html && n.local == local)
+    }
+
+    fn start(&mut self, node: NodeId) {
+        let Some(n) = self.nodes.get_mut(&node) else {
+            return;
+        };
+        if n.started {
+            return;
+        }
+        n.started = true;
+        if let Kind::Element { name, attrs } = &n.kind {
+            (self.on_event)(Event::StartElement {
+                name: name.clone(),
+                attrs: attrs.clone(),
+            });
+        }
+    }
+
+    // Reports `node` and everything below it that has not been reported yet,
+    // then drops them.
+    fn flush(&mut self, node: NodeId) {
+        let mut stack = vec![(node, false)];
+        while let Some((id, done)) = stack.pop() {
+            if done {
+                if let Some(EventNode {
+                    kind: Kind::Element { name, .. },
+                    ..
+                }) = self.nodes.remove(&id)
+                {
+                    (self.on_event)(Event::EndElement(name));
+                }
+                continue;
+            }
+            let Some(n) = self.nodes.get_mut(&id) else {
+                continue;
+            };
+            if let Kind::Element { .. } = n.kind {
+                let children = std::mem::take(&mut n.children);
+                self.start(id);
+                stack.push((id, true));
+                stack.extend(children.into_iter().rev().map(|c| (c, false)));
+                continue;
+            }
+            let event = match self.nodes.remove(&id).map(|n| n.kind) {
+                Some(Kind::Text(text)) => Event::Text(text),
+                Some(Kind::Comment(data)) => Event::Comment(data),
+                Some(Kind::Doctype(doctype)) => Event::Doctype(doctype),
+                _ => continue,
+            };
+            (self.on_event)(event);
+        }
+    }
+
+    // Flushes the first `count` children of `parent`.
+    fn flush_children(&mut self, parent: NodeId, count: usize) {
+        let Some(n) = self.nodes.get_mut(&parent) else {
+            return;
+        };
+        let settled: Vec<NodeId> = n.children.drain(..count).collect();
+        for child in settled {
+            self.flush(child);
+        }
+    }
+}
+
+impl<F: FnMut(Event)> TreeSink for EventSink<F> {
+    fn document(&self) -> NodeId {
+        0
+    }
+
+    fn element_name(&self, node: NodeId) -> Option<&QualName> {
+        match &self.nodes.get(&node)?.kind {
+            Kind::Element { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+
+    fn element_attrs(&self, element: NodeId) -> &[Attr] {
+        match self.nodes.get(&element).map(|n| &n.kind) {
+            Some(Kind::Element { attrs, .. }) => attrs,
+            _ => &[],
+        }
+    }
+
+    fn parent(&self, node: NodeId) -> Option<NodeId> {
+        self.nodes.get(&node)?.parent
+    }
+
+    fn create_element(&mut self, name: QualName, attrs: Vec<Attr>) -> NodeId {
+        self.create(Kind::Element { name, attrs })
+    }
+
+    fn create_comment(&mut self, data: String) -> NodeId {
+        self.create(Kind::Comment(data))
+    }
+
+    fn create_doctype(&mut self, doctype: Doctype) -> NodeId {
+        self.create(Kind::Doctype(doctype))
+    }
+
+    fn append(&mut self, parent: NodeId, child: NodeId) {
+        self.insert_before(parent, child, None);
+    }
+
+    fn insert_before(&mut self, parent: NodeId, child: NodeId, reference: Option<NodeId>) {
+        self.remove_from_parent(child);
+        let Some(n) = self.nodes.get_mut(&parent) else {
+            return;
+        };
+        let index = reference
+            .and_then(|r| n.children.iter().position(|&c| c == r))
+            .unwrap_or(n.children.len());
+        n.children.insert(index, child);
+        if let Some(c) = self.nodes.get_mut(&child) {
+            c.parent = Some(parent);
+        }
+    }
+
+    fn insert_text(&mut self, parent: NodeId, reference: Option<NodeId>, text: &str) -> NodeId {
+        if let Some(prev) = self.preceding_sibling(parent, reference)
+            && let Some(EventNode {
+                kind: Kind::Text(existing),
+                ..
+            }) = self.nodes.get_mut(&prev)
+        {
+            existing.push_str(text);
+            return prev;
+        }
+        let id = self.create(Kind::Text(text.to_string()));
+        self.insert_before(parent, id, reference);
+        id
+    }
+
+    fn preceding_text(&self, parent: NodeId, reference: Option<NodeId>) -> Option<&str> {
+        match &self.nodes.get(&self.preceding_sibling(parent, reference)?)?.kind {
+            Kind::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    fn remove_from_parent(&mut self, node: NodeId) {
+        let Some(parent) = self.nodes.get_mut(&node).and_then(|n| n.parent.take()) else {
+            return;
+        };
+        if let Some(p) = self.nodes.get_mut(&parent) {
+            p.children.retain(|&c| c != node);
+        }
+    }
+
+    fn reparent_children(&mut self, from: NodeId, to: NodeId) {
+        let children = match self.nodes.get_mut(&from) {
+            Some(n) => std::mem::take(&mut n.children),
+            None => return,
+        };
+        for child in children {
+            if let Some(c) = self.nodes.get_mut(&child) {
+                c.parent = None;
+            }
+            self.append(to, child);
+        }
+    }
+
+    fn template_contents(&mut self, template: NodeId) -> NodeId {
+        template
+    }
+
+    fn add_attrs_if_missing(&mut self, element: NodeId, attrs: Vec<Attr>) {
+        let Some(EventNode {
+            kind: Kind::Element { name, attrs: existing },
+            started,
+            ..
+        }) = self.nodes.get_mut(&element)
+        else {
+            return;
+        };
+        let added: Vec<Attr> = attrs
+            .into_iter()
+            .filter(|attr| !existing.iter().any(|a| a.name == attr.name))
+            .collect();
+        existing.extend(added.iter().cloned());
+        if *started && !added.is_empty() {
+            (self.on_event)(Event::ExtraAttributes {
+                name: name.clone(),
+                attrs: added,
+            });
+        }
+    }
+
+    fn set_quirks_mode(&mut self, mode: QuirksMode) {
+        self.quirks_mode = mode;
+    }
+
+    fn checkpoint(&mut self, stable: &[NodeId]) {
+        let mut parent = 0;
+        for &next in stable {
+            if self.parent(next) != Some(parent) {
+                return;
+            }
+            let index = self.children(parent).iter().position(|&c| c == next).unwrap_or(0);
+            self.flush_children(parent, index);
+            self.start(next);
+            parent = next;
+        }
+        let children = self.children(parent);
+        let keep = match children.last() {
+            Some(&last) if self.is_html_element(last, "table") => 2,
+            _ => 1,
+        };
+        self.flush_children(parent, children.len().saturating_sub(keep));
+    }
+
+    fn end(&mut self) {
+        self.flush_children(0, self.children(0).len());
+    }
+}
@@ -383,11 +383,24 @@ pub fn parse_tree_construction_dat(path: &Path) -> io::Result<Vec<TreeConstructi
 
         let mut data_lines = Vec::new();
         while let Some(&next) = lines.peek() {
-            if next == "#errors" {
+            if matches!(next, "#errors" | "#script-on" | "#script-off") {
                 break;
             }
             data_lines.push(lines.next().unwrap());
         }
+        // Older fixture files put the scripting directive before `#errors`.
+        let mut script_directive = ScriptDirective::Both;
+        match lines.peek().copied() {
+            Some("#script-on") => {
+                script_directive = ScriptDirective::On;
+                lines.next();
+            }
+            Some("#script-off") => {
+                script_directive = ScriptDirective::Off;
+                lines.next();
+            }
+            _ => {}
+        }
         if lines.next() != Some("#errors") {
             continue;
         }
@@ -428,7 +441,6 @@ pub fn parse_tree_construction_dat(path: &Path) -> io::Result<Vec<TreeConstructi
             fragment_context = Some(parse_fragment_context_line(ctx_line));
         }
 
-        let mut script_directive = ScriptDirective::Both;
         if let Some(&next) = lines.peek() {
             if next == "#script-on" {
                 script_directive = ScriptDirective::On;
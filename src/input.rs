@@ -0,0 +1,180 @@
+use crate::{ErrorCode, Location, Span};
+
+pub(crate) fn is_noncharacter(code: u32) -> bool {
+    (0xFDD0..=0xFDEF).contains(&code) || ((code & 0xFFFE) == 0xFFFE && code <= 0x10FFFF)
+}
+
+pub(crate) fn is_control(code: u32) -> bool {
+    code <= 0x1F || (0x7F..=0x9F).contains(&code)
+}
+
+pub(crate) fn is_surrogate(code: u32) -> bool {
+    (0xD800..=0xDFFF).contains(&code)
+}
+
+// The largest char boundary of `s` that is at most `index`.
+pub(crate) fn floor_char_boundary(s: &str, index: usize) -> usize {
+    if index >= s.len() {
+        return s.len();
+    }
+    (0..=index).rev().find(|&i| s.is_char_boundary(i)).unwrap_or(0)
+}
+
+// A `&str` can never hold a surrogate, so only control characters and
+// noncharacters are reported here. The UTF-16 decoders turn lone surrogates
+// into U+FFFD without an error, as the Encoding Standard does, so
+// `surrogate-in-input-stream` is never reported.
+fn preprocessing_error(c: char) -> Option<ErrorCode> {
+    let code = c as u32;
+    if is_noncharacter(code) {
+        Some(ErrorCode::NoncharacterInInputStream)
+    } else if is_control(code) && !matches!(c, '\t' | '\n' | '\x0C' | '\r' | ' ' | '\0') {
+        Some(ErrorCode::ControlCharacterInInputStream)
+    } else {
+        None
+    }
+}
+
+// Offsets are into the whole input, but once more input is pushed the text
+// before the previous character is dropped: `buf` starts at `base`.
+#[derive(Clone, Debug)]
+pub struct InputStream {
+    buf: String,
+    base: usize,
+    pos: usize,
+    prev_pos: usize,
+    line: u32,
+    col: u32,
+    col_utf16: u32,
+    prev_line: u32,
+    prev_col: u32,
+    prev_col_utf16: u32,
+    checked_up_to: usize,
+    pending_error: Option<ErrorCode>,
+}
+
+impl InputStream {
+    pub fn new(input: &str) -> Self {
+        Self {
+            buf: input.to_string(),
+            base: 0,
+            pos: 0,
+            prev_pos: 0,
+            line: 1,
+            col: 0,
+            col_utf16: 0,
+            prev_line: 1,
+            prev_col: 0,
+            prev_col_utf16: 0,
+            checked_up_to: 0,
+            pending_error: None,
+        }
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        self.buf.drain(..self.prev_pos - self.base);
+        self.base = self.prev_pos;
+        self.buf.push_str(s);
+    }
+
+    fn tail(&self) -> &str {
+        &self.buf[self.pos - self.base..]
+    }
+
+    pub fn next_char(&mut self) -> Option<char> {
+        self.prev_pos = self.pos;
+        self.prev_line = self.line;
+        self.prev_col = self.col;
+        self.prev_col_utf16 = self.col_utf16;
+        let c = self.tail().chars().next()?;
+        self.pos += c.len_utf8();
+        let c = if c == '\r' {
+            if self.tail().starts_with('\n') {
+                self.pos += 1;
+            }
+            '\n'
+        } else {
+            c
+        };
+        if c == '\n' {
+            self.line += 1;
+            self.col = 0;
+            self.col_utf16 = 0;
+        } else {
+            self.col += 1;
+            self.col_utf16 += c.len_utf16() as u32;
+        }
+        if self.pos > self.checked_up_to {
+            self.checked_up_to = self.pos;
+            self.pending_error = preprocessing_error(c);
+        }
+        Some(c)
+    }
+
+    pub fn back(&mut self) {
+        self.pos = self.prev_pos;
+        self.line = self.prev_line;
+        self.col = self.prev_col;
+        self.col_utf16 = self.prev_col_utf16;
+    }
+
+    pub fn take_error(&mut self) -> Option<ErrorCode> {
+        self.pending_error.take()
+    }
+
+    pub fn location(&self) -> Location {
+        Location {
+            line: self.line,
+            col: self.col,
+            col_utf16: self.col_utf16,
+            offset: self.pos,
+        }
+    }
+
+    // Where a character appended to the input would start.
+    pub fn end_location(&self) -> Location {
+        let rest = Location::at(self.tail(), self.tail().len());
+        let (col, col_utf16) = match rest.line {
+            1 => (self.col + rest.col, self.col_utf16 + rest.col_utf16),
+            _ => (rest.col, rest.col_utf16),
+        };
+        Location {
+            line: self.line + rest.line - 1,
+            col,
+            col_utf16,
+            offset: self.base + self.buf.len(),
+        }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.pos
+    }
+
+    // The bytes of the most recently consumed character (both bytes of a
+    // CRLF pair), or an empty span after `back()` or at end of input.
+    pub fn last_char_span(&self) -> Span {
+        Span::new(self.prev_pos, self.pos)
+    }
+
+    pub fn starts_with(&self, s: &str, ascii_case_insensitive: bool) -> bool {
+        let Some(rest) = self.tail().get(..s.len()) else {
+            return false;
+        };
+        if ascii_case_insensitive {
+            rest.eq_ignore_ascii_case(s)
+        } else {
+            rest == s
+        }
+    }
+
+    pub fn rest(&self) -> &str {
+        self.tail()
+    }
+
+    pub fn skip(&mut self, n: usize) {
+        self.pos += n;
+        self.col += n as u32;
+        self.col_utf16 += n as u32;
+        self.checked_up_to = self.checked_up_to.max(self.pos);
+    }
+}
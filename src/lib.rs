@@ -1,45 +1,125 @@
 pub mod dom;
+pub mod encoding;
+pub mod entities;
+pub mod events;
+mod error;
 pub mod html5lib;
+pub mod input;
 pub mod serialize;
+pub mod sink;
+pub mod tokenizer;
+mod treebuilder;
 
-#[derive(Clone, Debug)]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+pub use encoding::{Confidence, Encoding, EncodingChange, EncodingHint};
+pub use entities::decode_character_references;
+pub use events::{Event, EventSink};
+pub use error::{AttributeSpan, ErrorCode, Limit, LimitExceeded, Location, ParseError, Span, UnknownErrorCode};
+pub use sink::TreeSink;
+
+#[derive(Clone, Debug, Default)]
 pub struct Options {
     pub scripting_enabled: bool,
     pub iframe_srcdoc: bool,
     pub collect_errors: bool,
+    // Record where each node came from in `Document::sources`, or report it
+    // through `TreeSink::record_source` for other sinks.
+    pub source_spans: bool,
+    // Keep the input and the tree as parsed in `Document::original`, for
+    // `serialize::to_html_preserving`. Implies `source_spans`. Only
+    // `parse_document` and `parse_document_bytes` keep them.
+    pub preserve_source: bool,
+    // Turn `<template shadowrootmode>` into a shadow root on its parent, as
+    // browsers do, rather than a template element.
+    pub declarative_shadow_roots: bool,
+    // Parse `<select>` with the current rules, which allow rich content in
+    // it, and copy the selected option into `<selectedcontent>`. Off, select
+    // parsing follows the rules from before that change. Only sinks that
+    // implement `TreeSink::clone_option_contents` make the copy: `Document`
+    // does, while `EventSink` has already streamed `<selectedcontent>` out by
+    // the time an option closes and leaves it empty.
+    pub customizable_select: bool,
+    pub limits: Limits,
+    pub budget: Budget,
+}
+
+// Bounds for parsing untrusted input; `None` means unlimited. Lengths are in
+// bytes of UTF-8. Depth counts open elements, so `<html>` is at depth 1.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits {
+    pub max_depth: Option<usize>,
+    pub max_nodes: Option<usize>,
+    pub max_attributes: Option<usize>,
+    pub max_attribute_length: Option<usize>,
+    // Applies to text nodes (after merging adjacent text) and comments.
+    pub max_text_length: Option<usize>,
+    pub max_input_bytes: Option<usize>,
+    pub on_exceeded: LimitAction,
 }
 
-impl Default for Options {
-    fn default() -> Self {
-        Self {
-            scripting_enabled: false,
-            iframe_srcdoc: false,
-            collect_errors: false,
+// Stops a parse early, leaving the tree as end of file at that point would.
+// Both are checked between tokens and every `BUDGET_CHECK_INTERVAL`
+// tokenizer steps within long tokens.
+#[derive(Clone, Debug, Default)]
+pub struct Budget {
+    // Tokenizer steps, roughly one per input character.
+    pub max_steps: Option<u64>,
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+const BUDGET_CHECK_INTERVAL: u64 = 4096;
+
+impl Budget {
+    fn interruption(&self, steps: u64) -> Option<Interruption> {
+        if self.cancel.as_ref().is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+            Some(Interruption::Cancelled)
+        } else if self.max_steps.is_some_and(|max| steps >= max) {
+            Some(Interruption::OutOfSteps)
+        } else {
+            None
         }
     }
-}
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct Location {
-    pub line: u32,
-    pub col: u32,
+    // The step count at which the tokenizer should pause for the next check.
+    fn next_check(&self, steps: u64) -> Option<u64> {
+        if self.cancel.is_none() && self.max_steps.is_none() {
+            return None;
+        }
+        let next = steps + BUDGET_CHECK_INTERVAL;
+        Some(self.max_steps.map_or(next, |max| next.min(max)))
+    }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub enum ErrorCode {
-    Code(String),
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interruption {
+    Cancelled,
+    OutOfSteps,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct ParseError {
-    pub code: ErrorCode,
-    pub location: Location,
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LimitAction {
+    // Drop whatever goes over the limit, report it with the limit's
+    // `ErrorCode`, and keep parsing.
+    #[default]
+    Truncate,
+    // Stop at the first token that goes over a limit and set
+    // `Parsed::aborted`.
+    Abort,
 }
 
 #[derive(Clone, Debug)]
 pub struct Parsed<T> {
     pub value: T,
     pub errors: Vec<ParseError>,
+    pub encoding: Option<Encoding>,
+    pub confidence: Confidence,
+    pub encoding_change: Option<EncodingChange>,
+    // Set when a limit stopped the parse; `value` holds what was built so far.
+    pub aborted: Option<LimitExceeded>,
+    // Set when `Options::budget` cut the parse short.
+    pub interrupted: Option<Interruption>,
 }
 
 #[derive(Clone, Debug)]
@@ -48,6 +128,133 @@ pub struct FragmentContext {
     pub tag_name: String,
 }
 
+fn drain_errors<S: TreeSink>(
+    opts: &Options,
+    tokenizer: &mut tokenizer::Tokenizer,
+    builder: &mut treebuilder::TreeBuilder<S>,
+    errors: &mut Vec<ParseError>,
+) {
+    let mut new_errors = tokenizer.take_errors();
+    new_errors.extend(builder.take_errors());
+    if opts.collect_errors {
+        for error in new_errors {
+            builder.sink.parse_error(&error);
+            errors.push(error);
+        }
+    }
+}
+
+fn run<S: TreeSink>(
+    opts: &Options,
+    tokenizer: &mut tokenizer::Tokenizer,
+    builder: &mut treebuilder::TreeBuilder<S>,
+    errors: &mut Vec<ParseError>,
+) {
+    if builder.interrupted().is_some() {
+        return;
+    }
+    if let Some(state) = builder.take_tokenizer_state() {
+        tokenizer.set_state(state);
+    }
+    tokenizer.set_cdata_allowed(builder.cdata_allowed());
+    loop {
+        if let Some(reason) = opts.budget.interruption(tokenizer.steps()) {
+            tokenizer.set_step_limit(Some(tokenizer.steps()));
+            tokenizer.flush_pending_text();
+            while let Some(token) = tokenizer.next() {
+                builder.set_position(tokenizer.location(), tokenizer.token_span(), tokenizer.attribute_spans());
+                builder.process_token(token);
+            }
+            let location = tokenizer.location();
+            let offset = location.offset;
+            builder.set_position(location, Span::new(offset, offset), &[]);
+            builder.interrupt(reason);
+            drain_errors(opts, tokenizer, builder, errors);
+            builder.sink.end();
+            break;
+        }
+        let step_limit = opts.budget.next_check(tokenizer.steps());
+        tokenizer.set_step_limit(step_limit);
+        let Some(token) = tokenizer.next() else {
+            if step_limit.is_some_and(|limit| tokenizer.steps() >= limit) {
+                continue;
+            }
+            break;
+        };
+        let eof = token == tokenizer::Token::Eof;
+        builder.set_position(tokenizer.location(), tokenizer.token_span(), tokenizer.attribute_spans());
+        builder.process_token(token);
+        if let Some(state) = builder.take_tokenizer_state() {
+            tokenizer.set_state(state);
+        }
+        tokenizer.set_cdata_allowed(builder.cdata_allowed());
+        drain_errors(opts, tokenizer, builder, errors);
+        if builder.encoding_change().is_some() {
+            break;
+        }
+        if builder.aborted().is_some() {
+            builder.sink.end();
+            break;
+        }
+        if eof {
+            builder.sink.end();
+        } else {
+            builder.checkpoint();
+        }
+    }
+}
+
+fn report_limit<S: TreeSink>(
+    opts: &Options,
+    builder: &mut treebuilder::TreeBuilder<S>,
+    errors: &mut Vec<ParseError>,
+    exceeded: LimitExceeded,
+) {
+    if opts.collect_errors {
+        let error = ParseError {
+            code: exceeded.limit.error_code(),
+            location: exceeded.location,
+            span: exceeded.span,
+            secondary: None,
+        };
+        builder.sink.parse_error(&error);
+        errors.push(error);
+    }
+}
+
+// Parses all of `input`, or the part of it `Limits::max_input_bytes` allows.
+fn run_to_end<S: TreeSink>(
+    opts: &Options,
+    input: &str,
+    tokenizer_opts: tokenizer::TokenizerOpts,
+    builder: &mut treebuilder::TreeBuilder<S>,
+    errors: &mut Vec<ParseError>,
+) -> Option<LimitExceeded> {
+    let mut rejected = None;
+    let mut input = input;
+    if let Some(max) = opts.limits.max_input_bytes
+        && input.len() > max
+    {
+        let cut = input::floor_char_boundary(input, max);
+        rejected = Some(LimitExceeded {
+            limit: Limit::InputSize,
+            location: Location::at(input, cut),
+            span: Span::new(cut, input.len()),
+        });
+        input = &input[..cut];
+        if opts.limits.on_exceeded == LimitAction::Abort {
+            builder.sink.end();
+            return rejected;
+        }
+    }
+    let mut tokenizer = tokenizer::Tokenizer::with_opts(input, tokenizer_opts);
+    run(opts, &mut tokenizer, builder, errors);
+    if let Some(rejected) = rejected {
+        report_limit(opts, builder, errors, rejected);
+    }
+    builder.aborted().cloned()
+}
+
 pub struct Parser {
     opts: Options,
 }
@@ -57,33 +264,262 @@ impl Parser {
         Self { opts }
     }
 
-    pub fn parse_document(&mut self, _input: &str) -> Parsed<dom::Document> {
-        let doc = dom::Document::new_empty();
+    fn parse_decoded<S: TreeSink>(
+        &self,
+        sink: S,
+        input: &str,
+        encoding: Option<Encoding>,
+        confidence: Confidence,
+    ) -> Parsed<S> {
+        let mut builder = treebuilder::TreeBuilder::new(self.opts.clone(), sink);
+        if let Some(encoding) = encoding {
+            builder.set_encoding(encoding, confidence);
+        }
+        let mut errors = Vec::new();
+        let aborted = run_to_end(&self.opts, input, Default::default(), &mut builder, &mut errors);
         Parsed {
-            value: doc,
-            errors: if self.opts.collect_errors {
-                vec![ParseError {
-                    code: ErrorCode::Code("unimplemented".to_string()),
-                    location: Location { line: 1, col: 1 },
-                }]
-            } else {
-                Vec::new()
-            },
+            interrupted: builder.interrupted(),
+            encoding_change: builder.encoding_change().zip(encoding).map(|(to, from)| EncodingChange { from, to }),
+            value: builder.sink,
+            errors,
+            encoding,
+            confidence,
+            aborted,
+        }
+    }
+
+    pub fn parse_document(&mut self, input: &str) -> Parsed<dom::Document> {
+        let mut parsed = self.parse_document_into(dom::Document::new_empty(), input);
+        self.keep_original(&mut parsed.value, input);
+        parsed
+    }
+
+    fn keep_original(&self, doc: &mut dom::Document, input: &str) {
+        if self.opts.preserve_source {
+            doc.original = Some(dom::Original {
+                text: input.to_string(),
+                nodes: doc.arena.clone(),
+            });
         }
     }
 
-    pub fn parse_fragment(&mut self, _ctx: FragmentContext, _input: &str) -> Parsed<dom::DocumentFragment> {
-        let frag = dom::DocumentFragment::new_empty();
+    pub fn parse_document_into<S: TreeSink>(&mut self, sink: S, input: &str) -> Parsed<S> {
+        self.parse_decoded(sink, input, None, Confidence::Irrelevant)
+    }
+
+    pub fn begin_document(&mut self) -> DocumentParser {
+        self.begin_document_into(dom::Document::new_empty())
+    }
+
+    // Bytes fed to it are sniffed as `parse_document_bytes` would with a
+    // UTF-8 fallback.
+    pub fn begin_document_into<S: TreeSink>(&mut self, sink: S) -> DocumentParser<S> {
+        let hint = EncodingHint {
+            transport: None,
+            fallback: Some(Encoding::Utf8),
+        };
+        self.begin_document_bytes_into(sink, hint)
+    }
+
+    pub fn begin_document_bytes(&mut self, hint: EncodingHint) -> DocumentParser {
+        self.begin_document_bytes_into(dom::Document::new_empty(), hint)
+    }
+
+    // A `<meta>` past the first 1024 bytes cannot restart a stream, so unlike
+    // `parse_document_bytes` it never changes the sniffed encoding.
+    pub fn begin_document_bytes_into<S: TreeSink>(&mut self, sink: S, hint: EncodingHint) -> DocumentParser<S> {
+        DocumentParser {
+            opts: self.opts.clone(),
+            tokenizer: tokenizer::Tokenizer::new_streaming(),
+            builder: treebuilder::TreeBuilder::new(self.opts.clone(), sink),
+            errors: Vec::new(),
+            sniffing: Some((hint, Vec::new())),
+            decoder: encoding::StreamDecoder::new(Encoding::Utf8),
+            encoding: None,
+            received: 0,
+            rejected: None,
+        }
+    }
+
+    pub fn parse_document_bytes(&mut self, input: &[u8], hint: EncodingHint) -> Parsed<dom::Document> {
+        let (text, encoding, confidence) = encoding::decode(input, &hint);
+        let mut parsed = self.parse_decoded(dom::Document::new_empty(), &text, Some(encoding), confidence);
+        let Some(change) = parsed.encoding_change else {
+            self.keep_original(&mut parsed.value, &text);
+            return parsed;
+        };
+        // Only a tentative guess can be overturned, and those never come from
+        // a BOM, so the whole input is decoded again.
+        let text = change.to.decode(input);
+        let mut parsed = Parsed {
+            encoding_change: Some(change),
+            ..self.parse_decoded(dom::Document::new_empty(), &text, Some(change.to), Confidence::Certain)
+        };
+        self.keep_original(&mut parsed.value, &text);
+        parsed
+    }
+
+    pub fn parse_fragment(&mut self, ctx: FragmentContext, input: &str) -> Parsed<dom::DocumentFragment> {
+        let ns = match ctx.namespace.as_deref() {
+            None | Some("html") | Some("http://www.w3.org/1999/xhtml") => dom::Namespace::Html,
+            Some("svg") | Some("http://www.w3.org/2000/svg") => dom::Namespace::Svg,
+            Some("math") | Some("http://www.w3.org/1998/Math/MathML") => dom::Namespace::MathMl,
+            Some(other) => dom::Namespace::Other(other.to_string()),
+        };
+        let tokenizer_opts = tokenizer::TokenizerOpts {
+            last_start_tag: (ns == dom::Namespace::Html).then(|| ctx.tag_name.clone()),
+            ..Default::default()
+        };
+        let mut builder =
+            treebuilder::TreeBuilder::new_fragment(self.opts.clone(), dom::Document::new_empty(), ns, &ctx.tag_name);
+        let mut errors = Vec::new();
+        let aborted = run_to_end(&self.opts, input, tokenizer_opts, &mut builder, &mut errors);
+        Parsed {
+            interrupted: builder.interrupted(),
+            value: builder.into_fragment(),
+            errors,
+            encoding: None,
+            confidence: Confidence::Irrelevant,
+            encoding_change: None,
+            aborted,
+        }
+    }
+}
+
+// Push-based document parsing. Chunks may split tokens, character references
+// and CRLF pairs anywhere; the result is identical to `parse_document` on the
+// concatenated input.
+pub struct DocumentParser<S = dom::Document> {
+    opts: Options,
+    tokenizer: tokenizer::Tokenizer,
+    builder: treebuilder::TreeBuilder<S>,
+    errors: Vec<ParseError>,
+    // Bytes held back until there are enough for the `<meta>` prescan, with
+    // the hint to sniff them with. Taken once the encoding is settled.
+    sniffing: Option<(EncodingHint, Vec<u8>)>,
+    decoder: encoding::StreamDecoder,
+    encoding: Option<(Encoding, Confidence)>,
+    received: usize,
+    // Set once input beyond `Limits::max_input_bytes` has been cut off;
+    // everything fed afterwards is ignored.
+    rejected: Option<LimitExceeded>,
+}
+
+impl<S: TreeSink> DocumentParser<S> {
+    // Settles the encoding with the bytes held back so far, and ends a
+    // sequence `feed_bytes` left cut off, which text cannot complete, so that
+    // it comes out ahead of `chunk` as U+FFFD.
+    pub fn feed(&mut self, chunk: &str) {
+        self.settle_encoding();
+        let rest = self.decoder.decode(&[], true);
+        if !rest.is_empty() {
+            self.feed_text(&rest);
+        }
+        self.feed_text(chunk);
+    }
+
+    fn feed_text(&mut self, chunk: &str) {
+        if self.rejected.is_some() || self.builder.aborted().is_some() || self.builder.interrupted().is_some() {
+            return;
+        }
+        let accepted = match self.opts.limits.max_input_bytes {
+            Some(max) => input::floor_char_boundary(chunk, max.saturating_sub(self.received)),
+            None => chunk.len(),
+        };
+        self.tokenizer.feed(&chunk[..accepted]);
+        self.received += accepted;
+        if accepted < chunk.len() {
+            self.rejected = Some(LimitExceeded {
+                limit: Limit::InputSize,
+                location: self.tokenizer.end_location(),
+                span: Span::new(self.received, self.received + chunk.len() - accepted),
+            });
+            if self.opts.limits.on_exceeded == LimitAction::Abort {
+                self.builder.sink.end();
+                return;
+            }
+        }
+        run(&self.opts, &mut self.tokenizer, &mut self.builder, &mut self.errors);
+    }
+
+    // Feeds bytes in the encoding sniffed from the first 1024 of them, which
+    // are held back until they have all arrived. A sequence cut off at the end
+    // of the chunk waits for the next call; invalid ones become U+FFFD. Once
+    // `feed` has been called, bytes are taken in the encoding sniffed from
+    // those held back before it, or as UTF-8 if there were none.
+    pub fn feed_bytes(&mut self, chunk: &[u8]) {
+        if let Some((_, buffered)) = &mut self.sniffing {
+            buffered.extend_from_slice(chunk);
+            if buffered.len() >= encoding::PRESCAN_LIMIT {
+                self.settle_encoding();
+            }
+            return;
+        }
+        let text = self.decoder.decode(chunk, false);
+        self.feed_text(&text);
+    }
+
+    // Runs encoding sniffing on the bytes held back so far. Text fed before
+    // any bytes leaves the encoding unset.
+    fn settle_encoding(&mut self) {
+        let Some((hint, buffered)) = self.sniffing.take() else {
+            return;
+        };
+        if buffered.is_empty() {
+            return;
+        }
+        let (encoding, confidence, bom_len) = encoding::sniff(&buffered, &hint);
+        self.encoding = Some((encoding, confidence));
+        self.decoder = encoding::StreamDecoder::new(encoding);
+        let text = self.decoder.decode(&buffered[bom_len..], false);
+        self.feed_text(&text);
+    }
+
+    // Reads bytes until end of stream, as `feed_bytes`. Call `finish`
+    // afterwards.
+    pub fn read_from<R: std::io::Read>(&mut self, mut reader: R) -> std::io::Result<()> {
+        let mut buf = [0; 8192];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => return Ok(()),
+                Ok(n) => self.feed_bytes(&buf[..n]),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.builder.sink
+    }
+
+    pub fn finish(mut self) -> Parsed<S> {
+        self.settle_encoding();
+        let rest = self.decoder.decode(&[], true);
+        if !rest.is_empty() {
+            self.feed_text(&rest);
+        }
+        let aborted = match self.rejected.take() {
+            Some(rejected) if self.opts.limits.on_exceeded == LimitAction::Abort => Some(rejected),
+            rejected => {
+                if self.builder.aborted().is_none() {
+                    self.tokenizer.end_input();
+                    run(&self.opts, &mut self.tokenizer, &mut self.builder, &mut self.errors);
+                }
+                if let Some(rejected) = rejected {
+                    report_limit(&self.opts, &mut self.builder, &mut self.errors, rejected);
+                }
+                self.builder.aborted().cloned()
+            }
+        };
         Parsed {
-            value: frag,
-            errors: if self.opts.collect_errors {
-                vec![ParseError {
-                    code: ErrorCode::Code("unimplemented".to_string()),
-                    location: Location { line: 1, col: 1 },
-                }]
-            } else {
-                Vec::new()
-            },
+            interrupted: self.builder.interrupted(),
+            value: self.builder.sink,
+            errors: self.errors,
+            encoding: self.encoding.map(|(encoding, _)| encoding),
+            confidence: self.encoding.map_or(Confidence::Irrelevant, |(_, confidence)| confidence),
+            encoding_change: None,
+            aborted,
         }
     }
 }
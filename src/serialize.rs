@@ -1,4 +1,7 @@
-use crate::dom::{Attr, Doctype, Namespace, Node, NodeData, NodeId, QualName};
+use std::collections::HashMap;
+
+use crate::dom::{Attr, Doctype, Document, Namespace, Node, NodeData, NodeId, NodeSource, Original, QualName, ShadowRoot, ShadowRootMode};
+use crate::{AttributeSpan, Span};
 
 fn namespace_prefix(ns: &Namespace) -> &'_ str {
     match ns {
@@ -63,7 +66,7 @@ fn doctype_to_test_format(doctype: &Doctype) -> String {
 fn node_to_test_lines(arena: &[Node], node_id: NodeId, indent: usize, out: &mut Vec<String>) {
     let node = &arena[node_id];
     match &node.data {
-        NodeData::Document | NodeData::DocumentFragment => {
+        NodeData::Document | NodeData::DocumentFragment | NodeData::ShadowRoot(_) => {
             for &child in &node.children {
                 node_to_test_lines(arena, child, indent, out);
             }
@@ -81,22 +84,32 @@ fn node_to_test_lines(arena: &[Node], node_id: NodeId, indent: usize, out: &mut
             name,
             attrs,
             template_contents,
+            shadow_root,
         } => {
             out.push(format!("| {}<{}>", " ".repeat(indent), qualified_name(name)));
             for (_key, display, value) in sort_attrs_for_test_output(attrs) {
                 out.push(format!("| {}{}=\"{}\"", " ".repeat(indent + 2), display, value));
             }
 
-            if is_template_html_ns(node) {
-                if let Some(contents) = *template_contents {
-                    out.push(format!("| {}content", " ".repeat(indent + 2)));
-                    for &child in &arena[contents].children {
-                        node_to_test_lines(arena, child, indent + 4, out);
-                    }
-                    return;
+            if let Some(root) = *shadow_root
+                && let NodeData::ShadowRoot(shadow) = &arena[root].data
+            {
+                out.push(format!("| {}#shadow-root ({})", " ".repeat(indent + 2), mode_name(shadow.mode)));
+                for &child in &arena[root].children {
+                    node_to_test_lines(arena, child, indent + 4, out);
                 }
             }
 
+            if is_template_html_ns(node)
+                && let Some(contents) = *template_contents
+            {
+                out.push(format!("| {}content", " ".repeat(indent + 2)));
+                for &child in &arena[contents].children {
+                    node_to_test_lines(arena, child, indent + 4, out);
+                }
+                return;
+            }
+
             for &child in &node.children {
                 node_to_test_lines(arena, child, indent + 2, out);
             }
@@ -110,6 +123,784 @@ pub fn to_test_format(arena: &[Node], root: NodeId) -> String {
     lines.join("\n")
 }
 
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "basefont", "bgsound", "br", "col", "embed", "frame", "hr", "img", "input", "keygen", "link",
+    "meta", "param", "source", "track", "wbr",
+];
+
+// Elements whose text is written out unescaped (`noscript` is escaped, as
+// it is when scripting is disabled).
+const RAW_TEXT_ELEMENTS: &[&str] = &["iframe", "noembed", "noframes", "plaintext", "script", "style", "xmp"];
+
+// Elements whose text runs until their end tag, which a copy of their source
+// may have left to the end of the input (`plaintext` has none).
+const TEXT_ELEMENTS: &[&str] = &[
+    "iframe", "noembed", "noframes", "script", "style", "textarea", "title", "xmp",
+];
+
+// Elements the tree builder inserts without a tag when the markup needs them.
+const IMPLIED_ELEMENTS: &[&str] = &["body", "colgroup", "head", "html", "tbody", "tr"];
+
+// Elements that go into the `head` when they come after it, rather than
+// implying a `body`.
+const HEAD_ELEMENTS: &[&str] = &[
+    "base", "basefont", "bgsound", "link", "meta", "noframes", "script", "style", "template", "title",
+];
+
+// Elements the tree builder keeps active after they are closed, until an
+// end tag of theirs or a marker takes them off the list.
+const FORMATTING_ELEMENTS: &[&str] = &[
+    "a", "b", "big", "code", "em", "font", "i", "nobr", "s", "small", "strike", "strong", "tt", "u",
+];
+
+fn is_html_one_of(arena: &[Node], node: Option<NodeId>, locals: &[&str]) -> bool {
+    match node.map(|id| &arena[id].data) {
+        Some(NodeData::Element { name, .. }) => name.ns == Namespace::Html && locals.contains(&name.local.as_str()),
+        _ => false,
+    }
+}
+
+fn attribute_name(name: &QualName) -> String {
+    match &name.ns {
+        Namespace::Other(prefix) if prefix == "xmlns" && name.local == "xmlns" => name.local.clone(),
+        Namespace::Other(prefix) => format!("{prefix}:{}", name.local),
+        _ => name.local.clone(),
+    }
+}
+
+fn escape(text: &str, in_attribute: bool, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '\u{a0}' => out.push_str("&nbsp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if in_attribute => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+fn push_attr(attr: &Attr, out: &mut String) {
+    out.push(' ');
+    out.push_str(&attribute_name(&attr.name));
+    out.push_str("=\"");
+    escape(&attr.value, true, out);
+    out.push('"');
+}
+
+fn push_start_tag(name: &QualName, attrs: &[Attr], out: &mut String) {
+    out.push('<');
+    out.push_str(&name.local);
+    for attr in attrs {
+        push_attr(attr, out);
+    }
+    out.push('>');
+}
+
+fn push_end_tag(local: &str, out: &mut String) {
+    out.push_str("</");
+    out.push_str(local);
+    out.push('>');
+}
+
+fn push_text(arena: &[Node], node: NodeId, text: &str, out: &mut String) {
+    if is_html_one_of(arena, arena[node].parent, RAW_TEXT_ELEMENTS) {
+        out.push_str(text);
+    } else {
+        escape(text, false, out);
+    }
+}
+
+fn push_leaf(arena: &[Node], node: NodeId, out: &mut String) {
+    match &arena[node].data {
+        NodeData::Text(text) => push_text(arena, node, text, out),
+        NodeData::Comment(data) => {
+            out.push_str("<!--");
+            out.push_str(data);
+            out.push_str("-->");
+        }
+        NodeData::Doctype(doctype) => {
+            out.push_str("<!DOCTYPE ");
+            out.push_str(&doctype.name);
+            out.push('>');
+        }
+        _ => {}
+    }
+}
+
+// The children serialized, or a template's contents in their place.
+fn content_children(arena: &[Node], node: NodeId) -> &[NodeId] {
+    match &arena[node].data {
+        NodeData::Element {
+            template_contents: Some(contents),
+            ..
+        } => &arena[*contents].children,
+        _ => &arena[node].children,
+    }
+}
+
+fn mode_name(mode: ShadowRootMode) -> &'static str {
+    match mode {
+        ShadowRootMode::Open => "open",
+        ShadowRootMode::Closed => "closed",
+    }
+}
+
+fn push_shadow_root_start_tag(shadow: &ShadowRoot, out: &mut String) {
+    out.push_str("<template shadowrootmode=\"");
+    out.push_str(mode_name(shadow.mode));
+    out.push('"');
+    let flags = [
+        (shadow.delegates_focus, "shadowrootdelegatesfocus"),
+        (shadow.serializable, "shadowrootserializable"),
+        (shadow.clonable, "shadowrootclonable"),
+    ];
+    for (_, attr) in flags.iter().filter(|(set, _)| *set) {
+        out.push(' ');
+        out.push_str(attr);
+        out.push_str("=\"\"");
+    }
+    out.push('>');
+}
+
+// Which shadow roots `to_html_with` writes out, as declarative templates
+// ahead of their host's children. These are the options of `getHTML()`:
+// every shadow root marked serializable, and those listed by id.
+#[derive(Clone, Debug, Default)]
+pub struct HtmlOptions {
+    pub serializable_shadow_roots: bool,
+    pub shadow_roots: Vec<NodeId>,
+}
+
+fn push_shadow_root(arena: &[Node], host: NodeId, opts: &HtmlOptions, out: &mut String) {
+    let NodeData::Element {
+        shadow_root: Some(root),
+        ..
+    } = &arena[host].data
+    else {
+        return;
+    };
+    let NodeData::ShadowRoot(shadow) = &arena[*root].data else {
+        return;
+    };
+    if !(opts.serializable_shadow_roots && shadow.serializable || opts.shadow_roots.contains(root)) {
+        return;
+    }
+    push_shadow_root_start_tag(shadow, out);
+    for &child in &arena[*root].children {
+        node_to_html(arena, child, opts, out);
+    }
+    out.push_str("</template>");
+}
+
+fn node_to_html(arena: &[Node], node: NodeId, opts: &HtmlOptions, out: &mut String) {
+    let NodeData::Element { name, attrs, .. } = &arena[node].data else {
+        push_leaf(arena, node, out);
+        for &child in content_children(arena, node) {
+            node_to_html(arena, child, opts, out);
+        }
+        return;
+    };
+    push_start_tag(name, attrs, out);
+    if is_html_one_of(arena, Some(node), VOID_ELEMENTS) {
+        return;
+    }
+    push_shadow_root(arena, node, opts, out);
+    for &child in content_children(arena, node) {
+        node_to_html(arena, child, opts, out);
+    }
+    push_end_tag(&name.local, out);
+}
+
+// The HTML fragment serialization of `node`'s children (its `innerHTML`);
+// for a document root, the whole document. Shadow roots are left out.
+pub fn to_html(arena: &[Node], node: NodeId) -> String {
+    to_html_with(arena, node, &HtmlOptions::default())
+}
+
+// `to_html` with the shadow roots `opts` selects, like `getHTML(opts)`.
+pub fn to_html_with(arena: &[Node], node: NodeId, opts: &HtmlOptions) -> String {
+    let mut out = String::new();
+    push_shadow_root(arena, node, opts, &mut out);
+    for &child in content_children(arena, node) {
+        node_to_html(arena, child, opts, &mut out);
+    }
+    out
+}
+
+// Serializes a document parsed with `Options::preserve_source`, copying the
+// original markup wherever the tree still matches what was parsed. Untouched
+// subtrees come out byte for byte, along with ignored markup between them; a
+// changed element gets a new start tag that keeps the source text of its
+// unchanged attributes, and new nodes are serialized as `to_html` would.
+// Without `Document::original` this is `to_html` of the whole document.
+pub fn to_html_preserving(doc: &Document) -> String {
+    let Some(original) = &doc.original else {
+        return to_html(&doc.arena, doc.root);
+    };
+    let mut writer = PreservingWriter::new(doc, original);
+    if writer.untouched[doc.root] {
+        return original.text.clone();
+    }
+    writer.node(doc.root);
+    writer.gap_to(original.text.len());
+    writer.out
+}
+
+struct PreservingWriter<'a> {
+    doc: &'a Document,
+    original: &'a Original,
+    // Every recorded span and its node, sorted by start, with the furthest
+    // end reached by each prefix of the list.
+    spans: Vec<(Span, NodeId)>,
+    reach: Vec<usize>,
+    // Per node: the source its subtree covers, the number of spans recorded
+    // in the subtree, and whether the subtree is exactly as parsed.
+    extents: Vec<Option<Span>>,
+    span_counts: Vec<usize>,
+    untouched: Vec<bool>,
+    // Per node, what copying the subtree's source relies on or leaves behind
+    // in the tree builder: formatting elements cloned from ones written
+    // earlier, formatting elements still active after it because no end tag
+    // of theirs closed them, and a comment or doctype cut off by the end of
+    // the input, which swallows whatever is written after it.
+    inherits: Vec<bool>,
+    leaves: Vec<bool>,
+    unterminated: Vec<bool>,
+    // Template contents and the template each belongs to, and shadow hosts
+    // and the template each one's shadow root was declared with.
+    templates: HashMap<NodeId, NodeId>,
+    shadow_templates: HashMap<NodeId, NodeId>,
+    // Whether what was written before may not be what the source had there,
+    // so that every element is closed by an end tag and only subtrees that
+    // parse the same wherever they are put are copied whole.
+    explicit: bool,
+    // How far into the source the output has caught up, and how long the
+    // output was when it last did.
+    cursor: usize,
+    copied_to: usize,
+    out: String,
+}
+
+impl<'a> PreservingWriter<'a> {
+    fn new(doc: &'a Document, original: &'a Original) -> Self {
+        let mut spans: Vec<(Span, NodeId)> = doc
+            .sources
+            .iter()
+            .flat_map(|(&id, s)| [s.start_tag, s.end_tag, s.data].into_iter().flatten().map(move |span| (span, id)))
+            .collect();
+        spans.sort_by_key(|(span, _)| (span.start, span.end));
+        let reach = spans
+            .iter()
+            .scan(0, |reach, (span, _)| {
+                *reach = span.end.max(*reach);
+                Some(*reach)
+            })
+            .collect();
+        let arena = &doc.arena;
+        let templates: HashMap<NodeId, NodeId> = (0..arena.len())
+            .filter_map(|id| match arena[id].data {
+                NodeData::Element {
+                    template_contents: Some(contents),
+                    ..
+                } => Some((contents, id)),
+                _ => None,
+            })
+            .collect();
+        let shadow_templates = (0..arena.len())
+            .filter_map(|id| match arena[id].data {
+                NodeData::Element {
+                    shadow_root: Some(root),
+                    ..
+                } if matches!(arena[root].data, NodeData::ShadowRoot(_)) => Some((id, *templates.get(&root)?)),
+                _ => None,
+            })
+            .collect();
+        let len = arena.len();
+        let mut writer = Self {
+            doc,
+            original,
+            spans,
+            reach,
+            extents: vec![None; len],
+            span_counts: vec![0; len],
+            untouched: vec![false; len],
+            inherits: vec![false; len],
+            leaves: vec![false; len],
+            unterminated: vec![false; len],
+            templates,
+            shadow_templates,
+            explicit: false,
+            cursor: 0,
+            copied_to: 0,
+            out: String::new(),
+        };
+        writer.summarize_subtrees();
+        writer
+    }
+
+    fn summarize_subtrees(&mut self) {
+        let arena = &self.doc.arena;
+        let mut stack = vec![(self.doc.root, false)];
+        while let Some((id, done)) = stack.pop() {
+            let children = self.children(id, false);
+            if !done {
+                stack.push((id, true));
+                stack.extend(children.into_iter().map(|c| (c, false)));
+                continue;
+            }
+            let own = self.doc.source(id);
+            let own_spans = own.map(|s| [s.start_tag, s.end_tag, s.data]).unwrap_or_default();
+            let mut extent = None;
+            let mut count = 0;
+            for span in own_spans.into_iter().flatten() {
+                extent = Some(extent.map_or(span, |e: Span| e.cover(span)));
+                count += 1;
+            }
+            let mut untouched = self.same_as_parsed(id)
+                && self.original.nodes[id].children == arena[id].children
+                && self.template_contents_untouched(id);
+            let mut inherits = self.cloned(id);
+            let mut leaves = is_html_one_of(arena, Some(id), FORMATTING_ELEMENTS) && own.is_none_or(|s| s.end_tag.is_none());
+            for &child in &children {
+                if let Some(child_extent) = self.extents[child] {
+                    extent = Some(extent.map_or(child_extent, |e| e.cover(child_extent)));
+                }
+                count += self.span_counts[child];
+                untouched &= self.untouched[child];
+                inherits |= self.inherits[child];
+                leaves |= self.leaves[child];
+            }
+            self.unterminated[id] = match (&arena[id].data, own) {
+                (NodeData::Element { .. }, Some(NodeSource { end_tag: Some(_), .. })) => false,
+                (NodeData::Element { .. }, _) if is_html_one_of(arena, Some(id), TEXT_ELEMENTS) => true,
+                (NodeData::Element { .. }, _) => children.last().is_some_and(|&c| self.unterminated[c]),
+                (_, Some(NodeSource { data: Some(span), .. })) => self.cut_off(id, *span),
+                _ => false,
+            };
+            self.extents[id] = extent;
+            self.span_counts[id] = count;
+            self.untouched[id] = untouched;
+            self.inherits[id] = inherits;
+            self.leaves[id] = leaves;
+        }
+    }
+
+    fn same_as_parsed(&self, node: NodeId) -> bool {
+        self.original.nodes.get(node).is_some_and(|n| n.data == self.doc.arena[node].data)
+    }
+
+    fn template_contents_untouched(&self, node: NodeId) -> bool {
+        match &self.doc.arena[node].data {
+            NodeData::Element {
+                template_contents: Some(contents),
+                ..
+            } => self
+                .original
+                .nodes
+                .get(*contents)
+                .is_some_and(|n| *n == self.doc.arena[*contents]),
+            _ => true,
+        }
+    }
+
+    // What is written inside `node`: its children or template contents, and
+    // for a shadow host the template its shadow root was declared with, in
+    // source order. Copies the parser made itself are left out of what is
+    // compared against the source, as reparsing it makes them again.
+    fn children(&self, node: NodeId, with_copies: bool) -> Vec<NodeId> {
+        let mut children: Vec<NodeId> = content_children(&self.doc.arena, node)
+            .iter()
+            .copied()
+            .filter(|&child| with_copies || !self.copied_by_parser(child))
+            .collect();
+        if let Some(&template) = self.shadow_templates.get(&node) {
+            let start = |id: NodeId| self.doc.source(id).and_then(|s| s.start_tag.or(s.data)).map(|s| s.start);
+            let at = start(template).map_or(0, |t| {
+                children
+                    .iter()
+                    .position(|&c| start(c).is_some_and(|c| c > t))
+                    .unwrap_or(children.len())
+            });
+            children.insert(at, template);
+        }
+        children
+    }
+
+    // The copy of the selected option that `selectedcontent` is given with
+    // `Options::customizable_select` has no source, and parsing the output
+    // makes it again as long as the `selectedcontent` is copied.
+    fn copied_by_parser(&self, node: NodeId) -> bool {
+        node < self.original.nodes.len()
+            && self.doc.source(node).is_none()
+            && is_html_one_of(&self.doc.arena, self.doc.arena[node].parent, &["selectedcontent"])
+    }
+
+    // A formatting element the tree builder cloned from an earlier one.
+    fn cloned(&self, node: NodeId) -> bool {
+        self.doc.source(node).is_some_and(|s| s.synthetic && s.start_tag.is_none())
+            && is_html_one_of(&self.doc.arena, Some(node), FORMATTING_ELEMENTS)
+    }
+
+    // Whether the tree builder inserts `node` again without a start tag: the
+    // document's `html`, `head` and `body`, table sections and rows ahead of
+    // the content that needs them, and the `p` a stray `</p>` makes.
+    fn implied(&self, node: NodeId) -> bool {
+        let arena = &self.doc.arena;
+        let Some(NodeData::Element { name, attrs, .. }) = arena.get(node).map(|n| &n.data) else {
+            return false;
+        };
+        let first = content_children(arena, node).first().copied();
+        // Attributes merged in from a later tag need a start tag to keep them.
+        if !self.same_as_parsed(node) || !attrs.is_empty() {
+            return false;
+        }
+        let needed_by: &[&str] = match name.local.as_str() {
+            "p" => {
+                return first.is_none() && self.doc.source(node).is_some_and(|s| s.synthetic && s.end_tag.is_some());
+            }
+            _ if !is_html_one_of(arena, Some(node), IMPLIED_ELEMENTS) => return false,
+            "colgroup" => &["col"],
+            "tbody" => &["tr"],
+            "tr" => &["td", "th"],
+            // Whatever came first in the source made these, unless it was
+            // whitespace, which is dropped ahead of them. After the `head`,
+            // comments and head elements do not make a `body`.
+            _ => {
+                let parsed = &self.original.nodes[node].children;
+                let start = |id: &NodeId| self.doc.source(*id).and_then(|s| s.start_tag.or(s.data)).map(|s| s.start);
+                let stays_out = |c: NodeId| match &arena[c].data {
+                    NodeData::Text(t) => t.starts_with(|c: char| c.is_ascii_whitespace()),
+                    NodeData::Comment(_) => name.local == "body",
+                    _ => name.local == "body" && is_html_one_of(arena, Some(c), HEAD_ELEMENTS),
+                };
+                return first == parsed.first().copied()
+                    && first.and_then(|c| start(&c)) <= parsed.iter().filter_map(start).min()
+                    && !first.is_some_and(stays_out);
+            }
+        };
+        is_html_one_of(arena, first, needed_by)
+    }
+
+    // Whether what follows `node` in the source no longer follows it in the
+    // tree, so that markup relying on it to close `node` needs an end tag.
+    // After the last child that is whatever follows its parent, unless the
+    // parent's own end tag closes it; template contents end with the template.
+    fn followed_differently(&self, node: NodeId) -> bool {
+        let Some(parent) = self.doc.arena[node].parent else {
+            return self.templates.get(&node).is_some_and(|&t| self.followed_differently(t));
+        };
+        let next = |children: &[NodeId]| children.iter().position(|&c| c == node).map(|i| children.get(i + 1).copied());
+        let parsed = self.original.nodes.get(parent).map(|p| &p.children[..]);
+        match (next(&self.doc.arena[parent].children), parsed.and_then(next)) {
+            (Some(None), Some(None)) => {
+                let closer = self.templates.get(&parent).copied().unwrap_or(parent);
+                let closes = |s: &NodeSource| {
+                    s.end_tag.is_some_and(|end| self.extents[node].is_none_or(|e| e.end <= end.start))
+                };
+                !self.doc.source(closer).is_some_and(closes) && self.followed_differently(parent)
+            }
+            (Some(Some(now)), Some(Some(then))) if now == then => self.given_start_tag(now),
+            (now, then) => now != then,
+        }
+    }
+
+    // Whether `node` is an element the source left implied that is now
+    // written with a start tag.
+    fn given_start_tag(&self, node: NodeId) -> bool {
+        matches!(self.doc.arena[node].data, NodeData::Element { .. })
+            && self.doc.source(node).is_some_and(|s| s.start_tag.is_none())
+            && !self.implied(node)
+            && (self.explicit || !self.cloned(node))
+    }
+
+    // Whether markup copied from the source for the children of `node` could
+    // leave the tree builder in a state that differs from the one the rest of
+    // its children were parsed in: when formatting elements are left active
+    // ahead of children that changed, or children were moved ahead of the
+    // table they were written in, everything inside `node` is written with
+    // explicit tags instead.
+    fn needs_explicit_tags(&self, node: NodeId) -> bool {
+        let children = content_children(&self.doc.arena, node);
+        let starts: Vec<usize> = children.iter().filter_map(|&c| self.extents[c]).map(|e| e.start).collect();
+        if !starts.is_sorted() {
+            return true;
+        }
+        let Some(first) = children.iter().position(|&c| self.inherits[c] || self.leaves[c]) else {
+            return false;
+        };
+        // Whatever is copied has to be copied whole, and clones rely on the
+        // markup ahead of them being copied as it was.
+        let copyable = |&c: &NodeId| {
+            self.untouched[c] && self.extents[c].is_none_or(|e| self.holds_only(e, self.span_counts[c]))
+        };
+        let parsed = match self.original.nodes.get(node) {
+            Some(_) => content_children(&self.original.nodes, node),
+            None => &[],
+        };
+        if self.inherits[children[first]]
+            && !(parsed.get(..=first) == Some(&children[..=first]) && children[..first].iter().all(copyable))
+        {
+            return true;
+        }
+        let tail = &children[first..];
+        // The first of them may have changed inside, which is then looked at
+        // when writing it, as long as it is not itself the formatting element
+        // and what it leaves behind does not reach the rest.
+        let own = |c: NodeId| self.cloned(c) || is_html_one_of(&self.doc.arena, Some(c), FORMATTING_ELEMENTS);
+        let checked_inside = !own(children[first]) && (tail.len() == 1 || !self.leaves[children[first]]);
+        !(parsed.ends_with(tail) && tail.iter().skip(usize::from(checked_inside)).all(copyable))
+    }
+
+    // Whether exactly `count` recorded spans touch `span`, which is then known
+    // to hold nothing but the markup they belong to.
+    fn holds_only(&self, span: Span, count: usize) -> bool {
+        let lo = self.spans.partition_point(|(s, _)| s.start < span.start);
+        let hi = self.spans.partition_point(|(s, _)| s.start < span.end);
+        (lo == 0 || self.reach[lo - 1] <= span.start) && hi - lo == count
+    }
+
+    // Copies source text that belongs to no node, such as ignored end tags,
+    // lying between what was last copied and `start`, unless something new
+    // was written after it or around it, where the tree builder may not
+    // ignore it the same way.
+    fn gap_to(&mut self, start: usize) {
+        let gap = &self.original.text[self.cursor.min(start)..start];
+        if !gap.is_empty()
+            && self.out.len() == self.copied_to
+            && !(self.explicit && gap.contains('<'))
+            && self.holds_only(Span::new(self.cursor, start), 0)
+        {
+            self.out.push_str(gap);
+        }
+        self.cursor = self.cursor.max(start);
+    }
+
+    fn copy(&mut self, span: Span) {
+        self.gap_to(span.start);
+        self.out.push_str(&self.original.text[span.start..span.end]);
+        self.cursor = self.cursor.max(span.end);
+        self.copied_to = self.out.len();
+    }
+
+    // Copies the source of an untouched subtree, if it holds nothing else.
+    fn copy_subtree(&mut self, node: NodeId) -> bool {
+        match self.extents[node] {
+            Some(extent) if self.untouched[node] && self.holds_only(extent, self.span_counts[node]) => {
+                self.copy(extent);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Writes `node`, copying what the source still says right.
+    fn node(&mut self, id: NodeId) {
+        let source = self.doc.source(id);
+        let arena = &self.doc.arena;
+        let NodeData::Element { name, attrs, .. } = &arena[id].data else {
+            match source.and_then(|s| s.data) {
+                Some(span) if self.same_as_parsed(id) && self.holds_only(span, 1) && !self.unterminated[id] && !self.runs_on(id) => {
+                    self.copy(span)
+                }
+                _ => push_leaf(arena, id, &mut self.out),
+            }
+            self.children_of(id);
+            return;
+        };
+        let parsed = self.same_as_parsed(id);
+        // Without explicit tags, markup as parsed makes a clone again.
+        let cloned = parsed && !self.explicit && self.cloned(id);
+        // A foreign element's self-closing tag no longer fits once it has
+        // children, which then need an end tag.
+        let self_closed = name.ns != Namespace::Html
+            && source.and_then(|s| s.start_tag).is_some_and(|s| self.original.text[..s.end].ends_with("/>"))
+            && !arena[id].children.is_empty();
+        // Attributes merged in from a later `<html>` or `<body>` tag have to
+        // be written into the first.
+        let merged = |span: Span| {
+            source.is_some_and(|s| s.attributes.iter().any(|a| a.name.start < span.start || a.name.end > span.end))
+        };
+        match source.and_then(|s| s.start_tag) {
+            Some(span) if parsed && self.holds_only(span, 1) && !self_closed && !merged(span) => self.copy(span),
+            Some(span) => {
+                self.gap_to(span.start);
+                self.rebuild_start_tag(id, span, attrs);
+                self.cursor = self.cursor.max(span.end);
+            }
+            None if cloned || self.implied(id) => {}
+            None => push_start_tag(name, attrs, &mut self.out),
+        }
+        if is_html_one_of(arena, Some(id), VOID_ELEMENTS) {
+            return;
+        }
+        self.children_of(id);
+        let same_name = matches!(
+            self.original.nodes.get(id).map(|n| &n.data),
+            Some(NodeData::Element { name: parsed_name, .. }) if parsed_name == name
+        );
+        match source.and_then(|s| s.end_tag) {
+            Some(span) if same_name && self.holds_only(span, 1) => self.copy(span),
+            // An end tag in the source that cannot be copied is written anew,
+            // and markup left unclosed only reparses the same way around the
+            // markup that followed it.
+            Some(_) => push_end_tag(&name.local, &mut self.out),
+            None if self.explicit
+                || self_closed
+                || id >= self.original.nodes.len()
+                || is_html_one_of(arena, Some(id), TEXT_ELEMENTS)
+                || self.followed_differently(id) =>
+            {
+                push_end_tag(&name.local, &mut self.out)
+            }
+            None => {}
+        }
+    }
+
+    fn children_of(&mut self, id: NodeId) {
+        let outer = self.explicit;
+        self.explicit |= self.needs_explicit_tags(id);
+        for child in self.children(id, true) {
+            let copied = !self.unterminated[child]
+                && (!self.explicit || !self.inherits[child] && !self.leaves[child])
+                && !self.runs_on(child)
+                && !self.given_start_tag(child)
+                && self.copy_subtree(child);
+            if !copied {
+                self.node(child);
+            } else if self.explicit || self.followed_differently(child) {
+                self.close_left_open(child);
+            }
+        }
+        self.explicit = outer;
+    }
+
+    // Whether the source of a comment or doctype lacks its closing `>`,
+    // having been cut off by the end of the input.
+    fn cut_off(&self, node: NodeId, span: Span) -> bool {
+        !matches!(self.doc.arena[node].data, NodeData::Text(_)) && !self.original.text[..span.end].ends_with('>')
+    }
+
+    // Whether the source of a text node ends in what the markup written
+    // after it could turn into a tag or a character reference, when that
+    // is no longer what the source had there.
+    fn runs_on(&self, node: NodeId) -> bool {
+        let Some(span) = self.doc.source(node).and_then(|s| s.data) else {
+            return false;
+        };
+        let text = &self.original.text[span.start..span.end];
+        matches!(self.doc.arena[node].data, NodeData::Text(_))
+            && (text.ends_with('<')
+                || text.ends_with("</")
+                || text.rfind('&').is_some_and(|i| text[i + 1..].chars().all(|c| c.is_ascii_alphanumeric() || c == '#')))
+            && self.followed_differently(node)
+    }
+
+    // Closes the elements a copied subtree leaves open at its end, which the
+    // original markup relied on what came next to close. A start tag that
+    // closes the one element left open by itself, like `<li>` after an `li`,
+    // is left to do so.
+    fn close_left_open(&mut self, node: NodeId) {
+        let arena = &self.doc.arena;
+        let mut open = Vec::new();
+        let mut next = Some(node);
+        // An end tag ahead of an element's content, like a `</head>` before a
+        // `<template>` that reopens it, leaves that content open.
+        while let Some(id) = next
+            && let NodeData::Element { name, .. } = &arena[id].data
+        {
+            let last = self.children(id, true).last().copied();
+            match self.doc.source(id).and_then(|s| s.end_tag) {
+                Some(end) if last.and_then(|c| self.extents[c]).is_none_or(|e| e.end <= end.start) => break,
+                Some(_) => {}
+                None if is_html_one_of(arena, Some(id), VOID_ELEMENTS) => {}
+                None => open.push(&name.local),
+            }
+            next = last;
+        }
+        if let [only] = open[..]
+            && self.closed_by_next_start_tag(node, only)
+        {
+            return;
+        }
+        for local in open.into_iter().rev() {
+            push_end_tag(local, &mut self.out);
+        }
+    }
+
+    fn closed_by_next_start_tag(&self, node: NodeId, local: &str) -> bool {
+        let arena = &self.doc.arena;
+        let Some(parent) = arena[node].parent else {
+            return false;
+        };
+        let siblings = &arena[parent].children;
+        let next = siblings.iter().position(|&c| c == node).and_then(|i| siblings.get(i + 1)).copied();
+        let closers: &[&str] = match local {
+            "li" => &["li"],
+            "dd" | "dt" => &["dd", "dt"],
+            "option" => &["optgroup", "option"],
+            "optgroup" => &["optgroup"],
+            "p" => &["p"],
+            _ => return false,
+        };
+        is_html_one_of(arena, next, closers) && next.is_some_and(|n| !self.implied(n))
+    }
+
+    // Writes a start tag for an edited element in the style of the one it
+    // replaces: the original tag name and, for attributes that have not
+    // changed, their original text.
+    fn rebuild_start_tag(&mut self, id: NodeId, span: Span, attrs: &[Attr]) {
+        let text = &self.original.text;
+        let tag = &text[span.start..span.end];
+        // A `br` written as `</br>` is given a start tag of its own.
+        let name_start = if tag.starts_with("</") { 2 } else { 1 };
+        let name_end = tag[name_start..]
+            .find(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+            .map_or(tag.len(), |i| i + name_start);
+        self.out.push('<');
+        self.out.push_str(&tag[name_start..name_end]);
+        let parsed_attrs = match &self.original.nodes[id].data {
+            NodeData::Element { attrs, .. } => attrs.as_slice(),
+            _ => &[],
+        };
+        let attr_spans = self.doc.source(id).map_or(&[][..], |s| &s.attributes);
+        let attrs_end = attr_spans
+            .iter()
+            .map(|a| attribute_source(text, a).end)
+            .fold(span.start + name_end, usize::max);
+        for attr in attrs {
+            let kept = parsed_attrs
+                .iter()
+                .position(|a| a == attr)
+                .and_then(|i| attr_spans.get(i))
+                .map(|a| attribute_source(text, a));
+            match kept {
+                Some(source) => {
+                    self.out.push(' ');
+                    self.out.push_str(&text[source.start..source.end]);
+                }
+                None => push_attr(attr, &mut self.out),
+            }
+        }
+        let self_closing = tag.ends_with("/>") && attrs_end <= span.end - 2 && self.doc.arena[id].children.is_empty();
+        self.out.push_str(if self_closing { "/>" } else { ">" });
+    }
+}
+
+// The source of one attribute, from its name to the end of its value
+// including any closing quote.
+fn attribute_source(text: &str, attr: &AttributeSpan) -> Span {
+    let Some(value) = attr.value else {
+        return attr.name;
+    };
+    let bytes = text.as_bytes();
+    let quoted = value.start > 0
+        && matches!(bytes[value.start - 1], b'"' | b'\'')
+        && bytes.get(value.end) == Some(&bytes[value.start - 1]);
+    Span::new(attr.name.start, value.end + usize::from(quoted))
+}
+
 pub fn normalize_tree_text(text: &str) -> String {
     let trimmed = text.trim();
     trimmed
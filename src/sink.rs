@@ -0,0 +1,247 @@
+use crate::dom::{self, Attr, Doctype, Document, NodeData, NodeId, NodeSource, QualName, QuirksMode, ShadowRoot};
+use crate::ParseError;
+
+// The tree builder's view of the DOM it is building. Node ids are chosen by
+// the sink; the builder only hands back ids the sink gave it. `Document` is
+// the built-in implementation.
+pub trait TreeSink {
+    fn document(&self) -> NodeId;
+
+    // `None` for anything that is not an element.
+    fn element_name(&self, node: NodeId) -> Option<&QualName>;
+
+    fn element_attrs(&self, element: NodeId) -> &[Attr];
+
+    fn parent(&self, node: NodeId) -> Option<NodeId>;
+
+    fn create_element(&mut self, name: QualName, attrs: Vec<Attr>) -> NodeId;
+
+    fn create_comment(&mut self, data: String) -> NodeId;
+
+    fn create_doctype(&mut self, doctype: Doctype) -> NodeId;
+
+    // `child` may already have a parent, in which case it is moved.
+    fn append(&mut self, parent: NodeId, child: NodeId);
+
+    // Inserts before `reference`, or appends when it is `None`. Moves `child`
+    // like `append`.
+    fn insert_before(&mut self, parent: NodeId, child: NodeId, reference: Option<NodeId>);
+
+    // Inserts text at the same position as `insert_before`, merging it into
+    // an immediately preceding text node if there is one. Returns the text
+    // node written to.
+    fn insert_text(&mut self, parent: NodeId, reference: Option<NodeId>, text: &str) -> NodeId;
+
+    // The contents of the text node `insert_text` would merge into.
+    fn preceding_text(&self, parent: NodeId, reference: Option<NodeId>) -> Option<&str>;
+
+    fn remove_from_parent(&mut self, node: NodeId);
+
+    fn reparent_children(&mut self, from: NodeId, to: NodeId);
+
+    // The template's contents fragment, created on first use.
+    fn template_contents(&mut self, template: NodeId) -> NodeId;
+
+    // For a `<template shadowrootmode>` with `Options::declarative_shadow_roots`:
+    // gives `host` a shadow root that becomes the contents of `template`,
+    // which is then never inserted. Returning false has the template inserted
+    // as usual instead, as when `host` already has a shadow root or the sink
+    // does not model them.
+    fn attach_shadow_root(&mut self, host: NodeId, template: NodeId, shadow: ShadowRoot) -> bool {
+        let _ = (host, template, shadow);
+        false
+    }
+
+    // With `Options::customizable_select`, mirrors the selected option of a
+    // `select` into its `selectedcontent`: replaces the children of
+    // `selectedcontent` with deep copies of the children of `option`, and
+    // returns how many nodes that created. Returns `None` without touching
+    // anything when that would be more than `max_nodes`. Sinks that cannot
+    // read back what they built leave `selectedcontent` alone.
+    fn clone_option_contents(
+        &mut self,
+        option: NodeId,
+        selectedcontent: NodeId,
+        max_nodes: Option<usize>,
+    ) -> Option<usize> {
+        let _ = (option, selectedcontent, max_nodes);
+        Some(0)
+    }
+
+    fn add_attrs_if_missing(&mut self, element: NodeId, attrs: Vec<Attr>);
+
+    fn set_quirks_mode(&mut self, mode: QuirksMode);
+
+    // Called for every tokenizer and tree-construction error when
+    // `Options::collect_errors` is set.
+    fn parse_error(&mut self, error: &ParseError) {
+        let _ = error;
+    }
+
+    // Called when `Options::source_spans` is set with what the builder knows
+    // of where `node` came from: once when it is created, again when an end
+    // tag closes it or `<html>`/`<body>` attributes are merged into it, and
+    // for text nodes each time text is added. See `Document::record_source`
+    // for how the pieces combine.
+    fn record_source(&mut self, node: NodeId, source: NodeSource) {
+        let _ = (node, source);
+    }
+
+    // Called after each token of a document parse with a chain of open
+    // elements, outermost first. Every child of the document or of one of
+    // these elements that is not the last child (nor, when the last child is
+    // a table, the one before it) will never be moved, merged into or
+    // wrapped again, and neither will the chain elements themselves.
+    // Attributes may still be added to `html` and `body`.
+    fn checkpoint(&mut self, stable: &[NodeId]) {
+        let _ = stable;
+    }
+
+    // Called once tree construction has seen end of file, or has stopped
+    // early because of `Limits`.
+    fn end(&mut self) {}
+}
+
+fn preceding_sibling(doc: &Document, parent: NodeId, reference: Option<NodeId>) -> Option<NodeId> {
+    let children = &doc.arena[parent].children;
+    match reference {
+        Some(r) => children
+            .iter()
+            .position(|&c| c == r)
+            .and_then(|i| i.checked_sub(1))
+            .map(|i| children[i]),
+        None => children.last().copied(),
+    }
+}
+
+impl TreeSink for Document {
+    fn document(&self) -> NodeId {
+        self.root
+    }
+
+    fn element_name(&self, node: NodeId) -> Option<&QualName> {
+        match &self.arena[node].data {
+            NodeData::Element { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+
+    fn element_attrs(&self, element: NodeId) -> &[Attr] {
+        match &self.arena[element].data {
+            NodeData::Element { attrs, .. } => attrs,
+            _ => &[],
+        }
+    }
+
+    fn parent(&self, node: NodeId) -> Option<NodeId> {
+        self.arena[node].parent
+    }
+
+    fn create_element(&mut self, name: QualName, attrs: Vec<Attr>) -> NodeId {
+        let id = Document::create_element(self, name);
+        for attr in attrs {
+            dom::set_attr(&mut self.arena, id, attr);
+        }
+        id
+    }
+
+    fn create_comment(&mut self, data: String) -> NodeId {
+        Document::create_comment(self, data)
+    }
+
+    fn create_doctype(&mut self, doctype: Doctype) -> NodeId {
+        Document::create_doctype(self, doctype)
+    }
+
+    fn append(&mut self, parent: NodeId, child: NodeId) {
+        dom::append_child(&mut self.arena, parent, child);
+    }
+
+    fn insert_before(&mut self, parent: NodeId, child: NodeId, reference: Option<NodeId>) {
+        dom::insert_before(&mut self.arena, parent, child, reference);
+    }
+
+    fn insert_text(&mut self, parent: NodeId, reference: Option<NodeId>, text: &str) -> NodeId {
+        if let Some(prev) = preceding_sibling(self, parent, reference)
+            && let NodeData::Text(existing) = &mut self.arena[prev].data
+        {
+            existing.push_str(text);
+            return prev;
+        }
+        let id = self.create_text(text);
+        dom::insert_before(&mut self.arena, parent, id, reference);
+        id
+    }
+
+    fn preceding_text(&self, parent: NodeId, reference: Option<NodeId>) -> Option<&str> {
+        match &self.arena[preceding_sibling(self, parent, reference)?].data {
+            NodeData::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    fn remove_from_parent(&mut self, node: NodeId) {
+        dom::detach(&mut self.arena, node);
+    }
+
+    fn reparent_children(&mut self, from: NodeId, to: NodeId) {
+        dom::reparent_children(&mut self.arena, from, to);
+    }
+
+    fn template_contents(&mut self, template: NodeId) -> NodeId {
+        dom::ensure_template_contents(&mut self.arena, template)
+    }
+
+    fn attach_shadow_root(&mut self, host: NodeId, template: NodeId, shadow: ShadowRoot) -> bool {
+        let Some(root) = dom::attach_shadow_root(&mut self.arena, host, shadow) else {
+            return false;
+        };
+        if let NodeData::Element {
+            template_contents, ..
+        } = &mut self.arena[template].data
+        {
+            *template_contents = Some(root);
+        }
+        true
+    }
+
+    fn clone_option_contents(
+        &mut self,
+        option: NodeId,
+        selectedcontent: NodeId,
+        max_nodes: Option<usize>,
+    ) -> Option<usize> {
+        if let Some(max) = max_nodes {
+            let children = &self.arena[option].children;
+            if children.iter().map(|&child| dom::subtree_len(&self.arena, child)).sum::<usize>() > max {
+                return None;
+            }
+        }
+        let before = self.arena.len();
+        for child in std::mem::take(&mut self.arena[selectedcontent].children) {
+            self.arena[child].parent = None;
+        }
+        for i in 0..self.arena[option].children.len() {
+            let child = self.arena[option].children[i];
+            let copy = dom::clone_subtree(&mut self.arena, child);
+            dom::append_child(&mut self.arena, selectedcontent, copy);
+        }
+        Some(self.arena.len() - before)
+    }
+
+    fn add_attrs_if_missing(&mut self, element: NodeId, attrs: Vec<Attr>) {
+        for attr in attrs {
+            if !self.element_attrs(element).iter().any(|a| a.name == attr.name) {
+                dom::set_attr(&mut self.arena, element, attr);
+            }
+        }
+    }
+
+    fn set_quirks_mode(&mut self, mode: QuirksMode) {
+        self.quirks_mode = mode;
+    }
+
+    fn record_source(&mut self, node: NodeId, source: NodeSource) {
+        Document::record_source(self, node, source);
+    }
+}
@@ -0,0 +1,1654 @@
+use std::collections::VecDeque;
+
+use crate::entities::{longest_named_match, numeric_character_reference};
+use crate::input::InputStream;
+use crate::{AttributeSpan, ErrorCode, Location, ParseError, Span};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Attribute {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tag {
+    pub name: String,
+    pub attrs: Vec<Attribute>,
+    pub self_closing: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DoctypeToken {
+    pub name: Option<String>,
+    pub public_id: Option<String>,
+    pub system_id: Option<String>,
+    pub force_quirks: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Token {
+    Doctype(DoctypeToken),
+    StartTag(Tag),
+    EndTag(Tag),
+    Comment(String),
+    Character(String),
+    Eof,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum State {
+    #[default]
+    Data,
+    Rcdata,
+    Rawtext,
+    ScriptData,
+    Plaintext,
+    TagOpen,
+    EndTagOpen,
+    TagName,
+    RcdataLessThanSign,
+    RcdataEndTagOpen,
+    RcdataEndTagName,
+    RawtextLessThanSign,
+    RawtextEndTagOpen,
+    RawtextEndTagName,
+    ScriptDataLessThanSign,
+    ScriptDataEndTagOpen,
+    ScriptDataEndTagName,
+    ScriptDataEscapeStart,
+    ScriptDataEscapeStartDash,
+    ScriptDataEscaped,
+    ScriptDataEscapedDash,
+    ScriptDataEscapedDashDash,
+    ScriptDataEscapedLessThanSign,
+    ScriptDataEscapedEndTagOpen,
+    ScriptDataEscapedEndTagName,
+    ScriptDataDoubleEscapeStart,
+    ScriptDataDoubleEscaped,
+    ScriptDataDoubleEscapedDash,
+    ScriptDataDoubleEscapedDashDash,
+    ScriptDataDoubleEscapedLessThanSign,
+    ScriptDataDoubleEscapeEnd,
+    BeforeAttributeName,
+    AttributeName,
+    AfterAttributeName,
+    BeforeAttributeValue,
+    AttributeValueDoubleQuoted,
+    AttributeValueSingleQuoted,
+    AttributeValueUnquoted,
+    AfterAttributeValueQuoted,
+    SelfClosingStartTag,
+    BogusComment,
+    MarkupDeclarationOpen,
+    CommentStart,
+    CommentStartDash,
+    Comment,
+    CommentLessThanSign,
+    CommentLessThanSignBang,
+    CommentLessThanSignBangDash,
+    CommentLessThanSignBangDashDash,
+    CommentEndDash,
+    CommentEnd,
+    CommentEndBang,
+    Doctype,
+    BeforeDoctypeName,
+    DoctypeName,
+    AfterDoctypeName,
+    AfterDoctypePublicKeyword,
+    BeforeDoctypePublicIdentifier,
+    DoctypePublicIdentifierDoubleQuoted,
+    DoctypePublicIdentifierSingleQuoted,
+    AfterDoctypePublicIdentifier,
+    BetweenDoctypePublicAndSystemIdentifiers,
+    AfterDoctypeSystemKeyword,
+    BeforeDoctypeSystemIdentifier,
+    DoctypeSystemIdentifierDoubleQuoted,
+    DoctypeSystemIdentifierSingleQuoted,
+    AfterDoctypeSystemIdentifier,
+    BogusDoctype,
+    CdataSection,
+    CdataSectionBracket,
+    CdataSectionEnd,
+    CharacterReference,
+    NamedCharacterReference,
+    AmbiguousAmpersand,
+    NumericCharacterReference,
+    HexadecimalCharacterReferenceStart,
+    DecimalCharacterReferenceStart,
+    HexadecimalCharacterReference,
+    DecimalCharacterReference,
+    NumericCharacterReferenceEnd,
+}
+
+// How a standalone tokenizer starts out. The tree builder normally supplies
+// all three as it goes: it switches states for elements like `<title>` and
+// `<script>`, an end tag is only appropriate when it matches the last start
+// tag emitted (or `last_start_tag` before any), and `<![CDATA[` only opens a
+// CDATA section while the adjusted current node is not in the HTML namespace.
+#[derive(Clone, Debug, Default)]
+pub struct TokenizerOpts {
+    pub initial_state: State,
+    pub last_start_tag: Option<String>,
+    pub cdata_allowed: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TagKind {
+    Start,
+    End,
+}
+
+pub struct Tokenizer {
+    input: InputStream,
+    state: State,
+    return_state: State,
+    char_ref_code: u32,
+    pending: VecDeque<(Token, Span, Vec<AttributeSpan>)>,
+    text: String,
+    text_start: usize,
+    markup_start: usize,
+    token_span: Span,
+    token_attribute_spans: Vec<AttributeSpan>,
+    tag_kind: TagKind,
+    tag: Tag,
+    attr_name: String,
+    attr_value: String,
+    in_attr: bool,
+    attr_is_duplicate: bool,
+    attr_span: AttributeSpan,
+    attr_spans: Vec<AttributeSpan>,
+    comment: String,
+    doctype: DoctypeToken,
+    temp_buf: String,
+    last_start_tag: Option<String>,
+    cdata_allowed: bool,
+    errors: Vec<ParseError>,
+    input_complete: bool,
+    eof_emitted: bool,
+    steps: u64,
+    step_limit: Option<u64>,
+}
+
+// No single step of the state machine looks further ahead than this many
+// bytes (the longest named character reference plus one character), so
+// stepping with at least this much buffered input behaves exactly as if the
+// whole document were available.
+const STREAMING_LOOKAHEAD: usize = 64;
+
+fn is_ascii_whitespace(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\x0C' | ' ')
+}
+
+impl Tokenizer {
+    pub fn new(input: &str) -> Self {
+        Self::with_opts(input, TokenizerOpts::default())
+    }
+
+    pub fn with_opts(input: &str, opts: TokenizerOpts) -> Self {
+        Self {
+            input: InputStream::new(input),
+            state: opts.initial_state,
+            return_state: State::Data,
+            char_ref_code: 0,
+            pending: VecDeque::new(),
+            text: String::new(),
+            text_start: 0,
+            markup_start: 0,
+            token_span: Span::default(),
+            token_attribute_spans: Vec::new(),
+            tag_kind: TagKind::Start,
+            tag: Tag {
+                name: String::new(),
+                attrs: Vec::new(),
+                self_closing: false,
+            },
+            attr_name: String::new(),
+            attr_value: String::new(),
+            in_attr: false,
+            attr_is_duplicate: false,
+            attr_span: AttributeSpan::default(),
+            attr_spans: Vec::new(),
+            comment: String::new(),
+            doctype: DoctypeToken::default(),
+            temp_buf: String::new(),
+            last_start_tag: opts.last_start_tag,
+            cdata_allowed: opts.cdata_allowed,
+            errors: Vec::new(),
+            input_complete: true,
+            eof_emitted: false,
+            steps: 0,
+            step_limit: None,
+        }
+    }
+
+    // A tokenizer whose input arrives through `feed`. Until `end_input` is
+    // called, `next()` returns `None` whenever it runs short of buffered
+    // input rather than treating that as end of file.
+    pub fn new_streaming() -> Self {
+        Self {
+            input_complete: false,
+            ..Self::new("")
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &str) {
+        self.input.push_str(chunk);
+    }
+
+    pub fn end_input(&mut self) {
+        self.input_complete = true;
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn set_state(&mut self, state: State) {
+        self.state = state;
+    }
+
+    pub fn set_last_start_tag(&mut self, name: Option<String>) {
+        self.last_start_tag = name;
+    }
+
+    // The hook for the tree builder's "adjusted current node is not in the
+    // HTML namespace" check, re-evaluated after every token it processes.
+    pub fn set_cdata_allowed(&mut self, allowed: bool) {
+        self.cdata_allowed = allowed;
+    }
+
+    pub fn location(&self) -> Location {
+        self.input.location()
+    }
+
+    // State machine steps taken so far, roughly one per input character.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    // Once `steps()` reaches `limit`, `next()` returns `None` as if it had run
+    // out of input, until the limit is raised or removed.
+    pub fn set_step_limit(&mut self, limit: Option<u64>) {
+        self.step_limit = limit;
+    }
+
+    pub(crate) fn end_location(&self) -> Location {
+        self.input.end_location()
+    }
+
+    // Source bytes of the token most recently returned by `next()`. Character
+    // tokens cover everything between the surrounding markup, so the spans of
+    // consecutive tokens tile the input.
+    pub fn token_span(&self) -> Span {
+        self.token_span
+    }
+
+    // Where each attribute of the start tag most recently returned by
+    // `next()` was written, parallel to its `attrs`. Empty for other tokens.
+    pub fn attribute_spans(&self) -> &[AttributeSpan] {
+        &self.token_attribute_spans
+    }
+
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    pub fn take_errors(&mut self) -> Vec<ParseError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    fn error(&mut self, code: ErrorCode) {
+        self.errors.push(ParseError {
+            code,
+            location: self.input.location(),
+            span: self.input.last_char_span(),
+            secondary: None,
+        });
+    }
+
+    fn consume(&mut self) -> Option<char> {
+        let c = self.input.next_char();
+        if let Some(code) = self.input.take_error() {
+            self.error(code);
+        }
+        c
+    }
+
+    fn emit_char(&mut self, c: char) {
+        self.text.push(c);
+    }
+
+    fn emit_str(&mut self, s: &str) {
+        self.text.push_str(s);
+    }
+
+    fn flush_text(&mut self, end: usize) {
+        if !self.text.is_empty() {
+            let text = std::mem::take(&mut self.text);
+            let span = Span::new(self.text_start, end.max(self.text_start));
+            self.pending.push_back((Token::Character(text), span, Vec::new()));
+        }
+    }
+
+    // Queues buffered character data now rather than waiting for the markup
+    // that would end it, for a parse that stops early. A tag still being
+    // read is left out.
+    pub(crate) fn flush_pending_text(&mut self) {
+        let end = if self.markup_start > self.text_start {
+            self.markup_start
+        } else {
+            self.input.offset()
+        };
+        self.flush_text(end);
+    }
+
+    fn emit(&mut self, token: Token) {
+        let end = self.input.offset();
+        let start = match token {
+            Token::Eof => end,
+            _ => self.markup_start,
+        };
+        self.flush_text(start);
+        let attribute_spans = match token {
+            Token::StartTag(_) => std::mem::take(&mut self.attr_spans),
+            _ => Vec::new(),
+        };
+        self.pending.push_back((token, Span::new(start, end), attribute_spans));
+        self.text_start = end;
+    }
+
+    fn emit_eof(&mut self) {
+        self.emit(Token::Eof);
+        self.eof_emitted = true;
+    }
+
+    fn new_tag(&mut self, kind: TagKind) {
+        self.tag_kind = kind;
+        self.tag = Tag {
+            name: String::new(),
+            attrs: Vec::new(),
+            self_closing: false,
+        };
+        self.in_attr = false;
+        self.attr_spans.clear();
+    }
+
+    fn start_attr(&mut self) {
+        self.finish_attr();
+        self.in_attr = true;
+        self.attr_is_duplicate = false;
+    }
+
+    fn check_duplicate_attr(&mut self) {
+        if self.tag.attrs.iter().any(|a| a.name == self.attr_name) {
+            self.attr_is_duplicate = true;
+            self.error(ErrorCode::DuplicateAttribute);
+        }
+    }
+
+    fn finish_attr(&mut self) {
+        if !self.in_attr {
+            return;
+        }
+        self.in_attr = false;
+        let name = std::mem::take(&mut self.attr_name);
+        let value = std::mem::take(&mut self.attr_value);
+        if !self.attr_is_duplicate {
+            self.tag.attrs.push(Attribute { name, value });
+            self.attr_spans.push(self.attr_span);
+        }
+    }
+
+    fn emit_tag(&mut self) {
+        self.finish_attr();
+        let tag = std::mem::replace(
+            &mut self.tag,
+            Tag {
+                name: String::new(),
+                attrs: Vec::new(),
+                self_closing: false,
+            },
+        );
+        match self.tag_kind {
+            TagKind::Start => {
+                self.last_start_tag = Some(tag.name.clone());
+                self.emit(Token::StartTag(tag));
+            }
+            TagKind::End => {
+                if !tag.attrs.is_empty() {
+                    self.error(ErrorCode::EndTagWithAttributes);
+                }
+                if tag.self_closing {
+                    self.error(ErrorCode::EndTagWithTrailingSolidus);
+                }
+                self.emit(Token::EndTag(tag));
+            }
+        }
+    }
+
+    fn emit_comment(&mut self) {
+        let data = std::mem::take(&mut self.comment);
+        self.emit(Token::Comment(data));
+    }
+
+    fn emit_doctype(&mut self) {
+        let doctype = std::mem::take(&mut self.doctype);
+        self.emit(Token::Doctype(doctype));
+    }
+
+    fn is_appropriate_end_tag(&self) -> bool {
+        self.tag_kind == TagKind::End && self.last_start_tag.as_deref() == Some(self.tag.name.as_str())
+    }
+
+    fn reconsume_in(&mut self, state: State) {
+        self.input.back();
+        self.state = state;
+    }
+
+    // Follows attribute boundaries from the state transitions around them.
+    // `start` is where the character that caused the transition begins.
+    fn track_attr_span(&mut self, before: State, start: usize) {
+        match (before, self.state) {
+            (_, State::AttributeName) => {
+                self.attr_span = AttributeSpan {
+                    name: Span::new(start, start),
+                    value: None,
+                }
+            }
+            (State::AttributeName, _) => self.attr_span.name.end = start,
+            (State::BeforeAttributeValue, State::AttributeValueDoubleQuoted | State::AttributeValueSingleQuoted) => {
+                let offset = self.input.offset();
+                self.attr_span.value = Some(Span::new(offset, offset));
+            }
+            (State::BeforeAttributeValue, State::AttributeValueUnquoted) => {
+                self.attr_span.value = Some(Span::new(start, start))
+            }
+            (
+                State::AttributeValueDoubleQuoted | State::AttributeValueSingleQuoted,
+                State::AfterAttributeValueQuoted,
+            )
+            | (State::AttributeValueUnquoted, State::BeforeAttributeName) => self.end_attr_value(start),
+            _ => {}
+        }
+    }
+
+    fn end_attr_value(&mut self, end: usize) {
+        if let Some(value) = &mut self.attr_span.value {
+            value.end = end;
+        }
+    }
+
+    fn step(&mut self) {
+        self.steps += 1;
+        let start = self.input.offset();
+        let before = self.state;
+        self.step_state();
+        if self.state != before {
+            self.track_attr_span(before, start);
+        }
+        if self.state != before
+            && matches!(
+            self.state,
+            State::TagOpen
+                | State::RcdataLessThanSign
+                | State::RawtextLessThanSign
+                | State::ScriptDataLessThanSign
+                | State::ScriptDataEscapedLessThanSign
+        ) {
+            self.markup_start = start;
+        }
+    }
+
+    fn step_state(&mut self) {
+        match self.state {
+            State::Data => match self.consume() {
+                Some('&') => {
+                    self.return_state = State::Data;
+                    self.state = State::CharacterReference;
+                }
+                Some('<') => self.state = State::TagOpen,
+                Some('\0') => {
+                    self.error(ErrorCode::UnexpectedNullCharacter);
+                    self.emit_char('\0');
+                }
+                Some(c) => self.emit_char(c),
+                None => self.emit_eof(),
+            },
+            State::Rcdata => match self.consume() {
+                Some('&') => {
+                    self.return_state = State::Rcdata;
+                    self.state = State::CharacterReference;
+                }
+                Some('<') => self.state = State::RcdataLessThanSign,
+                Some('\0') => {
+                    self.error(ErrorCode::UnexpectedNullCharacter);
+                    self.emit_char('\u{FFFD}');
+                }
+                Some(c) => self.emit_char(c),
+                None => self.emit_eof(),
+            },
+            State::Rawtext => match self.consume() {
+                Some('<') => self.state = State::RawtextLessThanSign,
+                Some('\0') => {
+                    self.error(ErrorCode::UnexpectedNullCharacter);
+                    self.emit_char('\u{FFFD}');
+                }
+                Some(c) => self.emit_char(c),
+                None => self.emit_eof(),
+            },
+            State::ScriptData => match self.consume() {
+                Some('<') => self.state = State::ScriptDataLessThanSign,
+                Some('\0') => {
+                    self.error(ErrorCode::UnexpectedNullCharacter);
+                    self.emit_char('\u{FFFD}');
+                }
+                Some(c) => self.emit_char(c),
+                None => self.emit_eof(),
+            },
+            State::Plaintext => match self.consume() {
+                Some('\0') => {
+                    self.error(ErrorCode::UnexpectedNullCharacter);
+                    self.emit_char('\u{FFFD}');
+                }
+                Some(c) => self.emit_char(c),
+                None => self.emit_eof(),
+            },
+            State::TagOpen => match self.consume() {
+                Some('!') => self.state = State::MarkupDeclarationOpen,
+                Some('/') => self.state = State::EndTagOpen,
+                Some(c) if c.is_ascii_alphabetic() => {
+                    self.new_tag(TagKind::Start);
+                    self.reconsume_in(State::TagName);
+                }
+                Some('?') => {
+                    self.error(ErrorCode::UnexpectedQuestionMarkInsteadOfTagName);
+                    self.comment.clear();
+                    self.reconsume_in(State::BogusComment);
+                }
+                Some(_) => {
+                    self.error(ErrorCode::InvalidFirstCharacterOfTagName);
+                    self.emit_char('<');
+                    self.reconsume_in(State::Data);
+                }
+                None => {
+                    self.error(ErrorCode::EofBeforeTagName);
+                    self.emit_char('<');
+                    self.emit_eof();
+                }
+            },
+            State::EndTagOpen => match self.consume() {
+                Some(c) if c.is_ascii_alphabetic() => {
+                    self.new_tag(TagKind::End);
+                    self.reconsume_in(State::TagName);
+                }
+                Some('>') => {
+                    self.error(ErrorCode::MissingEndTagName);
+                    self.state = State::Data;
+                }
+                Some(_) => {
+                    self.error(ErrorCode::InvalidFirstCharacterOfTagName);
+                    self.comment.clear();
+                    self.reconsume_in(State::BogusComment);
+                }
+                None => {
+                    self.error(ErrorCode::EofBeforeTagName);
+                    self.emit_str("</");
+                    self.emit_eof();
+                }
+            },
+            State::TagName => match self.consume() {
+                Some(c) if is_ascii_whitespace(c) => self.state = State::BeforeAttributeName,
+                Some('/') => self.state = State::SelfClosingStartTag,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_tag();
+                }
+                Some('\0') => {
+                    self.error(ErrorCode::UnexpectedNullCharacter);
+                    self.tag.name.push('\u{FFFD}');
+                }
+                Some(c) => self.tag.name.push(c.to_ascii_lowercase()),
+                None => {
+                    self.error(ErrorCode::EofInTag);
+                    self.emit_eof();
+                }
+            },
+            State::RcdataLessThanSign => self.less_than_sign(State::RcdataEndTagOpen, State::Rcdata),
+            State::RcdataEndTagOpen => self.end_tag_open(State::RcdataEndTagName, State::Rcdata),
+            State::RcdataEndTagName => self.end_tag_name(State::Rcdata),
+            State::RawtextLessThanSign => self.less_than_sign(State::RawtextEndTagOpen, State::Rawtext),
+            State::RawtextEndTagOpen => self.end_tag_open(State::RawtextEndTagName, State::Rawtext),
+            State::RawtextEndTagName => self.end_tag_name(State::Rawtext),
+            State::ScriptDataLessThanSign => match self.consume() {
+                Some('/') => {
+                    self.temp_buf.clear();
+                    self.state = State::ScriptDataEndTagOpen;
+                }
+                Some('!') => {
+                    self.state = State::ScriptDataEscapeStart;
+                    self.emit_str("<!");
+                }
+                _ => {
+                    self.emit_char('<');
+                    self.reconsume_in(State::ScriptData);
+                }
+            },
+            State::ScriptDataEndTagOpen => self.end_tag_open(State::ScriptDataEndTagName, State::ScriptData),
+            State::ScriptDataEndTagName => self.end_tag_name(State::ScriptData),
+            State::ScriptDataEscapeStart => match self.consume() {
+                Some('-') => {
+                    self.state = State::ScriptDataEscapeStartDash;
+                    self.emit_char('-');
+                }
+                _ => self.reconsume_in(State::ScriptData),
+            },
+            State::ScriptDataEscapeStartDash => match self.consume() {
+                Some('-') => {
+                    self.state = State::ScriptDataEscapedDashDash;
+                    self.emit_char('-');
+                }
+                _ => self.reconsume_in(State::ScriptData),
+            },
+            State::ScriptDataEscaped => match self.consume() {
+                Some('-') => {
+                    self.state = State::ScriptDataEscapedDash;
+                    self.emit_char('-');
+                }
+                Some('<') => self.state = State::ScriptDataEscapedLessThanSign,
+                Some('\0') => {
+                    self.error(ErrorCode::UnexpectedNullCharacter);
+                    self.emit_char('\u{FFFD}');
+                }
+                Some(c) => self.emit_char(c),
+                None => {
+                    self.error(ErrorCode::EofInScriptHtmlCommentLikeText);
+                    self.emit_eof();
+                }
+            },
+            State::ScriptDataEscapedDash => match self.consume() {
+                Some('-') => {
+                    self.state = State::ScriptDataEscapedDashDash;
+                    self.emit_char('-');
+                }
+                Some('<') => self.state = State::ScriptDataEscapedLessThanSign,
+                Some('\0') => {
+                    self.error(ErrorCode::UnexpectedNullCharacter);
+                    self.state = State::ScriptDataEscaped;
+                    self.emit_char('\u{FFFD}');
+                }
+                Some(c) => {
+                    self.state = State::ScriptDataEscaped;
+                    self.emit_char(c);
+                }
+                None => {
+                    self.error(ErrorCode::EofInScriptHtmlCommentLikeText);
+                    self.emit_eof();
+                }
+            },
+            State::ScriptDataEscapedDashDash => match self.consume() {
+                Some('-') => self.emit_char('-'),
+                Some('<') => self.state = State::ScriptDataEscapedLessThanSign,
+                Some('>') => {
+                    self.state = State::ScriptData;
+                    self.emit_char('>');
+                }
+                Some('\0') => {
+                    self.error(ErrorCode::UnexpectedNullCharacter);
+                    self.state = State::ScriptDataEscaped;
+                    self.emit_char('\u{FFFD}');
+                }
+                Some(c) => {
+                    self.state = State::ScriptDataEscaped;
+                    self.emit_char(c);
+                }
+                None => {
+                    self.error(ErrorCode::EofInScriptHtmlCommentLikeText);
+                    self.emit_eof();
+                }
+            },
+            State::ScriptDataEscapedLessThanSign => match self.consume() {
+                Some('/') => {
+                    self.temp_buf.clear();
+                    self.state = State::ScriptDataEscapedEndTagOpen;
+                }
+                Some(c) if c.is_ascii_alphabetic() => {
+                    self.temp_buf.clear();
+                    self.emit_char('<');
+                    self.reconsume_in(State::ScriptDataDoubleEscapeStart);
+                }
+                _ => {
+                    self.emit_char('<');
+                    self.reconsume_in(State::ScriptDataEscaped);
+                }
+            },
+            State::ScriptDataEscapedEndTagOpen => {
+                self.end_tag_open(State::ScriptDataEscapedEndTagName, State::ScriptDataEscaped)
+            }
+            State::ScriptDataEscapedEndTagName => self.end_tag_name(State::ScriptDataEscaped),
+            State::ScriptDataDoubleEscapeStart => {
+                self.double_escape_boundary(State::ScriptDataDoubleEscaped, State::ScriptDataEscaped)
+            }
+            State::ScriptDataDoubleEscaped => match self.consume() {
+                Some('-') => {
+                    self.state = State::ScriptDataDoubleEscapedDash;
+                    self.emit_char('-');
+                }
+                Some('<') => {
+                    self.state = State::ScriptDataDoubleEscapedLessThanSign;
+                    self.emit_char('<');
+                }
+                Some('\0') => {
+                    self.error(ErrorCode::UnexpectedNullCharacter);
+                    self.emit_char('\u{FFFD}');
+                }
+                Some(c) => self.emit_char(c),
+                None => {
+                    self.error(ErrorCode::EofInScriptHtmlCommentLikeText);
+                    self.emit_eof();
+                }
+            },
+            State::ScriptDataDoubleEscapedDash => match self.consume() {
+                Some('-') => {
+                    self.state = State::ScriptDataDoubleEscapedDashDash;
+                    self.emit_char('-');
+                }
+                Some('<') => {
+                    self.state = State::ScriptDataDoubleEscapedLessThanSign;
+                    self.emit_char('<');
+                }
+                Some('\0') => {
+                    self.error(ErrorCode::UnexpectedNullCharacter);
+                    self.state = State::ScriptDataDoubleEscaped;
+                    self.emit_char('\u{FFFD}');
+                }
+                Some(c) => {
+                    self.state = State::ScriptDataDoubleEscaped;
+                    self.emit_char(c);
+                }
+                None => {
+                    self.error(ErrorCode::EofInScriptHtmlCommentLikeText);
+                    self.emit_eof();
+                }
+            },
+            State::ScriptDataDoubleEscapedDashDash => match self.consume() {
+                Some('-') => self.emit_char('-'),
+                Some('<') => {
+                    self.state = State::ScriptDataDoubleEscapedLessThanSign;
+                    self.emit_char('<');
+                }
+                Some('>') => {
+                    self.state = State::ScriptData;
+                    self.emit_char('>');
+                }
+                Some('\0') => {
+                    self.error(ErrorCode::UnexpectedNullCharacter);
+                    self.state = State::ScriptDataDoubleEscaped;
+                    self.emit_char('\u{FFFD}');
+                }
+                Some(c) => {
+                    self.state = State::ScriptDataDoubleEscaped;
+                    self.emit_char(c);
+                }
+                None => {
+                    self.error(ErrorCode::EofInScriptHtmlCommentLikeText);
+                    self.emit_eof();
+                }
+            },
+            State::ScriptDataDoubleEscapedLessThanSign => match self.consume() {
+                Some('/') => {
+                    self.temp_buf.clear();
+                    self.state = State::ScriptDataDoubleEscapeEnd;
+                    self.emit_char('/');
+                }
+                _ => self.reconsume_in(State::ScriptDataDoubleEscaped),
+            },
+            State::ScriptDataDoubleEscapeEnd => {
+                self.double_escape_boundary(State::ScriptDataEscaped, State::ScriptDataDoubleEscaped)
+            }
+            State::BeforeAttributeName => match self.consume() {
+                Some(c) if is_ascii_whitespace(c) => {}
+                Some('/' | '>') | None => self.reconsume_in(State::AfterAttributeName),
+                Some('=') => {
+                    self.error(ErrorCode::UnexpectedEqualsSignBeforeAttributeName);
+                    self.start_attr();
+                    self.attr_name.push('=');
+                    self.state = State::AttributeName;
+                }
+                Some(_) => {
+                    self.start_attr();
+                    self.reconsume_in(State::AttributeName);
+                }
+            },
+            State::AttributeName => match self.consume() {
+                Some(c) if is_ascii_whitespace(c) => {
+                    self.check_duplicate_attr();
+                    self.state = State::AfterAttributeName;
+                }
+                Some('/' | '>') | None => {
+                    self.check_duplicate_attr();
+                    self.reconsume_in(State::AfterAttributeName);
+                }
+                Some('=') => {
+                    self.check_duplicate_attr();
+                    self.state = State::BeforeAttributeValue;
+                }
+                Some('\0') => {
+                    self.error(ErrorCode::UnexpectedNullCharacter);
+                    self.attr_name.push('\u{FFFD}');
+                }
+                Some(c @ ('"' | '\'' | '<')) => {
+                    self.error(ErrorCode::UnexpectedCharacterInAttributeName);
+                    self.attr_name.push(c);
+                }
+                Some(c) => self.attr_name.push(c.to_ascii_lowercase()),
+            },
+            State::AfterAttributeName => match self.consume() {
+                Some(c) if is_ascii_whitespace(c) => {}
+                Some('/') => self.state = State::SelfClosingStartTag,
+                Some('=') => self.state = State::BeforeAttributeValue,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_tag();
+                }
+                Some(_) => {
+                    self.start_attr();
+                    self.reconsume_in(State::AttributeName);
+                }
+                None => {
+                    self.error(ErrorCode::EofInTag);
+                    self.emit_eof();
+                }
+            },
+            State::BeforeAttributeValue => match self.consume() {
+                Some(c) if is_ascii_whitespace(c) => {}
+                Some('"') => self.state = State::AttributeValueDoubleQuoted,
+                Some('\'') => self.state = State::AttributeValueSingleQuoted,
+                Some('>') => {
+                    self.error(ErrorCode::MissingAttributeValue);
+                    self.state = State::Data;
+                    self.emit_tag();
+                }
+                _ => self.reconsume_in(State::AttributeValueUnquoted),
+            },
+            State::AttributeValueDoubleQuoted => self.attribute_value_quoted('"'),
+            State::AttributeValueSingleQuoted => self.attribute_value_quoted('\''),
+            State::AttributeValueUnquoted => match self.consume() {
+                Some(c) if is_ascii_whitespace(c) => self.state = State::BeforeAttributeName,
+                Some('&') => {
+                    self.return_state = State::AttributeValueUnquoted;
+                    self.state = State::CharacterReference;
+                }
+                Some('>') => {
+                    self.end_attr_value(self.input.offset() - 1);
+                    self.state = State::Data;
+                    self.emit_tag();
+                }
+                Some('\0') => {
+                    self.error(ErrorCode::UnexpectedNullCharacter);
+                    self.attr_value.push('\u{FFFD}');
+                }
+                Some(c @ ('"' | '\'' | '<' | '=' | '`')) => {
+                    self.error(ErrorCode::UnexpectedCharacterInUnquotedAttributeValue);
+                    self.attr_value.push(c);
+                }
+                Some(c) => self.attr_value.push(c),
+                None => {
+                    self.error(ErrorCode::EofInTag);
+                    self.emit_eof();
+                }
+            },
+            State::AfterAttributeValueQuoted => match self.consume() {
+                Some(c) if is_ascii_whitespace(c) => self.state = State::BeforeAttributeName,
+                Some('/') => self.state = State::SelfClosingStartTag,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_tag();
+                }
+                Some(_) => {
+                    self.error(ErrorCode::MissingWhitespaceBetweenAttributes);
+                    self.reconsume_in(State::BeforeAttributeName);
+                }
+                None => {
+                    self.error(ErrorCode::EofInTag);
+                    self.emit_eof();
+                }
+            },
+            State::SelfClosingStartTag => match self.consume() {
+                Some('>') => {
+                    self.tag.self_closing = true;
+                    self.state = State::Data;
+                    self.emit_tag();
+                }
+                Some(_) => {
+                    self.error(ErrorCode::UnexpectedSolidusInTag);
+                    self.reconsume_in(State::BeforeAttributeName);
+                }
+                None => {
+                    self.error(ErrorCode::EofInTag);
+                    self.emit_eof();
+                }
+            },
+            State::BogusComment => match self.consume() {
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_comment();
+                }
+                Some('\0') => {
+                    self.error(ErrorCode::UnexpectedNullCharacter);
+                    self.comment.push('\u{FFFD}');
+                }
+                Some(c) => self.comment.push(c),
+                None => {
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+            },
+            State::MarkupDeclarationOpen => self.markup_declaration_open(),
+            State::CommentStart => match self.consume() {
+                Some('-') => self.state = State::CommentStartDash,
+                Some('>') => {
+                    self.error(ErrorCode::AbruptClosingOfEmptyComment);
+                    self.state = State::Data;
+                    self.emit_comment();
+                }
+                _ => self.reconsume_in(State::Comment),
+            },
+            State::CommentStartDash => match self.consume() {
+                Some('-') => self.state = State::CommentEnd,
+                Some('>') => {
+                    self.error(ErrorCode::AbruptClosingOfEmptyComment);
+                    self.state = State::Data;
+                    self.emit_comment();
+                }
+                Some(_) => {
+                    self.comment.push('-');
+                    self.reconsume_in(State::Comment);
+                }
+                None => {
+                    self.error(ErrorCode::EofInComment);
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+            },
+            State::Comment => match self.consume() {
+                Some('<') => {
+                    self.comment.push('<');
+                    self.state = State::CommentLessThanSign;
+                }
+                Some('-') => self.state = State::CommentEndDash,
+                Some('\0') => {
+                    self.error(ErrorCode::UnexpectedNullCharacter);
+                    self.comment.push('\u{FFFD}');
+                }
+                Some(c) => self.comment.push(c),
+                None => {
+                    self.error(ErrorCode::EofInComment);
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+            },
+            State::CommentLessThanSign => match self.consume() {
+                Some('!') => {
+                    self.comment.push('!');
+                    self.state = State::CommentLessThanSignBang;
+                }
+                Some('<') => self.comment.push('<'),
+                _ => self.reconsume_in(State::Comment),
+            },
+            State::CommentLessThanSignBang => match self.consume() {
+                Some('-') => self.state = State::CommentLessThanSignBangDash,
+                _ => self.reconsume_in(State::Comment),
+            },
+            State::CommentLessThanSignBangDash => match self.consume() {
+                Some('-') => self.state = State::CommentLessThanSignBangDashDash,
+                _ => self.reconsume_in(State::CommentEndDash),
+            },
+            State::CommentLessThanSignBangDashDash => match self.consume() {
+                Some('>') | None => self.reconsume_in(State::CommentEnd),
+                Some(_) => {
+                    self.error(ErrorCode::NestedComment);
+                    self.reconsume_in(State::CommentEnd);
+                }
+            },
+            State::CommentEndDash => match self.consume() {
+                Some('-') => self.state = State::CommentEnd,
+                Some(_) => {
+                    self.comment.push('-');
+                    self.reconsume_in(State::Comment);
+                }
+                None => {
+                    self.error(ErrorCode::EofInComment);
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+            },
+            State::CommentEnd => match self.consume() {
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_comment();
+                }
+                Some('!') => self.state = State::CommentEndBang,
+                Some('-') => self.comment.push('-'),
+                Some(_) => {
+                    self.comment.push_str("--");
+                    self.reconsume_in(State::Comment);
+                }
+                None => {
+                    self.error(ErrorCode::EofInComment);
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+            },
+            State::CommentEndBang => match self.consume() {
+                Some('-') => {
+                    self.comment.push_str("--!");
+                    self.state = State::CommentEndDash;
+                }
+                Some('>') => {
+                    self.error(ErrorCode::IncorrectlyClosedComment);
+                    self.state = State::Data;
+                    self.emit_comment();
+                }
+                Some(_) => {
+                    self.comment.push_str("--!");
+                    self.reconsume_in(State::Comment);
+                }
+                None => {
+                    self.error(ErrorCode::EofInComment);
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+            },
+            State::Doctype => match self.consume() {
+                Some(c) if is_ascii_whitespace(c) => self.state = State::BeforeDoctypeName,
+                Some('>') => self.reconsume_in(State::BeforeDoctypeName),
+                Some(_) => {
+                    self.error(ErrorCode::MissingWhitespaceBeforeDoctypeName);
+                    self.reconsume_in(State::BeforeDoctypeName);
+                }
+                None => {
+                    self.error(ErrorCode::EofInDoctype);
+                    self.doctype = DoctypeToken {
+                        force_quirks: true,
+                        ..DoctypeToken::default()
+                    };
+                    self.emit_doctype();
+                    self.emit_eof();
+                }
+            },
+            State::BeforeDoctypeName => match self.consume() {
+                Some(c) if is_ascii_whitespace(c) => {}
+                Some('\0') => {
+                    self.error(ErrorCode::UnexpectedNullCharacter);
+                    self.doctype = DoctypeToken {
+                        name: Some("\u{FFFD}".to_string()),
+                        ..DoctypeToken::default()
+                    };
+                    self.state = State::DoctypeName;
+                }
+                Some('>') => {
+                    self.error(ErrorCode::MissingDoctypeName);
+                    self.doctype = DoctypeToken {
+                        force_quirks: true,
+                        ..DoctypeToken::default()
+                    };
+                    self.state = State::Data;
+                    self.emit_doctype();
+                }
+                Some(c) => {
+                    self.doctype = DoctypeToken {
+                        name: Some(c.to_ascii_lowercase().to_string()),
+                        ..DoctypeToken::default()
+                    };
+                    self.state = State::DoctypeName;
+                }
+                None => {
+                    self.error(ErrorCode::EofInDoctype);
+                    self.doctype = DoctypeToken {
+                        force_quirks: true,
+                        ..DoctypeToken::default()
+                    };
+                    self.emit_doctype();
+                    self.emit_eof();
+                }
+            },
+            State::DoctypeName => match self.consume() {
+                Some(c) if is_ascii_whitespace(c) => self.state = State::AfterDoctypeName,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_doctype();
+                }
+                Some('\0') => {
+                    self.error(ErrorCode::UnexpectedNullCharacter);
+                    self.doctype.name.get_or_insert_with(String::new).push('\u{FFFD}');
+                }
+                Some(c) => self
+                    .doctype
+                    .name
+                    .get_or_insert_with(String::new)
+                    .push(c.to_ascii_lowercase()),
+                None => self.eof_in_doctype(),
+            },
+            State::AfterDoctypeName => match self.consume() {
+                Some(c) if is_ascii_whitespace(c) => {}
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_doctype();
+                }
+                Some(_) => {
+                    self.input.back();
+                    if self.input.starts_with("PUBLIC", true) {
+                        self.input.skip(6);
+                        self.state = State::AfterDoctypePublicKeyword;
+                    } else if self.input.starts_with("SYSTEM", true) {
+                        self.input.skip(6);
+                        self.state = State::AfterDoctypeSystemKeyword;
+                    } else {
+                        self.consume();
+                        self.error(ErrorCode::InvalidCharacterSequenceAfterDoctypeName);
+                        self.doctype.force_quirks = true;
+                        self.reconsume_in(State::BogusDoctype);
+                    }
+                }
+                None => self.eof_in_doctype(),
+            },
+            State::AfterDoctypePublicKeyword => match self.consume() {
+                Some(c) if is_ascii_whitespace(c) => self.state = State::BeforeDoctypePublicIdentifier,
+                Some('"') => {
+                    self.error(ErrorCode::MissingWhitespaceAfterDoctypePublicKeyword);
+                    self.doctype.public_id = Some(String::new());
+                    self.state = State::DoctypePublicIdentifierDoubleQuoted;
+                }
+                Some('\'') => {
+                    self.error(ErrorCode::MissingWhitespaceAfterDoctypePublicKeyword);
+                    self.doctype.public_id = Some(String::new());
+                    self.state = State::DoctypePublicIdentifierSingleQuoted;
+                }
+                Some('>') => {
+                    self.error(ErrorCode::MissingDoctypePublicIdentifier);
+                    self.doctype.force_quirks = true;
+                    self.state = State::Data;
+                    self.emit_doctype();
+                }
+                Some(_) => {
+                    self.error(ErrorCode::MissingQuoteBeforeDoctypePublicIdentifier);
+                    self.doctype.force_quirks = true;
+                    self.reconsume_in(State::BogusDoctype);
+                }
+                None => self.eof_in_doctype(),
+            },
+            State::BeforeDoctypePublicIdentifier => match self.consume() {
+                Some(c) if is_ascii_whitespace(c) => {}
+                Some('"') => {
+                    self.doctype.public_id = Some(String::new());
+                    self.state = State::DoctypePublicIdentifierDoubleQuoted;
+                }
+                Some('\'') => {
+                    self.doctype.public_id = Some(String::new());
+                    self.state = State::DoctypePublicIdentifierSingleQuoted;
+                }
+                Some('>') => {
+                    self.error(ErrorCode::MissingDoctypePublicIdentifier);
+                    self.doctype.force_quirks = true;
+                    self.state = State::Data;
+                    self.emit_doctype();
+                }
+                Some(_) => {
+                    self.error(ErrorCode::MissingQuoteBeforeDoctypePublicIdentifier);
+                    self.doctype.force_quirks = true;
+                    self.reconsume_in(State::BogusDoctype);
+                }
+                None => self.eof_in_doctype(),
+            },
+            State::DoctypePublicIdentifierDoubleQuoted => self.doctype_identifier('"', true),
+            State::DoctypePublicIdentifierSingleQuoted => self.doctype_identifier('\'', true),
+            State::AfterDoctypePublicIdentifier => match self.consume() {
+                Some(c) if is_ascii_whitespace(c) => {
+                    self.state = State::BetweenDoctypePublicAndSystemIdentifiers
+                }
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_doctype();
+                }
+                Some('"') => {
+                    self.error(ErrorCode::MissingWhitespaceBetweenDoctypePublicAndSystemIdentifiers);
+                    self.doctype.system_id = Some(String::new());
+                    self.state = State::DoctypeSystemIdentifierDoubleQuoted;
+                }
+                Some('\'') => {
+                    self.error(ErrorCode::MissingWhitespaceBetweenDoctypePublicAndSystemIdentifiers);
+                    self.doctype.system_id = Some(String::new());
+                    self.state = State::DoctypeSystemIdentifierSingleQuoted;
+                }
+                Some(_) => {
+                    self.error(ErrorCode::MissingQuoteBeforeDoctypeSystemIdentifier);
+                    self.doctype.force_quirks = true;
+                    self.reconsume_in(State::BogusDoctype);
+                }
+                None => self.eof_in_doctype(),
+            },
+            State::BetweenDoctypePublicAndSystemIdentifiers => match self.consume() {
+                Some(c) if is_ascii_whitespace(c) => {}
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_doctype();
+                }
+                Some('"') => {
+                    self.doctype.system_id = Some(String::new());
+                    self.state = State::DoctypeSystemIdentifierDoubleQuoted;
+                }
+                Some('\'') => {
+                    self.doctype.system_id = Some(String::new());
+                    self.state = State::DoctypeSystemIdentifierSingleQuoted;
+                }
+                Some(_) => {
+                    self.error(ErrorCode::MissingQuoteBeforeDoctypeSystemIdentifier);
+                    self.doctype.force_quirks = true;
+                    self.reconsume_in(State::BogusDoctype);
+                }
+                None => self.eof_in_doctype(),
+            },
+            State::AfterDoctypeSystemKeyword => match self.consume() {
+                Some(c) if is_ascii_whitespace(c) => self.state = State::BeforeDoctypeSystemIdentifier,
+                Some('"') => {
+                    self.error(ErrorCode::MissingWhitespaceAfterDoctypeSystemKeyword);
+                    self.doctype.system_id = Some(String::new());
+                    self.state = State::DoctypeSystemIdentifierDoubleQuoted;
+                }
+                Some('\'') => {
+                    self.error(ErrorCode::MissingWhitespaceAfterDoctypeSystemKeyword);
+                    self.doctype.system_id = Some(String::new());
+                    self.state = State::DoctypeSystemIdentifierSingleQuoted;
+                }
+                Some('>') => {
+                    self.error(ErrorCode::MissingDoctypeSystemIdentifier);
+                    self.doctype.force_quirks = true;
+                    self.state = State::Data;
+                    self.emit_doctype();
+                }
+                Some(_) => {
+                    self.error(ErrorCode::MissingQuoteBeforeDoctypeSystemIdentifier);
+                    self.doctype.force_quirks = true;
+                    self.reconsume_in(State::BogusDoctype);
+                }
+                None => self.eof_in_doctype(),
+            },
+            State::BeforeDoctypeSystemIdentifier => match self.consume() {
+                Some(c) if is_ascii_whitespace(c) => {}
+                Some('"') => {
+                    self.doctype.system_id = Some(String::new());
+                    self.state = State::DoctypeSystemIdentifierDoubleQuoted;
+                }
+                Some('\'') => {
+                    self.doctype.system_id = Some(String::new());
+                    self.state = State::DoctypeSystemIdentifierSingleQuoted;
+                }
+                Some('>') => {
+                    self.error(ErrorCode::MissingDoctypeSystemIdentifier);
+                    self.doctype.force_quirks = true;
+                    self.state = State::Data;
+                    self.emit_doctype();
+                }
+                Some(_) => {
+                    self.error(ErrorCode::MissingQuoteBeforeDoctypeSystemIdentifier);
+                    self.doctype.force_quirks = true;
+                    self.reconsume_in(State::BogusDoctype);
+                }
+                None => self.eof_in_doctype(),
+            },
+            State::DoctypeSystemIdentifierDoubleQuoted => self.doctype_identifier('"', false),
+            State::DoctypeSystemIdentifierSingleQuoted => self.doctype_identifier('\'', false),
+            State::AfterDoctypeSystemIdentifier => match self.consume() {
+                Some(c) if is_ascii_whitespace(c) => {}
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_doctype();
+                }
+                Some(_) => {
+                    self.error(ErrorCode::UnexpectedCharacterAfterDoctypeSystemIdentifier);
+                    self.reconsume_in(State::BogusDoctype);
+                }
+                None => self.eof_in_doctype(),
+            },
+            State::BogusDoctype => match self.consume() {
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_doctype();
+                }
+                Some('\0') => self.error(ErrorCode::UnexpectedNullCharacter),
+                Some(_) => {}
+                None => {
+                    self.emit_doctype();
+                    self.emit_eof();
+                }
+            },
+            State::CdataSection => match self.consume() {
+                Some(']') => self.state = State::CdataSectionBracket,
+                Some(c) => self.emit_char(c),
+                None => {
+                    self.error(ErrorCode::EofInCdata);
+                    self.emit_eof();
+                }
+            },
+            State::CdataSectionBracket => match self.consume() {
+                Some(']') => self.state = State::CdataSectionEnd,
+                _ => {
+                    self.emit_char(']');
+                    self.reconsume_in(State::CdataSection);
+                }
+            },
+            State::CdataSectionEnd => match self.consume() {
+                Some(']') => self.emit_char(']'),
+                Some('>') => self.state = State::Data,
+                _ => {
+                    self.emit_str("]]");
+                    self.reconsume_in(State::CdataSection);
+                }
+            },
+            State::CharacterReference => {
+                self.temp_buf.clear();
+                self.temp_buf.push('&');
+                match self.consume() {
+                    Some(c) if c.is_ascii_alphanumeric() => self.reconsume_in(State::NamedCharacterReference),
+                    Some('#') => {
+                        self.temp_buf.push('#');
+                        self.state = State::NumericCharacterReference;
+                    }
+                    _ => {
+                        self.flush_char_ref_buf();
+                        self.reconsume_in(self.return_state);
+                    }
+                }
+            }
+            State::NamedCharacterReference => self.named_character_reference(),
+            State::AmbiguousAmpersand => match self.consume() {
+                Some(c) if c.is_ascii_alphanumeric() => {
+                    if self.char_ref_in_attribute() {
+                        self.attr_value.push(c);
+                    } else {
+                        self.emit_char(c);
+                    }
+                }
+                Some(';') => {
+                    self.error(ErrorCode::UnknownNamedCharacterReference);
+                    self.reconsume_in(self.return_state);
+                }
+                _ => self.reconsume_in(self.return_state),
+            },
+            State::NumericCharacterReference => {
+                self.char_ref_code = 0;
+                match self.consume() {
+                    Some(c @ ('x' | 'X')) => {
+                        self.temp_buf.push(c);
+                        self.state = State::HexadecimalCharacterReferenceStart;
+                    }
+                    _ => self.reconsume_in(State::DecimalCharacterReferenceStart),
+                }
+            }
+            State::HexadecimalCharacterReferenceStart => match self.consume() {
+                Some(c) if c.is_ascii_hexdigit() => self.reconsume_in(State::HexadecimalCharacterReference),
+                _ => {
+                    self.error(ErrorCode::AbsenceOfDigitsInNumericCharacterReference);
+                    self.flush_char_ref_buf();
+                    self.reconsume_in(self.return_state);
+                }
+            },
+            State::DecimalCharacterReferenceStart => match self.consume() {
+                Some(c) if c.is_ascii_digit() => self.reconsume_in(State::DecimalCharacterReference),
+                _ => {
+                    self.error(ErrorCode::AbsenceOfDigitsInNumericCharacterReference);
+                    self.flush_char_ref_buf();
+                    self.reconsume_in(self.return_state);
+                }
+            },
+            State::HexadecimalCharacterReference => self.numeric_character_reference_digits(16),
+            State::DecimalCharacterReference => self.numeric_character_reference_digits(10),
+            State::NumericCharacterReferenceEnd => {
+                let (c, error) = numeric_character_reference(self.char_ref_code);
+                if let Some(code) = error {
+                    self.error(code);
+                }
+                self.temp_buf.clear();
+                self.temp_buf.push(c);
+                self.flush_char_ref_buf();
+                self.state = self.return_state;
+            }
+        }
+    }
+
+    fn char_ref_in_attribute(&self) -> bool {
+        matches!(
+            self.return_state,
+            State::AttributeValueDoubleQuoted | State::AttributeValueSingleQuoted | State::AttributeValueUnquoted
+        )
+    }
+
+    fn flush_char_ref_buf(&mut self) {
+        let buf = std::mem::take(&mut self.temp_buf);
+        if self.char_ref_in_attribute() {
+            self.attr_value.push_str(&buf);
+        } else {
+            self.emit_str(&buf);
+        }
+    }
+
+    fn named_character_reference(&mut self) {
+        let Some((len, value)) = longest_named_match(self.input.rest()) else {
+            self.flush_char_ref_buf();
+            self.state = State::AmbiguousAmpersand;
+            return;
+        };
+        let name = &self.input.rest()[..len];
+        self.temp_buf.push_str(name);
+        let ends_with_semicolon = name.ends_with(';');
+        self.input.skip(len);
+        if !ends_with_semicolon
+            && self.char_ref_in_attribute()
+            && self
+                .input
+                .rest()
+                .starts_with(|c: char| c == '=' || c.is_ascii_alphanumeric())
+        {
+            self.flush_char_ref_buf();
+            self.state = self.return_state;
+            return;
+        }
+        if !ends_with_semicolon {
+            self.error(ErrorCode::MissingSemicolonAfterCharacterReference);
+        }
+        self.temp_buf.clear();
+        self.temp_buf.push_str(value);
+        self.flush_char_ref_buf();
+        self.state = self.return_state;
+    }
+
+    fn numeric_character_reference_digits(&mut self, radix: u32) {
+        match self.consume() {
+            Some(c) if c.is_digit(radix) => {
+                let digit = c.to_digit(radix).unwrap_or(0);
+                self.char_ref_code = self.char_ref_code.saturating_mul(radix).saturating_add(digit);
+            }
+            Some(';') => self.state = State::NumericCharacterReferenceEnd,
+            _ => {
+                self.error(ErrorCode::MissingSemicolonAfterCharacterReference);
+                self.reconsume_in(State::NumericCharacterReferenceEnd);
+            }
+        }
+    }
+
+    fn less_than_sign(&mut self, end_tag_open: State, text_state: State) {
+        match self.consume() {
+            Some('/') => {
+                self.temp_buf.clear();
+                self.state = end_tag_open;
+            }
+            _ => {
+                self.emit_char('<');
+                self.reconsume_in(text_state);
+            }
+        }
+    }
+
+    fn end_tag_open(&mut self, end_tag_name: State, text_state: State) {
+        match self.consume() {
+            Some(c) if c.is_ascii_alphabetic() => {
+                self.new_tag(TagKind::End);
+                self.reconsume_in(end_tag_name);
+            }
+            _ => {
+                self.emit_str("</");
+                self.reconsume_in(text_state);
+            }
+        }
+    }
+
+    fn end_tag_name(&mut self, text_state: State) {
+        let c = self.consume();
+        match c {
+            Some(c) if is_ascii_whitespace(c) && self.is_appropriate_end_tag() => {
+                self.state = State::BeforeAttributeName;
+                return;
+            }
+            Some('/') if self.is_appropriate_end_tag() => {
+                self.state = State::SelfClosingStartTag;
+                return;
+            }
+            Some('>') if self.is_appropriate_end_tag() => {
+                self.state = State::Data;
+                self.emit_tag();
+                return;
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                self.tag.name.push(c.to_ascii_lowercase());
+                self.temp_buf.push(c);
+                return;
+            }
+            _ => {}
+        }
+        self.text.push_str("</");
+        let buf = std::mem::take(&mut self.temp_buf);
+        self.text.push_str(&buf);
+        self.reconsume_in(text_state);
+    }
+
+    fn double_escape_boundary(&mut self, matched: State, otherwise: State) {
+        match self.consume() {
+            Some(c) if is_ascii_whitespace(c) || c == '/' || c == '>' => {
+                self.state = if self.temp_buf == "script" { matched } else { otherwise };
+                self.emit_char(c);
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                self.temp_buf.push(c.to_ascii_lowercase());
+                self.emit_char(c);
+            }
+            _ => self.reconsume_in(otherwise),
+        }
+    }
+
+    fn attribute_value_quoted(&mut self, quote: char) {
+        match self.consume() {
+            Some(c) if c == quote => self.state = State::AfterAttributeValueQuoted,
+            Some('&') => {
+                self.return_state = self.state;
+                self.state = State::CharacterReference;
+            }
+            Some('\0') => {
+                self.error(ErrorCode::UnexpectedNullCharacter);
+                self.attr_value.push('\u{FFFD}');
+            }
+            Some(c) => self.attr_value.push(c),
+            None => {
+                self.error(ErrorCode::EofInTag);
+                self.emit_eof();
+            }
+        }
+    }
+
+    fn markup_declaration_open(&mut self) {
+        if self.input.starts_with("--", false) {
+            self.input.skip(2);
+            self.comment.clear();
+            self.state = State::CommentStart;
+        } else if self.input.starts_with("DOCTYPE", true) {
+            self.input.skip(7);
+            self.state = State::Doctype;
+        } else if self.input.starts_with("[CDATA[", false) && self.cdata_allowed {
+            self.input.skip(7);
+            self.state = State::CdataSection;
+        } else if self.input.starts_with("[CDATA[", false) {
+            self.input.skip(7);
+            self.error(ErrorCode::CdataInHtmlContent);
+            self.comment.clear();
+            self.comment.push_str("[CDATA[");
+            self.state = State::BogusComment;
+        } else {
+            self.error(ErrorCode::IncorrectlyOpenedComment);
+            self.comment.clear();
+            self.state = State::BogusComment;
+        }
+    }
+
+    fn doctype_identifier(&mut self, quote: char, public: bool) {
+        let (abrupt, after) = if public {
+            (ErrorCode::AbruptDoctypePublicIdentifier, State::AfterDoctypePublicIdentifier)
+        } else {
+            (ErrorCode::AbruptDoctypeSystemIdentifier, State::AfterDoctypeSystemIdentifier)
+        };
+        let c = self.consume();
+        let id = if public {
+            self.doctype.public_id.get_or_insert_with(String::new)
+        } else {
+            self.doctype.system_id.get_or_insert_with(String::new)
+        };
+        match c {
+            Some(c) if c == quote => self.state = after,
+            Some('\0') => {
+                id.push('\u{FFFD}');
+                self.error(ErrorCode::UnexpectedNullCharacter);
+            }
+            Some('>') => {
+                self.error(abrupt);
+                self.doctype.force_quirks = true;
+                self.state = State::Data;
+                self.emit_doctype();
+            }
+            Some(c) => id.push(c),
+            None => self.eof_in_doctype(),
+        }
+    }
+
+    fn eof_in_doctype(&mut self) {
+        self.error(ErrorCode::EofInDoctype);
+        self.doctype.force_quirks = true;
+        self.emit_doctype();
+        self.emit_eof();
+    }
+}
+
+impl Iterator for Tokenizer {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        loop {
+            if let Some((token, span, attribute_spans)) = self.pending.pop_front() {
+                self.token_span = span;
+                self.token_attribute_spans = attribute_spans;
+                return Some(token);
+            }
+            if self.eof_emitted {
+                return None;
+            }
+            if !self.input_complete && self.input.rest().len() < STREAMING_LOOKAHEAD {
+                return None;
+            }
+            if self.step_limit.is_some_and(|limit| self.steps >= limit) {
+                return None;
+            }
+            self.step();
+        }
+    }
+}
+
+pub fn tokenize(input: &str) -> Vec<Token> {
+    Tokenizer::new(input).collect()
+}
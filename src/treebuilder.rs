@@ -0,0 +1,3288 @@
+use std::collections::HashMap;
+
+use crate::dom::{
+    self, Attr, Doctype, Document, DocumentFragment, Namespace, NodeData, NodeId, NodeSource, QualName, QuirksMode,
+    ShadowRoot, ShadowRootMode,
+};
+use crate::encoding::{self, Confidence, Encoding};
+use crate::sink::TreeSink;
+use crate::tokenizer::{DoctypeToken, State, Tag, Token};
+use crate::input::floor_char_boundary;
+use crate::{
+    AttributeSpan, ErrorCode, Interruption, Limit, LimitAction, LimitExceeded, Location, Options, ParseError, Span,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum InsertionMode {
+    Initial,
+    BeforeHtml,
+    BeforeHead,
+    InHead,
+    InHeadNoscript,
+    AfterHead,
+    InBody,
+    Text,
+    InTable,
+    InTableText,
+    InCaption,
+    InColumnGroup,
+    InTableBody,
+    InRow,
+    InCell,
+    InSelect,
+    InSelectInTable,
+    InTemplate,
+    AfterBody,
+    InFrameset,
+    AfterFrameset,
+    AfterAfterBody,
+    AfterAfterFrameset,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Scope {
+    Default,
+    ListItem,
+    Button,
+    Table,
+    Select,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FormattingEntry {
+    Marker,
+    Element(NodeId),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Bookmark {
+    Replace(NodeId),
+    InsertAfter(NodeId),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CharClass {
+    Whitespace,
+    Null,
+    Other,
+}
+
+const SPECIAL_HTML: &[&str] = &[
+    "address", "applet", "area", "article", "aside", "base", "basefont", "bgsound", "blockquote", "body", "br",
+    "button", "caption", "center", "col", "colgroup", "dd", "details", "dir", "div", "dl", "dt", "embed",
+    "fieldset", "figcaption", "figure", "footer", "form", "frame", "frameset", "h1", "h2", "h3", "h4", "h5", "h6",
+    "head", "header", "hgroup", "hr", "html", "iframe", "img", "input", "keygen", "li", "link", "listing", "main",
+    "marquee", "menu", "meta", "nav", "noembed", "noframes", "noscript", "object", "ol", "p", "param", "plaintext",
+    "pre", "script", "search", "section", "select", "source", "style", "summary", "table", "tbody", "td",
+    "template", "textarea", "tfoot", "th", "thead", "title", "tr", "track", "ul", "wbr", "xmp",
+];
+
+const DEFAULT_SCOPE_HTML: &[&str] = &[
+    "applet", "caption", "html", "table", "td", "th", "marquee", "object", "template",
+];
+
+const MATHML_SCOPE: &[&str] = &["mi", "mo", "mn", "ms", "mtext", "annotation-xml"];
+
+const SVG_SCOPE: &[&str] = &["foreignObject", "desc", "title"];
+
+const IMPLIED_END_TAGS: &[&str] = &["dd", "dt", "li", "optgroup", "option", "p", "rb", "rp", "rt", "rtc"];
+
+const FORMATTING: &[&str] = &[
+    "a", "b", "big", "code", "em", "font", "i", "nobr", "s", "small", "strike", "strong", "tt", "u",
+];
+
+const TABLE_SECTIONS: &[&str] = &["tbody", "tfoot", "thead"];
+
+const TABLE_FOSTER_TARGETS: &[&str] = &["table", "tbody", "tfoot", "thead", "tr"];
+
+// Built-in elements that can host a shadow root; custom elements can too.
+const SHADOW_HOSTS: &[&str] = &[
+    "article", "aside", "blockquote", "body", "div", "footer", "h1", "h2", "h3", "h4", "h5", "h6", "header", "main",
+    "nav", "p", "section", "span",
+];
+
+const RESERVED_CUSTOM_ELEMENT_NAMES: &[&str] = &[
+    "annotation-xml", "color-profile", "font-face", "font-face-src", "font-face-uri", "font-face-format",
+    "font-face-name", "missing-glyph",
+];
+
+const THOROUGH_IMPLIED_END_TAGS: &[&str] = &[
+    "caption", "colgroup", "dd", "dt", "li", "optgroup", "option", "p", "rb", "rp", "rt", "rtc", "tbody", "td",
+    "tfoot", "th", "thead", "tr",
+];
+
+// Start tags that never leave an element open for further elements, and so
+// are still accepted at `Limits::max_depth`.
+const LEAF_TAGS: &[&str] = &[
+    "area", "base", "basefont", "bgsound", "body", "br", "col", "embed", "frame", "hr", "html", "iframe", "img",
+    "input", "keygen", "link", "meta", "noembed", "noframes", "param", "plaintext", "script", "source", "style",
+    "textarea", "title", "track", "wbr", "xmp",
+];
+
+// The most nodes one token makes besides clones of formatting elements: up to
+// three implied elements and its own node.
+const NODES_PER_TOKEN: usize = 4;
+
+const HEADINGS: &[&str] = &["h1", "h2", "h3", "h4", "h5", "h6"];
+
+const BODY_END_ALLOWED_OPEN: &[&str] = &[
+    "dd", "dt", "li", "optgroup", "option", "p", "rb", "rp", "rt", "rtc", "tbody", "td", "tfoot", "th", "thead",
+    "tr", "body", "html",
+];
+
+const BREAKOUT_TAGS: &[&str] = &[
+    "b", "big", "blockquote", "body", "br", "center", "code", "dd", "div", "dl", "dt", "em", "embed", "h1", "h2",
+    "h3", "h4", "h5", "h6", "head", "hr", "i", "img", "li", "listing", "menu", "meta", "nobr", "ol", "p", "pre",
+    "ruby", "s", "small", "span", "strike", "strong", "sub", "sup", "table", "tt", "u", "ul", "var",
+];
+
+const SVG_TAG_NAMES: &[(&str, &str)] = &[
+    ("altglyph", "altGlyph"),
+    ("altglyphdef", "altGlyphDef"),
+    ("altglyphitem", "altGlyphItem"),
+    ("animatecolor", "animateColor"),
+    ("animatemotion", "animateMotion"),
+    ("animatetransform", "animateTransform"),
+    ("clippath", "clipPath"),
+    ("feblend", "feBlend"),
+    ("fecolormatrix", "feColorMatrix"),
+    ("fecomponenttransfer", "feComponentTransfer"),
+    ("fecomposite", "feComposite"),
+    ("feconvolvematrix", "feConvolveMatrix"),
+    ("fediffuselighting", "feDiffuseLighting"),
+    ("fedisplacementmap", "feDisplacementMap"),
+    ("fedistantlight", "feDistantLight"),
+    ("fedropshadow", "feDropShadow"),
+    ("feflood", "feFlood"),
+    ("fefunca", "feFuncA"),
+    ("fefuncb", "feFuncB"),
+    ("fefuncg", "feFuncG"),
+    ("fefuncr", "feFuncR"),
+    ("fegaussianblur", "feGaussianBlur"),
+    ("feimage", "feImage"),
+    ("femerge", "feMerge"),
+    ("femergenode", "feMergeNode"),
+    ("femorphology", "feMorphology"),
+    ("feoffset", "feOffset"),
+    ("fepointlight", "fePointLight"),
+    ("fespecularlighting", "feSpecularLighting"),
+    ("fespotlight", "feSpotLight"),
+    ("fetile", "feTile"),
+    ("feturbulence", "feTurbulence"),
+    ("foreignobject", "foreignObject"),
+    ("glyphref", "glyphRef"),
+    ("lineargradient", "linearGradient"),
+    ("radialgradient", "radialGradient"),
+    ("textpath", "textPath"),
+];
+
+const SVG_ATTRIBUTE_NAMES: &[(&str, &str)] = &[
+    ("attributename", "attributeName"),
+    ("attributetype", "attributeType"),
+    ("basefrequency", "baseFrequency"),
+    ("baseprofile", "baseProfile"),
+    ("calcmode", "calcMode"),
+    ("clippathunits", "clipPathUnits"),
+    ("diffuseconstant", "diffuseConstant"),
+    ("edgemode", "edgeMode"),
+    ("filterunits", "filterUnits"),
+    ("glyphref", "glyphRef"),
+    ("gradienttransform", "gradientTransform"),
+    ("gradientunits", "gradientUnits"),
+    ("kernelmatrix", "kernelMatrix"),
+    ("kernelunitlength", "kernelUnitLength"),
+    ("keypoints", "keyPoints"),
+    ("keysplines", "keySplines"),
+    ("keytimes", "keyTimes"),
+    ("lengthadjust", "lengthAdjust"),
+    ("limitingconeangle", "limitingConeAngle"),
+    ("markerheight", "markerHeight"),
+    ("markerunits", "markerUnits"),
+    ("markerwidth", "markerWidth"),
+    ("maskcontentunits", "maskContentUnits"),
+    ("maskunits", "maskUnits"),
+    ("numoctaves", "numOctaves"),
+    ("pathlength", "pathLength"),
+    ("patterncontentunits", "patternContentUnits"),
+    ("patterntransform", "patternTransform"),
+    ("patternunits", "patternUnits"),
+    ("pointsatx", "pointsAtX"),
+    ("pointsaty", "pointsAtY"),
+    ("pointsatz", "pointsAtZ"),
+    ("preservealpha", "preserveAlpha"),
+    ("preserveaspectratio", "preserveAspectRatio"),
+    ("primitiveunits", "primitiveUnits"),
+    ("refx", "refX"),
+    ("refy", "refY"),
+    ("repeatcount", "repeatCount"),
+    ("repeatdur", "repeatDur"),
+    ("requiredextensions", "requiredExtensions"),
+    ("requiredfeatures", "requiredFeatures"),
+    ("specularconstant", "specularConstant"),
+    ("specularexponent", "specularExponent"),
+    ("spreadmethod", "spreadMethod"),
+    ("startoffset", "startOffset"),
+    ("stddeviation", "stdDeviation"),
+    ("stitchtiles", "stitchTiles"),
+    ("surfacescale", "surfaceScale"),
+    ("systemlanguage", "systemLanguage"),
+    ("tablevalues", "tableValues"),
+    ("targetx", "targetX"),
+    ("targety", "targetY"),
+    ("textlength", "textLength"),
+    ("viewbox", "viewBox"),
+    ("viewtarget", "viewTarget"),
+    ("xchannelselector", "xChannelSelector"),
+    ("ychannelselector", "yChannelSelector"),
+    ("zoomandpan", "zoomAndPan"),
+];
+
+const FOREIGN_ATTRIBUTES: &[(&str, &str, &str)] = &[
+    ("xlink:actuate", "xlink", "actuate"),
+    ("xlink:arcrole", "xlink", "arcrole"),
+    ("xlink:href", "xlink", "href"),
+    ("xlink:role", "xlink", "role"),
+    ("xlink:show", "xlink", "show"),
+    ("xlink:title", "xlink", "title"),
+    ("xlink:type", "xlink", "type"),
+    ("xml:lang", "xml", "lang"),
+    ("xml:space", "xml", "space"),
+    ("xmlns", "xmlns", "xmlns"),
+    ("xmlns:xlink", "xmlns", "xlink"),
+];
+
+const QUIRKY_PUBLIC_IDS: &[&str] = &["-//W3O//DTD W3 HTML Strict 3.0//EN//", "-/W3C/DTD HTML 4.0 Transitional/EN", "HTML"];
+
+const QUIRKY_PUBLIC_PREFIXES: &[&str] = &[
+    "+//Silmaril//dtd html Pro v0r11 19970101//",
+    "-//AS//DTD HTML 3.0 asWedit + extensions//",
+    "-//AdvaSoft Ltd//DTD HTML 3.0 asWedit + extensions//",
+    "-//IETF//DTD HTML 2.0 Level 1//",
+    "-//IETF//DTD HTML 2.0 Level 2//",
+    "-//IETF//DTD HTML 2.0 Strict Level 1//",
+    "-//IETF//DTD HTML 2.0 Strict Level 2//",
+    "-//IETF//DTD HTML 2.0 Strict//",
+    "-//IETF//DTD HTML 2.0//",
+    "-//IETF//DTD HTML 2.1E//",
+    "-//IETF//DTD HTML 3.0//",
+    "-//IETF//DTD HTML 3.2 Final//",
+    "-//IETF//DTD HTML 3.2//",
+    "-//IETF//DTD HTML 3//",
+    "-//IETF//DTD HTML Level 0//",
+    "-//IETF//DTD HTML Level 1//",
+    "-//IETF//DTD HTML Level 2//",
+    "-//IETF//DTD HTML Level 3//",
+    "-//IETF//DTD HTML Strict Level 0//",
+    "-//IETF//DTD HTML Strict Level 1//",
+    "-//IETF//DTD HTML Strict Level 2//",
+    "-//IETF//DTD HTML Strict Level 3//",
+    "-//IETF//DTD HTML Strict//",
+    "-//IETF//DTD HTML//",
+    "-//Metrius//DTD Metrius Presentational//",
+    "-//Microsoft//DTD Internet Explorer 2.0 HTML Strict//",
+    "-//Microsoft//DTD Internet Explorer 2.0 HTML//",
+    "-//Microsoft//DTD Internet Explorer 2.0 Tables//",
+    "-//Microsoft//DTD Internet Explorer 3.0 HTML Strict//",
+    "-//Microsoft//DTD Internet Explorer 3.0 HTML//",
+    "-//Microsoft//DTD Internet Explorer 3.0 Tables//",
+    "-//Netscape Comm. Corp.//DTD HTML//",
+    "-//Netscape Comm. Corp.//DTD Strict HTML//",
+    "-//O'Reilly and Associates//DTD HTML 2.0//",
+    "-//O'Reilly and Associates//DTD HTML Extended 1.0//",
+    "-//O'Reilly and Associates//DTD HTML Extended Relaxed 1.0//",
+    "-//SQ//DTD HTML 2.0 HoTMetaL + extensions//",
+    "-//SoftQuad Software//DTD HoTMetaL PRO 6.0::19990601::extensions to HTML 4.0//",
+    "-//SoftQuad//DTD HoTMetaL PRO 4.0::19971010::extensions to HTML 4.0//",
+    "-//Spyglass//DTD HTML 2.0 Extended//",
+    "-//Sun Microsystems Corp.//DTD HotJava HTML//",
+    "-//Sun Microsystems Corp.//DTD HotJava Strict HTML//",
+    "-//W3C//DTD HTML 3 1995-03-24//",
+    "-//W3C//DTD HTML 3.2 Draft//",
+    "-//W3C//DTD HTML 3.2 Final//",
+    "-//W3C//DTD HTML 3.2//",
+    "-//W3C//DTD HTML 3.2S Draft//",
+    "-//W3C//DTD HTML 4.0 Frameset//",
+    "-//W3C//DTD HTML 4.0 Transitional//",
+    "-//W3C//DTD HTML Experimental 19960712//",
+    "-//W3C//DTD HTML Experimental 970421//",
+    "-//W3C//DTD W3 HTML//",
+    "-//W3O//DTD W3 HTML 3.0//",
+    "-//WebTechs//DTD Mozilla HTML 2.0//",
+    "-//WebTechs//DTD Mozilla HTML//",
+];
+
+const QUIRKY_SYSTEM_IDS: &[&str] = &["http://www.ibm.com/data/dtd/v11/ibmxhtml1-transitional.dtd"];
+
+const HTML401_PUBLIC_PREFIXES: &[&str] = &["-//W3C//DTD HTML 4.01 Frameset//", "-//W3C//DTD HTML 4.01 Transitional//"];
+
+const LIMITED_QUIRKS_PUBLIC_PREFIXES: &[&str] =
+    &["-//W3C//DTD XHTML 1.0 Frameset//", "-//W3C//DTD XHTML 1.0 Transitional//"];
+
+fn starts_with_ignore_ascii_case(s: &str, prefix: &str) -> bool {
+    s.len() >= prefix.len() && s.as_bytes()[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+}
+
+fn doctype_quirks_mode(doctype: &DoctypeToken) -> QuirksMode {
+    if doctype.force_quirks || doctype.name.as_deref() != Some("html") {
+        return QuirksMode::Quirks;
+    }
+    let public = doctype.public_id.as_deref();
+    let system = doctype.system_id.as_deref();
+    let public_is = |ids: &[&str]| public.is_some_and(|p| ids.iter().any(|id| p.eq_ignore_ascii_case(id)));
+    let public_starts = |prefixes: &[&str]| {
+        public.is_some_and(|p| prefixes.iter().any(|prefix| starts_with_ignore_ascii_case(p, prefix)))
+    };
+    if public_is(QUIRKY_PUBLIC_IDS)
+        || system.is_some_and(|s| QUIRKY_SYSTEM_IDS.iter().any(|id| s.eq_ignore_ascii_case(id)))
+        || public_starts(QUIRKY_PUBLIC_PREFIXES)
+        || (system.is_none() && public_starts(HTML401_PUBLIC_PREFIXES))
+    {
+        QuirksMode::Quirks
+    } else if public_starts(LIMITED_QUIRKS_PUBLIC_PREFIXES) || (system.is_some() && public_starts(HTML401_PUBLIC_PREFIXES))
+    {
+        QuirksMode::LimitedQuirks
+    } else {
+        QuirksMode::NoQuirks
+    }
+}
+
+fn lookup<'a>(table: &[(&str, &'a str)], name: &str) -> Option<&'a str> {
+    table.iter().find(|(from, _)| *from == name).map(|&(_, to)| to)
+}
+
+fn foreign_attrs(tag: &Tag, ns: &Namespace) -> Vec<Attr> {
+    tag.attrs
+        .iter()
+        .map(|a| {
+            let name = if let Some(&(_, prefix, local)) = FOREIGN_ATTRIBUTES.iter().find(|(n, ..)| *n == a.name) {
+                QualName {
+                    ns: Namespace::Other(prefix.to_string()),
+                    local: local.to_string(),
+                }
+            } else {
+                let local = match ns {
+                    Namespace::MathMl if a.name == "definitionurl" => "definitionURL",
+                    Namespace::Svg => lookup(SVG_ATTRIBUTE_NAMES, &a.name).unwrap_or(&a.name),
+                    _ => &a.name,
+                };
+                QualName {
+                    ns: Namespace::Html,
+                    local: local.to_string(),
+                }
+            };
+            Attr {
+                name,
+                value: a.value.clone(),
+            }
+        })
+        .collect()
+}
+
+fn is_whitespace_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\x0C' | '\r' | ' ')
+}
+
+fn char_class(c: char) -> CharClass {
+    if c == '\0' {
+        CharClass::Null
+    } else if is_whitespace_char(c) {
+        CharClass::Whitespace
+    } else {
+        CharClass::Other
+    }
+}
+
+fn split_char_runs(s: &str) -> Vec<(CharClass, &str)> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    let mut class = None;
+    for (i, c) in s.char_indices() {
+        let cls = char_class(c);
+        match class {
+            Some(prev) if prev == cls => {}
+            Some(prev) => {
+                runs.push((prev, &s[start..i]));
+                start = i;
+                class = Some(cls);
+            }
+            None => class = Some(cls),
+        }
+    }
+    if let Some(cls) = class {
+        runs.push((cls, &s[start..]));
+    }
+    runs
+}
+
+fn is_custom_element_name(local: &str) -> bool {
+    local.starts_with(|c: char| c.is_ascii_lowercase())
+        && local.contains('-')
+        && !RESERVED_CUSTOM_ELEMENT_NAMES.contains(&local)
+        && local.chars().all(|c| {
+            matches!(c,
+                '-' | '.' | '_' | '0'..='9' | 'a'..='z' | '\u{B7}' | '\u{C0}'..='\u{D6}' | '\u{D8}'..='\u{F6}'
+                | '\u{F8}'..='\u{37D}' | '\u{37F}'..='\u{1FFF}' | '\u{200C}' | '\u{200D}' | '\u{203F}' | '\u{2040}'
+                | '\u{2070}'..='\u{218F}' | '\u{2C00}'..='\u{2FEF}' | '\u{3001}'..='\u{D7FF}'
+                | '\u{F900}'..='\u{FDCF}' | '\u{FDF0}'..='\u{FFFD}' | '\u{10000}'..='\u{EFFFF}')
+        })
+}
+
+// The rules for parsing non-negative integers; `None` on failure.
+fn parse_non_negative_integer(value: &str) -> Option<u64> {
+    let value = value.trim_start_matches(is_whitespace_char);
+    let value = value.strip_prefix('+').unwrap_or(value);
+    let digits = value.bytes().take_while(u8::is_ascii_digit).count();
+    value[..digits].parse().ok()
+}
+
+fn token_attrs(tag: &Tag) -> Vec<Attr> {
+    tag.attrs
+        .iter()
+        .map(|a| Attr {
+            name: QualName {
+                ns: Namespace::Html,
+                local: a.name.clone(),
+            },
+            value: a.value.clone(),
+        })
+        .collect()
+}
+
+fn synthetic_tag(name: &str) -> Tag {
+    Tag {
+        name: name.to_string(),
+        attrs: Vec::new(),
+        self_closing: false,
+    }
+}
+
+// What a `select` shows with `Options::customizable_select`: its selected
+// option and the `selectedcontent` element that mirrors it.
+#[derive(Default)]
+struct SelectState {
+    selected: Option<NodeId>,
+    selectedcontent: Option<NodeId>,
+}
+
+pub(crate) struct TreeBuilder<S = Document> {
+    pub(crate) sink: S,
+    quirks_mode: QuirksMode,
+    opts: Options,
+    mode: InsertionMode,
+    original_mode: InsertionMode,
+    template_modes: Vec<InsertionMode>,
+    open: Vec<NodeId>,
+    context: Option<NodeId>,
+    head: Option<NodeId>,
+    form: Option<NodeId>,
+    active_formatting: Vec<FormattingEntry>,
+    // Name, attributes and start tag span of the elements in
+    // `active_formatting`, kept here so they stay available after a streaming
+    // sink has flushed the nodes. Has exactly one entry per element in the list.
+    formatting_tags: HashMap<NodeId, (QualName, Vec<Attr>, Span)>,
+    frameset_ok: bool,
+    foster_parenting: bool,
+    pending_table_text: String,
+    pending_table_text_span: Option<Span>,
+    ignore_lf: bool,
+    tokenizer_state: Option<State>,
+    location: Location,
+    token_span: Span,
+    // Start tag spans of the elements in `open`.
+    element_spans: HashMap<NodeId, Span>,
+    // The current token's, kept only with `Options::source_spans`.
+    attribute_spans: Vec<AttributeSpan>,
+    encoding: Option<Encoding>,
+    confidence: Confidence,
+    encoding_change: Option<Encoding>,
+    nodes_created: usize,
+    nodes_exhausted: bool,
+    // Start tags dropped at `Limits::max_depth`, by name, so that their end
+    // tags are dropped as well rather than closing an ancestor.
+    dropped_tags: HashMap<String, usize>,
+    selects: HashMap<NodeId, SelectState>,
+    // Options inserted with `Options::customizable_select` that have not yet
+    // been popped off the stack of open elements.
+    open_options: Vec<NodeId>,
+    aborted: Option<LimitExceeded>,
+    interrupted: Option<Interruption>,
+    errors: Vec<ParseError>,
+}
+
+impl<S: TreeSink> TreeBuilder<S> {
+    pub(crate) fn new(mut opts: Options, sink: S) -> Self {
+        opts.source_spans |= opts.preserve_source;
+        Self {
+            sink,
+            quirks_mode: QuirksMode::NoQuirks,
+            opts,
+            mode: InsertionMode::Initial,
+            original_mode: InsertionMode::Initial,
+            template_modes: Vec::new(),
+            open: Vec::new(),
+            context: None,
+            head: None,
+            form: None,
+            active_formatting: Vec::new(),
+            formatting_tags: HashMap::new(),
+            frameset_ok: true,
+            foster_parenting: false,
+            pending_table_text: String::new(),
+            pending_table_text_span: None,
+            ignore_lf: false,
+            tokenizer_state: None,
+            location: Location {
+                line: 1,
+                col: 0,
+                col_utf16: 0,
+                offset: 0,
+            },
+            token_span: Span::default(),
+            element_spans: HashMap::new(),
+            attribute_spans: Vec::new(),
+            encoding: None,
+            confidence: Confidence::Irrelevant,
+            encoding_change: None,
+            nodes_created: 0,
+            nodes_exhausted: false,
+            dropped_tags: HashMap::new(),
+            selects: HashMap::new(),
+            open_options: Vec::new(),
+            aborted: None,
+            interrupted: None,
+            errors: Vec::new(),
+        }
+    }
+
+    pub(crate) fn new_fragment(opts: Options, sink: S, ns: Namespace, local: &str) -> Self {
+        let scripting_enabled = opts.scripting_enabled;
+        let mut tb = Self::new(opts, sink);
+        let html = tb.create_element("html", Namespace::Html, Vec::new());
+        let root = tb.sink.document();
+        tb.sink.append(root, html);
+        tb.open.push(html);
+        let context = tb.create_element(local, ns.clone(), Vec::new());
+        tb.context = Some(context);
+        if ns == Namespace::Html {
+            tb.tokenizer_state = match local {
+                "title" | "textarea" => Some(State::Rcdata),
+                "style" | "xmp" | "iframe" | "noembed" | "noframes" => Some(State::Rawtext),
+                "noscript" if scripting_enabled => Some(State::Rawtext),
+                "script" => Some(State::ScriptData),
+                "plaintext" => Some(State::Plaintext),
+                _ => None,
+            };
+            if local == "template" {
+                tb.template_modes.push(InsertionMode::InTemplate);
+            }
+            if local == "form" {
+                tb.form = Some(context);
+            }
+        }
+        tb.reset_insertion_mode();
+        tb
+    }
+
+    pub(crate) fn set_position(&mut self, location: Location, span: Span, attributes: &[AttributeSpan]) {
+        self.location = location;
+        self.token_span = span;
+        if self.opts.source_spans {
+            self.attribute_spans.clear();
+            self.attribute_spans.extend_from_slice(attributes);
+        }
+    }
+
+    pub(crate) fn set_encoding(&mut self, encoding: Encoding, confidence: Confidence) {
+        self.encoding = Some(encoding);
+        self.confidence = confidence;
+    }
+
+    // Set once a `<meta>` declares an encoding that conflicts with a tentative
+    // guess; the caller is expected to stop and reparse with it.
+    pub(crate) fn encoding_change(&self) -> Option<Encoding> {
+        self.encoding_change
+    }
+
+    // Reports the longest prefix of the stack of open elements whose earlier
+    // children the remaining parse cannot touch. The adoption agency can wrap
+    // children of elements above a formatting element, foster parenting
+    // inserts before the last table, `<frameset>` can still drop the body,
+    // and `head` can be reopened until the body exists. After `</body>` the
+    // tail of the document can still land in either `body` or `html`.
+    pub(crate) fn checkpoint(&mut self) {
+        if self.context.is_some()
+            || matches!(
+                self.mode,
+                InsertionMode::AfterBody
+                    | InsertionMode::AfterAfterBody
+                    | InsertionMode::AfterFrameset
+                    | InsertionMode::AfterAfterFrameset
+            )
+        {
+            return;
+        }
+        let before_body = |mode| {
+            matches!(
+                mode,
+                InsertionMode::Initial
+                    | InsertionMode::BeforeHtml
+                    | InsertionMode::BeforeHead
+                    | InsertionMode::InHead
+                    | InsertionMode::InHeadNoscript
+                    | InsertionMode::AfterHead
+            )
+        };
+        let mut len = self.open.len();
+        if before_body(self.mode) || (self.mode == InsertionMode::Text && before_body(self.original_mode)) {
+            len = 0;
+        } else if self.frameset_ok {
+            len = len.min(1);
+        }
+        if let Some(index) = self.open.iter().position(|id| self.formatting_tags.contains_key(id)) {
+            len = len.min(index + 1);
+        }
+        if let Some(table) = self.open.iter().rposition(|&id| self.is_html_named(id, "table")) {
+            len = len.min(table);
+        }
+        self.sink.checkpoint(&self.open[..len]);
+    }
+
+    pub(crate) fn take_tokenizer_state(&mut self) -> Option<State> {
+        self.tokenizer_state.take()
+    }
+
+    pub(crate) fn take_errors(&mut self) -> Vec<ParseError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    fn error(&mut self, code: ErrorCode) {
+        self.errors.push(ParseError {
+            code,
+            location: self.location.clone(),
+            span: self.token_span,
+            secondary: None,
+        });
+    }
+
+    // Reports going over one of `Options::limits`. Returns false when the
+    // parse aborts instead, in which case the caller drops what it was doing.
+    fn exceed(&mut self, limit: Limit) -> bool {
+        if self.opts.limits.on_exceeded == LimitAction::Abort {
+            if self.aborted.is_none() {
+                self.aborted = Some(LimitExceeded {
+                    limit,
+                    location: self.location.clone(),
+                    span: self.token_span,
+                });
+            }
+            return false;
+        }
+        self.error(limit.error_code());
+        true
+    }
+
+    // Whether `count` more nodes fit within `Limits::max_nodes`. The first time
+    // they do not, the budget counts as spent and the rest of the input is
+    // ignored.
+    fn room_for_nodes(&mut self, count: usize) -> bool {
+        if self.nodes_exhausted {
+            return false;
+        }
+        if self.opts.limits.max_nodes.is_some_and(|max| self.nodes_created + count > max) {
+            self.nodes_exhausted = true;
+            self.exceed(Limit::Nodes);
+            return false;
+        }
+        true
+    }
+
+    pub(crate) fn aborted(&self) -> Option<&LimitExceeded> {
+        self.aborted.as_ref()
+    }
+
+    // Ends the parse where it stands, as though the input stopped here, so the
+    // tree is as complete as end of file would leave it.
+    pub(crate) fn interrupt(&mut self, reason: Interruption) {
+        self.interrupted = Some(reason);
+        self.process_token(Token::Eof);
+    }
+
+    pub(crate) fn interrupted(&self) -> Option<Interruption> {
+        self.interrupted
+    }
+
+    // Reports an error about `node` being left open, pointing the secondary
+    // span at the start tag that created it.
+    fn error_unclosed(&mut self, code: ErrorCode, node: NodeId) {
+        self.error(code);
+        let secondary = self.element_spans.get(&node).copied();
+        if let Some(err) = self.errors.last_mut() {
+            err.secondary = secondary;
+        }
+    }
+
+    // ---- element and stack helpers ----
+
+    fn name(&self, id: NodeId) -> Option<&QualName> {
+        self.sink.element_name(id)
+    }
+
+    fn is_html_named(&self, id: NodeId, local: &str) -> bool {
+        self.name(id)
+            .is_some_and(|n| n.ns == Namespace::Html && n.local == local)
+    }
+
+    fn is_html_one_of(&self, id: NodeId, names: &[&str]) -> bool {
+        self.name(id)
+            .is_some_and(|n| n.ns == Namespace::Html && names.contains(&n.local.as_str()))
+    }
+
+    fn is_special(&self, id: NodeId) -> bool {
+        match self.name(id) {
+            Some(QualName {
+                ns: Namespace::Html,
+                local,
+            }) => SPECIAL_HTML.contains(&local.as_str()),
+            Some(QualName {
+                ns: Namespace::MathMl,
+                local,
+            }) => MATHML_SCOPE.contains(&local.as_str()),
+            Some(QualName { ns: Namespace::Svg, local }) => SVG_SCOPE.contains(&local.as_str()),
+            _ => false,
+        }
+    }
+
+    fn adjusted_current(&self) -> Option<NodeId> {
+        match self.context {
+            Some(context) if self.open.len() == 1 => Some(context),
+            _ => self.open.last().copied(),
+        }
+    }
+
+    pub(crate) fn cdata_allowed(&self) -> bool {
+        self.adjusted_current()
+            .and_then(|id| self.name(id))
+            .is_some_and(|n| n.ns != Namespace::Html)
+    }
+
+    fn is_mathml_text_integration_point(&self, id: NodeId) -> bool {
+        self.name(id).is_some_and(|n| {
+            n.ns == Namespace::MathMl && matches!(n.local.as_str(), "mi" | "mo" | "mn" | "ms" | "mtext")
+        })
+    }
+
+    fn is_html_integration_point(&self, id: NodeId) -> bool {
+        let Some(name) = self.name(id) else {
+            return false;
+        };
+        let attrs = self.sink.element_attrs(id);
+        match name.ns {
+            Namespace::MathMl => {
+                name.local == "annotation-xml"
+                    && attrs.iter().any(|a| {
+                        a.name.ns == Namespace::Html
+                            && a.name.local == "encoding"
+                            && (a.value.eq_ignore_ascii_case("text/html")
+                                || a.value.eq_ignore_ascii_case("application/xhtml+xml"))
+                    })
+            }
+            Namespace::Svg => matches!(name.local.as_str(), "foreignObject" | "desc" | "title"),
+            _ => false,
+        }
+    }
+
+    fn current(&self) -> NodeId {
+        self.open.last().copied().unwrap_or_else(|| self.sink.document())
+    }
+
+    fn current_is(&self, local: &str) -> bool {
+        self.open.last().is_some_and(|&id| self.is_html_named(id, local))
+    }
+
+    fn current_is_one_of(&self, names: &[&str]) -> bool {
+        self.open.last().is_some_and(|&id| self.is_html_one_of(id, names))
+    }
+
+    fn is_scope_boundary(&self, id: NodeId, scope: Scope) -> bool {
+        let Some(name) = self.name(id) else {
+            return false;
+        };
+        let local = name.local.as_str();
+        match scope {
+            Scope::Select => !(name.ns == Namespace::Html && matches!(local, "optgroup" | "option")),
+            Scope::Table => name.ns == Namespace::Html && matches!(local, "html" | "table" | "template"),
+            Scope::Default | Scope::ListItem | Scope::Button => {
+                let base = match name.ns {
+                    Namespace::Html => DEFAULT_SCOPE_HTML.contains(&local),
+                    Namespace::MathMl => MATHML_SCOPE.contains(&local),
+                    Namespace::Svg => SVG_SCOPE.contains(&local),
+                    Namespace::Other(_) => false,
+                };
+                base || match scope {
+                    Scope::ListItem => name.ns == Namespace::Html && matches!(local, "ol" | "ul"),
+                    Scope::Button => name.ns == Namespace::Html && local == "button",
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    fn in_scope_where(&self, scope: Scope, matches: impl Fn(&Self, NodeId) -> bool) -> bool {
+        for &id in self.open.iter().rev() {
+            if matches(self, id) {
+                return true;
+            }
+            if self.is_scope_boundary(id, scope) {
+                return false;
+            }
+        }
+        false
+    }
+
+    fn in_scope(&self, local: &str, scope: Scope) -> bool {
+        self.in_scope_where(scope, |tb, id| tb.is_html_named(id, local))
+    }
+
+    fn node_in_scope(&self, node: NodeId, scope: Scope) -> bool {
+        self.in_scope_where(scope, |_, id| id == node)
+    }
+
+    fn pop(&mut self) -> Option<NodeId> {
+        let id = self.open.pop()?;
+        self.popped(id);
+        Some(id)
+    }
+
+    fn truncate_open(&mut self, len: usize) {
+        while self.open.len() > len {
+            self.pop();
+        }
+    }
+
+    // Called for every element that leaves the stack of open elements.
+    fn popped(&mut self, id: NodeId) {
+        self.element_spans.remove(&id);
+        // A closed select takes no more options, so only open ones are kept.
+        self.selects.remove(&id);
+        if let Some(i) = self.open_options.iter().rposition(|&option| option == id) {
+            self.open_options.remove(i);
+            self.option_popped(id);
+        }
+    }
+
+    // Pops up to the `select` a `<select>` start tag ends as `</select>`
+    // would, crediting the tag as its end tag.
+    fn end_select_early(&mut self) {
+        while let Some(id) = self.pop() {
+            if self.is_html_named(id, "select") {
+                self.record_end_tag(id);
+                break;
+            }
+        }
+    }
+
+    fn pop_until_named(&mut self, local: &str) {
+        while let Some(id) = self.pop() {
+            if self.is_html_named(id, local) {
+                break;
+            }
+        }
+    }
+
+    fn pop_until_one_of(&mut self, names: &[&str]) {
+        while let Some(id) = self.pop() {
+            if self.is_html_one_of(id, names) {
+                break;
+            }
+        }
+    }
+
+    fn remove_from_stack(&mut self, node: NodeId) {
+        if let Some(pos) = self.open.iter().rposition(|&id| id == node) {
+            self.open.remove(pos);
+            self.popped(node);
+        }
+    }
+
+    fn generate_implied_end_tags(&mut self, except: Option<&str>) {
+        while let Some(&id) = self.open.last() {
+            if !self.is_html_one_of(id, IMPLIED_END_TAGS) || except.is_some_and(|e| self.is_html_named(id, e)) {
+                break;
+            }
+            self.pop();
+        }
+    }
+
+    fn has_template_on_stack(&self) -> bool {
+        self.open.iter().any(|&id| self.is_html_named(id, "template"))
+    }
+
+    fn generate_all_implied_end_tags(&mut self) {
+        while self.current_is_one_of(THOROUGH_IMPLIED_END_TAGS) {
+            self.pop();
+        }
+    }
+
+    fn close_p_element(&mut self) {
+        self.generate_implied_end_tags(Some("p"));
+        if !self.current_is("p") {
+            self.error(ErrorCode::UnexpectedEndTag);
+        }
+        self.pop_until_named("p");
+    }
+
+    fn close_p_if_in_button_scope(&mut self) {
+        if self.in_scope("p", Scope::Button) {
+            self.close_p_element();
+        }
+    }
+
+    // ---- active formatting elements ----
+
+    fn formatting_position(&self, node: NodeId) -> Option<usize> {
+        self.active_formatting
+            .iter()
+            .rposition(|&e| e == FormattingEntry::Element(node))
+    }
+
+    fn remove_formatting(&mut self, node: NodeId) {
+        if let Some(pos) = self.formatting_position(node) {
+            self.active_formatting.remove(pos);
+            self.formatting_tags.remove(&node);
+        }
+    }
+
+    fn replace_formatting(&mut self, index: usize, new: NodeId) {
+        if let FormattingEntry::Element(old) = self.active_formatting[index] {
+            self.formatting_tags.remove(&old);
+        }
+        self.active_formatting[index] = FormattingEntry::Element(new);
+    }
+
+    fn formatting_after_marker(&self, local: &str) -> Option<NodeId> {
+        for &entry in self.active_formatting.iter().rev() {
+            match entry {
+                FormattingEntry::Marker => return None,
+                FormattingEntry::Element(id)
+                    if self
+                        .formatting_tags
+                        .get(&id)
+                        .is_some_and(|(n, ..)| n.ns == Namespace::Html && n.local == local) =>
+                {
+                    return Some(id);
+                }
+                FormattingEntry::Element(_) => {}
+            }
+        }
+        None
+    }
+
+    fn same_element(&self, a: NodeId, name_b: &QualName, attrs_b: &[Attr]) -> bool {
+        let Some((name_a, attrs_a, _)) = self.formatting_tags.get(&a) else {
+            return false;
+        };
+        name_a == name_b && attrs_a.len() == attrs_b.len() && attrs_a.iter().all(|attr| attrs_b.contains(attr))
+    }
+
+    fn push_formatting(&mut self, node: NodeId) {
+        let Some(name) = self.name(node).cloned() else {
+            return;
+        };
+        let attrs = self.sink.element_attrs(node).to_vec();
+        let mut matching = Vec::new();
+        for (i, &entry) in self.active_formatting.iter().enumerate().rev() {
+            match entry {
+                FormattingEntry::Marker => break,
+                FormattingEntry::Element(id) if self.same_element(id, &name, &attrs) => matching.push(i),
+                FormattingEntry::Element(_) => {}
+            }
+        }
+        let span = self.element_spans.get(&node).copied().unwrap_or_default();
+        self.formatting_tags.insert(node, (name, attrs, span));
+        if matching.len() >= 3
+            && let Some(&earliest) = matching.last()
+            && let FormattingEntry::Element(evicted) = self.active_formatting.remove(earliest)
+        {
+            self.formatting_tags.remove(&evicted);
+        }
+        self.active_formatting.push(FormattingEntry::Element(node));
+    }
+
+    fn clear_formatting_to_marker(&mut self) {
+        while let Some(entry) = self.active_formatting.pop() {
+            match entry {
+                FormattingEntry::Marker => break,
+                FormattingEntry::Element(id) => {
+                    self.formatting_tags.remove(&id);
+                }
+            }
+        }
+    }
+
+    fn reconstruct_formatting(&mut self) {
+        let Some(&last) = self.active_formatting.last() else {
+            return;
+        };
+        let open_or_marker = |tb: &Self, entry: FormattingEntry| match entry {
+            FormattingEntry::Marker => true,
+            FormattingEntry::Element(id) => tb.open.contains(&id),
+        };
+        if open_or_marker(self, last) {
+            return;
+        }
+        let mut i = self.active_formatting.len() - 1;
+        while i > 0 && !open_or_marker(self, self.active_formatting[i - 1]) {
+            i -= 1;
+        }
+        for j in i..self.active_formatting.len() {
+            // Leave room for the node the current token inserts after these.
+            if self.opts.limits.max_depth.is_some_and(|max| self.open.len() >= max) || !self.room_for_nodes(2) {
+                break;
+            }
+            let FormattingEntry::Element(old) = self.active_formatting[j] else {
+                continue;
+            };
+            let new = self.clone_element(old);
+            let place = self.appropriate_place(None);
+            self.insert_at(place, new);
+            self.open.push(new);
+            self.replace_formatting(j, new);
+        }
+    }
+
+    // Returns false when the end tag should instead be handled by the
+    // "any other end tag" steps.
+    fn adoption_agency(&mut self, subject: &str) -> bool {
+        let current = self.current();
+        if self.is_html_named(current, subject) && self.formatting_position(current).is_none() {
+            self.pop();
+            return true;
+        }
+        for _ in 0..8 {
+            let Some(formatting) = self.formatting_after_marker(subject) else {
+                return false;
+            };
+            let Some(fe_index) = self.open.iter().rposition(|&id| id == formatting) else {
+                self.error(ErrorCode::AdoptionAgency1_1);
+                self.remove_formatting(formatting);
+                return true;
+            };
+            if !self.node_in_scope(formatting, Scope::Default) {
+                self.error(ErrorCode::AdoptionAgency1_2);
+                return true;
+            }
+            if formatting != self.current() {
+                self.error_unclosed(ErrorCode::AdoptionAgency1_3, self.current());
+            }
+            let Some(fb_index) = (fe_index + 1..self.open.len()).find(|&i| self.is_special(self.open[i])) else {
+                self.truncate_open(fe_index);
+                self.remove_formatting(formatting);
+                return true;
+            };
+            // The inner loop clones up to three formatting elements, then the
+            // formatting element itself is cloned; a start tag that ran this
+            // then inserts its own element.
+            let clones = (fe_index + 1..fb_index)
+                .rev()
+                .take(3)
+                .filter(|&i| self.formatting_position(self.open[i]).is_some())
+                .count();
+            if !self.room_for_nodes(clones + 2) {
+                return true;
+            }
+            let furthest_block = self.open[fb_index];
+            let common_ancestor = self.open[fe_index - 1];
+            let mut bookmark = Bookmark::Replace(formatting);
+            let mut node_index = fb_index;
+            let mut last_node = furthest_block;
+            let mut inner = 0;
+            loop {
+                inner += 1;
+                node_index -= 1;
+                let node = self.open[node_index];
+                if node == formatting {
+                    break;
+                }
+                if inner > 3 && self.formatting_position(node).is_some() {
+                    self.remove_formatting(node);
+                }
+                let Some(afe_index) = self.formatting_position(node) else {
+                    self.open.remove(node_index);
+                    self.popped(node);
+                    continue;
+                };
+                let new = self.clone_element(node);
+                self.replace_formatting(afe_index, new);
+                self.open[node_index] = new;
+                self.popped(node);
+                if last_node == furthest_block {
+                    bookmark = Bookmark::InsertAfter(new);
+                }
+                self.sink.append(new, last_node);
+                last_node = new;
+            }
+            let place = self.appropriate_place(Some(common_ancestor));
+            self.insert_at(place, last_node);
+            let new = self.clone_element(formatting);
+            self.sink.reparent_children(furthest_block, new);
+            self.sink.append(furthest_block, new);
+            match bookmark {
+                Bookmark::Replace(old) => {
+                    if let Some(i) = self.formatting_position(old) {
+                        self.replace_formatting(i, new);
+                    }
+                }
+                Bookmark::InsertAfter(prev) => {
+                    if let Some(i) = self.formatting_position(prev) {
+                        self.active_formatting.insert(i + 1, FormattingEntry::Element(new));
+                    }
+                    self.remove_formatting(formatting);
+                }
+            }
+            self.remove_from_stack(formatting);
+            if let Some(i) = self.open.iter().position(|&id| id == furthest_block) {
+                self.open.insert(i + 1, new);
+            }
+        }
+        true
+    }
+
+    // ---- insertion helpers ----
+
+    fn appropriate_place(&mut self, override_target: Option<NodeId>) -> (NodeId, Option<NodeId>) {
+        let target = override_target.unwrap_or_else(|| self.current());
+        let (parent, before) = if self.foster_parenting && self.is_html_one_of(target, TABLE_FOSTER_TARGETS) {
+            self.foster_parent_place()
+        } else {
+            (target, None)
+        };
+        // A table's parent is only ever a template for sinks that keep
+        // template contents inline, and then the table is already inside them.
+        if before.is_none() && self.is_html_named(parent, "template") {
+            return (self.sink.template_contents(parent), None);
+        }
+        (parent, before)
+    }
+
+    fn foster_parent_place(&self) -> (NodeId, Option<NodeId>) {
+        let last_template = self.open.iter().rposition(|&id| self.is_html_named(id, "template"));
+        let last_table = self.open.iter().rposition(|&id| self.is_html_named(id, "table"));
+        match (last_template, last_table) {
+            (Some(template), Some(table)) if template > table => (self.open[template], None),
+            (Some(template), None) => (self.open[template], None),
+            (_, None) => (self.open[0], None),
+            (_, Some(table_index)) => {
+                let table = self.open[table_index];
+                match self.sink.parent(table) {
+                    Some(parent) => (parent, Some(table)),
+                    None => (self.open[table_index - 1], None),
+                }
+            }
+        }
+    }
+
+    fn insert_at(&mut self, (parent, before): (NodeId, Option<NodeId>), node: NodeId) {
+        self.sink.insert_before(parent, node, before);
+    }
+
+    fn record_source(&mut self, node: NodeId, source: NodeSource) {
+        if self.opts.source_spans {
+            self.sink.record_source(node, source);
+        }
+    }
+
+    // Records `element` as written by the current start tag, with the first
+    // `count` of its attributes.
+    fn record_start_tag(&mut self, element: NodeId, count: usize) {
+        let attributes = self.attribute_spans.iter().take(count).copied().collect();
+        self.record_source(
+            element,
+            NodeSource {
+                start_tag: Some(self.token_span),
+                attributes,
+                ..NodeSource::default()
+            },
+        );
+    }
+
+    fn record_synthetic(&mut self, element: NodeId) {
+        self.record_source(
+            element,
+            NodeSource {
+                synthetic: true,
+                ..NodeSource::default()
+            },
+        );
+    }
+
+    fn record_end_tag(&mut self, element: NodeId) {
+        self.record_source(
+            element,
+            NodeSource {
+                end_tag: Some(self.token_span),
+                ..NodeSource::default()
+            },
+        );
+    }
+
+    // Credits an end tag to the innermost element of its name that processing
+    // it took off the stack of open elements; any heading closes any other.
+    fn record_closed_by(&mut self, name: &str, open_before: &[NodeId]) {
+        let heading = |local: &str| HEADINGS.iter().any(|h| local.eq_ignore_ascii_case(h));
+        let closed = open_before.iter().rev().copied().find(|id| {
+            !self.open.contains(id)
+                && self
+                    .sink
+                    .element_name(*id)
+                    .is_some_and(|n| n.local.eq_ignore_ascii_case(name) || heading(&n.local) && heading(name))
+        });
+        if let Some(element) = closed {
+            self.record_end_tag(element);
+        }
+    }
+
+    fn create_element(&mut self, local: &str, ns: Namespace, attrs: Vec<Attr>) -> NodeId {
+        self.nodes_created += 1;
+        self.sink.create_element(
+            QualName {
+                ns,
+                local: local.to_string(),
+            },
+            attrs,
+        )
+    }
+
+    // Only entries of the active formatting list are ever cloned.
+    fn clone_element(&mut self, node: NodeId) -> NodeId {
+        let (name, attrs, span) = self.formatting_tags[&node].clone();
+        self.nodes_created += 1;
+        let id = self.sink.create_element(name.clone(), attrs.clone());
+        self.formatting_tags.insert(id, (name, attrs, span));
+        self.element_spans.insert(id, span);
+        self.record_synthetic(id);
+        id
+    }
+
+    fn insert_html_element(&mut self, tag: &Tag) -> NodeId {
+        let id = self.create_element(&tag.name, Namespace::Html, token_attrs(tag));
+        self.element_spans.insert(id, self.token_span);
+        self.record_start_tag(id, tag.attrs.len());
+        self.insert_created(id)
+    }
+
+    fn insert_created(&mut self, id: NodeId) -> NodeId {
+        let place = self.appropriate_place(None);
+        self.insert_at(place, id);
+        self.open.push(id);
+        id
+    }
+
+    // A declarative shadow root's template is only pushed onto the stack, so
+    // that its contents go into the shadow root and its end tag closes it.
+    fn insert_template(&mut self, tag: &Tag) {
+        let id = self.create_element("template", Namespace::Html, token_attrs(tag));
+        self.element_spans.insert(id, self.token_span);
+        self.record_start_tag(id, tag.attrs.len());
+        let host = self.adjusted_current().unwrap_or(id);
+        match self.declarative_shadow_root(tag, host) {
+            Some(shadow) if self.sink.attach_shadow_root(host, id, shadow) => self.open.push(id),
+            _ => {
+                self.insert_created(id);
+                self.sink.template_contents(id);
+            }
+        }
+    }
+
+    // What `tag` declares for `host`, if it is a `<template shadowrootmode>`
+    // that may give it one. The `html` element never is a host, but the
+    // context element of a fragment can be.
+    fn declarative_shadow_root(&self, tag: &Tag, host: NodeId) -> Option<ShadowRoot> {
+        if !self.opts.declarative_shadow_roots || self.open.first() == Some(&host) {
+            return None;
+        }
+        let attr = |name: &str| tag.attrs.iter().find(|a| a.name == name).map(|a| a.value.as_str());
+        let mode = match attr("shadowrootmode") {
+            Some(mode) if mode.eq_ignore_ascii_case("open") => ShadowRootMode::Open,
+            Some(mode) if mode.eq_ignore_ascii_case("closed") => ShadowRootMode::Closed,
+            _ => return None,
+        };
+        let name = self.name(host)?;
+        if name.ns != Namespace::Html
+            || !(SHADOW_HOSTS.contains(&name.local.as_str()) || is_custom_element_name(&name.local))
+        {
+            return None;
+        }
+        Some(ShadowRoot {
+            mode,
+            delegates_focus: attr("shadowrootdelegatesfocus").is_some(),
+            clonable: attr("shadowrootclonable").is_some(),
+            serializable: attr("shadowrootserializable").is_some(),
+        })
+    }
+
+    fn insert_foreign_element(&mut self, tag: &Tag, ns: Namespace) -> NodeId {
+        let local = match ns {
+            Namespace::Svg => lookup(SVG_TAG_NAMES, &tag.name).unwrap_or(&tag.name),
+            _ => &tag.name,
+        };
+        let attrs = foreign_attrs(tag, &ns);
+        let id = self.create_element(local, ns, attrs);
+        self.element_spans.insert(id, self.token_span);
+        self.record_start_tag(id, tag.attrs.len());
+        self.insert_created(id);
+        if tag.self_closing {
+            self.pop();
+        }
+        id
+    }
+
+    fn insert_synthetic(&mut self, name: &str) -> NodeId {
+        let id = self.create_element(name, Namespace::Html, Vec::new());
+        self.record_synthetic(id);
+        self.insert_created(id)
+    }
+
+    fn insert_text(&mut self, mut text: &str) {
+        let (parent, before) = self.appropriate_place(None);
+        if parent == self.sink.document() {
+            return;
+        }
+        let existing = self.sink.preceding_text(parent, before).map(str::len);
+        if let Some(max) = self.opts.limits.max_text_length {
+            let room = max.saturating_sub(existing.unwrap_or(0));
+            if text.len() > room {
+                if !self.exceed(Limit::TextLength) {
+                    return;
+                }
+                text = &text[..floor_char_boundary(text, room)];
+                if text.is_empty() {
+                    return;
+                }
+            }
+        }
+        if existing.is_none() {
+            self.nodes_created += 1;
+        }
+        let id = self.sink.insert_text(parent, before, text);
+        self.record_data(id, self.pending_table_text_span.unwrap_or(self.token_span));
+    }
+
+    fn record_data(&mut self, node: NodeId, span: Span) {
+        self.record_source(
+            node,
+            NodeSource {
+                data: Some(span),
+                ..NodeSource::default()
+            },
+        );
+    }
+
+    fn insert_comment(&mut self, data: String, place: Option<(NodeId, Option<NodeId>)>) {
+        self.nodes_created += 1;
+        let id = self.sink.create_comment(data);
+        self.record_data(id, self.token_span);
+        let place = place.unwrap_or_else(|| self.appropriate_place(None));
+        self.insert_at(place, id);
+    }
+
+    fn set_quirks_mode(&mut self, mode: QuirksMode) {
+        self.quirks_mode = mode;
+        self.sink.set_quirks_mode(mode);
+    }
+
+    fn add_missing_attrs(&mut self, element: NodeId, tag: &Tag) {
+        let existing = self.sink.element_attrs(element);
+        let (mut attrs, mut attributes): (Vec<Attr>, Vec<AttributeSpan>) = token_attrs(tag)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, attr)| !existing.iter().any(|a| a.name == attr.name))
+            .map(|(i, attr)| (attr, self.attribute_spans.get(i).copied().unwrap_or_default()))
+            .unzip();
+        if let Some(max) = self.opts.limits.max_attributes {
+            let room = max.saturating_sub(existing.len());
+            if attrs.len() > room {
+                if !self.exceed(Limit::Attributes) {
+                    return;
+                }
+                attrs.truncate(room);
+                attributes.truncate(room);
+            }
+        }
+        self.sink.add_attrs_if_missing(element, attrs);
+        self.record_source(
+            element,
+            NodeSource {
+                attributes,
+                ..NodeSource::default()
+            },
+        );
+    }
+
+    fn parse_text_element(&mut self, tag: &Tag, state: State) {
+        self.insert_html_element(tag);
+        self.tokenizer_state = Some(state);
+        self.original_mode = self.mode;
+        self.mode = InsertionMode::Text;
+    }
+
+    fn reset_insertion_mode(&mut self) {
+        for (i, &node) in self.open.iter().enumerate().rev() {
+            let last = i == 0;
+            let node = match self.context {
+                Some(context) if last => context,
+                _ => node,
+            };
+            let Some(name) = self.name(node) else {
+                continue;
+            };
+            if name.ns != Namespace::Html {
+                if last {
+                    self.mode = InsertionMode::InBody;
+                    return;
+                }
+                continue;
+            }
+            let mode = match name.local.as_str() {
+                "select" if !self.opts.customizable_select => Some(self.select_mode_for(i)),
+                "td" | "th" if !last => Some(InsertionMode::InCell),
+                "tr" => Some(InsertionMode::InRow),
+                "tbody" | "thead" | "tfoot" => Some(InsertionMode::InTableBody),
+                "caption" => Some(InsertionMode::InCaption),
+                "template" => self.template_modes.last().copied(),
+                "colgroup" => Some(InsertionMode::InColumnGroup),
+                "table" => Some(InsertionMode::InTable),
+                "head" if !last => Some(InsertionMode::InHead),
+                "body" => Some(InsertionMode::InBody),
+                "frameset" => Some(InsertionMode::InFrameset),
+                "html" => Some(if self.head.is_none() {
+                    InsertionMode::BeforeHead
+                } else {
+                    InsertionMode::AfterHead
+                }),
+                _ if last => Some(InsertionMode::InBody),
+                _ => None,
+            };
+            if let Some(mode) = mode {
+                self.mode = mode;
+                return;
+            }
+        }
+        self.mode = InsertionMode::InBody;
+    }
+
+    fn select_mode_for(&self, select_index: usize) -> InsertionMode {
+        for &ancestor in self.open[..select_index].iter().rev() {
+            if self.is_html_named(ancestor, "template") {
+                break;
+            }
+            if self.is_html_named(ancestor, "table") {
+                return InsertionMode::InSelectInTable;
+            }
+        }
+        InsertionMode::InSelect
+    }
+
+    fn context_is(&self, local: &str) -> bool {
+        self.context.is_some_and(|context| self.is_html_named(context, local))
+    }
+
+    // ---- customizable select ----
+
+    // The select an option belongs to: its nearest select ancestor, unless a
+    // datalist, another option or a second optgroup comes first.
+    fn option_select(&self, option: NodeId) -> Option<NodeId> {
+        let mut optgroup = false;
+        let mut node = self.sink.parent(option);
+        while let Some(id) = node {
+            if let Some(name) = self.name(id).filter(|n| n.ns == Namespace::Html) {
+                match name.local.as_str() {
+                    "select" => return Some(id),
+                    "datalist" | "option" => return None,
+                    "optgroup" if optgroup => return None,
+                    "optgroup" => optgroup = true,
+                    _ => {}
+                }
+            }
+            node = self.sink.parent(id);
+        }
+        None
+    }
+
+    fn has_attr(&self, element: NodeId, local: &str) -> bool {
+        self.sink
+            .element_attrs(element)
+            .iter()
+            .any(|a| a.name.ns == Namespace::Html && a.name.local == local)
+    }
+
+    // Only a select without `multiple` shows a single option, and only one
+    // with a display size of 1 selects its first option by itself.
+    fn single_select(&self, select: NodeId) -> Option<bool> {
+        if self.has_attr(select, "multiple") {
+            return None;
+        }
+        let size = self
+            .sink
+            .element_attrs(select)
+            .iter()
+            .find(|a| a.name.ns == Namespace::Html && a.name.local == "size")
+            .and_then(|a| parse_non_negative_integer(&a.value));
+        Some(size.is_none_or(|size| size == 1))
+    }
+
+    // Runs the selectedness rules for a newly inserted option: one with a
+    // `selected` attribute takes over, and otherwise the first enabled option
+    // of a drop-down is selected.
+    fn option_inserted(&mut self, option: NodeId, tag: &Tag) {
+        self.open_options.push(option);
+        let Some(select) = self.option_select(option) else {
+            return;
+        };
+        let Some(picks_first) = self.single_select(select) else {
+            return;
+        };
+        let has = |name: &str| tag.attrs.iter().any(|a| a.name == name);
+        let disabled = has("disabled")
+            || self
+                .sink
+                .parent(option)
+                .is_some_and(|p| self.is_html_named(p, "optgroup") && self.has_attr(p, "disabled"));
+        let state = self.selects.entry(select).or_default();
+        if has("selected") || picks_first && !disabled && state.selected.is_none() {
+            state.selected = Some(option);
+            self.show_selected_option(select);
+        }
+    }
+
+    fn selectedcontent_inserted(&mut self, selectedcontent: NodeId) {
+        let mut node = self.sink.parent(selectedcontent);
+        while let Some(id) = node
+            && !self.is_html_named(id, "select")
+        {
+            node = self.sink.parent(id);
+        }
+        let Some(select) = node.filter(|&select| self.single_select(select).is_some()) else {
+            return;
+        };
+        let state = self.selects.entry(select).or_default();
+        if state.selectedcontent.is_none() {
+            state.selectedcontent = Some(selectedcontent);
+            self.show_selected_option(select);
+        }
+    }
+
+    // A selected option that has been popped off the stack is complete, so
+    // its contents are copied again.
+    fn option_popped(&mut self, option: NodeId) {
+        if let Some(select) = self.option_select(option)
+            && self.selects.get(&select).is_some_and(|s| s.selected == Some(option))
+        {
+            self.show_selected_option(select);
+        }
+    }
+
+    fn show_selected_option(&mut self, select: NodeId) {
+        if let Some(&SelectState {
+            selected: Some(option),
+            selectedcontent: Some(selectedcontent),
+        }) = self.selects.get(&select)
+        {
+            // Leave room for the node the current token may still insert.
+            let budget = self.opts.limits.max_nodes.map(|max| max.saturating_sub(self.nodes_created + 1));
+            match self.sink.clone_option_contents(option, selectedcontent, budget) {
+                Some(created) => self.nodes_created += created,
+                None => {
+                    self.exceed(Limit::Nodes);
+                }
+            }
+        }
+    }
+
+    fn clear_to_context(&mut self, names: &[&str]) {
+        while !self.current_is_one_of(names) && !self.current_is_one_of(&["template", "html"]) {
+            self.pop();
+        }
+    }
+
+    fn close_cell(&mut self) {
+        self.generate_implied_end_tags(None);
+        if !self.current_is_one_of(&["td", "th"]) {
+            self.error(ErrorCode::UnexpectedCellEndTag);
+        }
+        self.pop_until_one_of(&["td", "th"]);
+        self.clear_formatting_to_marker();
+        self.mode = InsertionMode::InRow;
+    }
+
+    // ---- dispatch ----
+
+    pub(crate) fn process_token(&mut self, token: Token) {
+        let Some(token) = self.apply_limits(token) else {
+            return;
+        };
+        match token {
+            Token::Character(mut text) => {
+                if std::mem::take(&mut self.ignore_lf) && text.starts_with('\n') {
+                    text.remove(0);
+                }
+                for (_, run) in split_char_runs(&text) {
+                    self.dispatch(Token::Character(run.to_string()));
+                }
+            }
+            other => {
+                self.ignore_lf = false;
+                let closing = match &other {
+                    Token::EndTag(tag) if self.opts.source_spans => Some((tag.name.clone(), self.open.clone())),
+                    _ => None,
+                };
+                self.dispatch(other);
+                if let Some((name, open_before)) = closing {
+                    self.record_closed_by(&name, &open_before);
+                }
+            }
+        }
+    }
+
+    // Checks a token against `Options::limits` before tree construction sees
+    // it, trimming it or dropping it entirely. Text length is checked as text
+    // is inserted, since that is where adjacent text merges.
+    fn apply_limits(&mut self, mut token: Token) -> Option<Token> {
+        if self.aborted.is_some() {
+            return None;
+        }
+        if token == Token::Eof {
+            return Some(token);
+        }
+        let limits = self.opts.limits;
+        // Once the node budget could be spent by this token the rest of the
+        // input is ignored, apart from end of file closing what is still open.
+        if !self.room_for_nodes(NODES_PER_TOKEN) {
+            return None;
+        }
+        let foreign = self.use_foreign_rules(&token);
+        match &mut token {
+            Token::StartTag(tag) => {
+                let leaf = if foreign {
+                    tag.self_closing
+                } else {
+                    LEAF_TAGS.contains(&tag.name.as_str())
+                };
+                if limits.max_depth.is_some_and(|max| self.open.len() >= max) && !leaf {
+                    if self.exceed(Limit::Depth) {
+                        *self.dropped_tags.entry(tag.name.clone()).or_default() += 1;
+                    }
+                    return None;
+                }
+                if let Some(max) = limits.max_attributes
+                    && tag.attrs.len() > max
+                {
+                    if !self.exceed(Limit::Attributes) {
+                        return None;
+                    }
+                    tag.attrs.truncate(max);
+                }
+                if let Some(max) = limits.max_attribute_length {
+                    for attr in &mut tag.attrs {
+                        if attr.value.len() > max {
+                            if !self.exceed(Limit::AttributeLength) {
+                                return None;
+                            }
+                            attr.value.truncate(floor_char_boundary(&attr.value, max));
+                        }
+                    }
+                }
+            }
+            Token::EndTag(tag) => {
+                if let Some(dropped) = self.dropped_tags.get_mut(&tag.name)
+                    && *dropped > 0
+                {
+                    *dropped -= 1;
+                    return None;
+                }
+            }
+            Token::Comment(data) => {
+                if let Some(max) = limits.max_text_length
+                    && data.len() > max
+                {
+                    if !self.exceed(Limit::TextLength) {
+                        return None;
+                    }
+                    data.truncate(floor_char_boundary(data, max));
+                }
+            }
+            _ => {}
+        }
+        Some(token)
+    }
+
+    fn dispatch(&mut self, token: Token) {
+        if self.use_foreign_rules(&token) {
+            self.foreign_content(token);
+        } else {
+            self.process_in_mode(self.mode, token);
+        }
+    }
+
+    fn use_foreign_rules(&self, token: &Token) -> bool {
+        let Some(node) = self.adjusted_current() else {
+            return false;
+        };
+        let Some(name) = self.name(node) else {
+            return false;
+        };
+        if name.ns == Namespace::Html || matches!(token, Token::Eof) {
+            return false;
+        }
+        if self.is_mathml_text_integration_point(node) {
+            match token {
+                Token::StartTag(tag) if !matches!(tag.name.as_str(), "mglyph" | "malignmark") => return false,
+                Token::Character(_) => return false,
+                _ => {}
+            }
+        }
+        if name.ns == Namespace::MathMl
+            && name.local == "annotation-xml"
+            && matches!(token, Token::StartTag(tag) if tag.name == "svg")
+        {
+            return false;
+        }
+        !(self.is_html_integration_point(node) && matches!(token, Token::StartTag(_) | Token::Character(_)))
+    }
+
+    fn reprocess_in(&mut self, mode: InsertionMode, token: Token) {
+        self.mode = mode;
+        self.dispatch(token);
+    }
+
+    fn process_in_mode(&mut self, mode: InsertionMode, token: Token) {
+        match mode {
+            InsertionMode::Initial => self.initial(token),
+            InsertionMode::BeforeHtml => self.before_html(token),
+            InsertionMode::BeforeHead => self.before_head(token),
+            InsertionMode::InHead => self.in_head(token),
+            InsertionMode::InHeadNoscript => self.in_head_noscript(token),
+            InsertionMode::AfterHead => self.after_head(token),
+            InsertionMode::InBody => self.in_body(token),
+            InsertionMode::Text => self.text(token),
+            InsertionMode::InTable => self.in_table(token),
+            InsertionMode::InTableText => self.in_table_text(token),
+            InsertionMode::InCaption => self.in_caption(token),
+            InsertionMode::InColumnGroup => self.in_column_group(token),
+            InsertionMode::InTableBody => self.in_table_body(token),
+            InsertionMode::InRow => self.in_row(token),
+            InsertionMode::InCell => self.in_cell(token),
+            InsertionMode::InSelect => self.in_select(token),
+            InsertionMode::InSelectInTable => self.in_select_in_table(token),
+            InsertionMode::InTemplate => self.in_template(token),
+            InsertionMode::AfterBody => self.after_body(token),
+            InsertionMode::InFrameset => self.in_frameset(token),
+            InsertionMode::AfterFrameset => self.after_frameset(token),
+            InsertionMode::AfterAfterBody => self.after_after_body(token),
+            InsertionMode::AfterAfterFrameset => self.after_after_frameset(token),
+        }
+    }
+
+    fn stop_parsing(&mut self) {
+        self.truncate_open(0);
+    }
+
+    // ---- insertion modes ----
+
+    fn initial(&mut self, token: Token) {
+        match token {
+            Token::Character(ref s) if s.starts_with(is_whitespace_char) => {}
+            Token::Comment(data) => {
+                let root = self.sink.document();
+                self.insert_comment(data, Some((root, None)));
+            }
+            Token::Doctype(doctype) => {
+                self.insert_doctype(doctype);
+                self.mode = InsertionMode::BeforeHtml;
+            }
+            token => {
+                if !self.opts.iframe_srcdoc {
+                    self.error(match &token {
+                        Token::StartTag(_) => ErrorCode::ExpectedDoctypeButGotStartTag,
+                        Token::EndTag(_) => ErrorCode::ExpectedDoctypeButGotEndTag,
+                        Token::Eof => ErrorCode::ExpectedDoctypeButGotEof,
+                        _ => ErrorCode::ExpectedDoctypeButGotChars,
+                    });
+                    self.set_quirks_mode(QuirksMode::Quirks);
+                }
+                self.reprocess_in(InsertionMode::BeforeHtml, token);
+            }
+        }
+    }
+
+    fn insert_doctype(&mut self, doctype: DoctypeToken) {
+        let name = doctype.name.clone().unwrap_or_default();
+        if name != "html"
+            || doctype.public_id.is_some()
+            || doctype.system_id.as_deref().is_some_and(|s| s != "about:legacy-compat")
+        {
+            self.error(ErrorCode::UnknownDoctype);
+        }
+        if !self.opts.iframe_srcdoc {
+            self.set_quirks_mode(doctype_quirks_mode(&doctype));
+        }
+        self.nodes_created += 1;
+        let id = self.sink.create_doctype(Doctype {
+            name,
+            public_id: doctype.public_id.unwrap_or_default(),
+            system_id: doctype.system_id.unwrap_or_default(),
+        });
+        self.record_data(id, self.token_span);
+        let root = self.sink.document();
+        self.sink.append(root, id);
+    }
+
+    fn before_html(&mut self, token: Token) {
+        match token {
+            Token::Doctype(_) => self.error(ErrorCode::UnexpectedDoctype),
+            Token::Comment(data) => {
+                let root = self.sink.document();
+                self.insert_comment(data, Some((root, None)));
+            }
+            Token::Character(ref s) if s.starts_with(is_whitespace_char) => {}
+            Token::StartTag(tag) if tag.name == "html" => {
+                let id = self.create_element("html", Namespace::Html, token_attrs(&tag));
+                self.record_start_tag(id, tag.attrs.len());
+                let root = self.sink.document();
+                self.sink.append(root, id);
+                self.open.push(id);
+                self.mode = InsertionMode::BeforeHead;
+            }
+            Token::EndTag(ref tag) if !matches!(tag.name.as_str(), "head" | "body" | "html" | "br") => {
+                self.error(ErrorCode::UnexpectedEndTagBeforeHtml);
+            }
+            token => {
+                let id = self.create_element("html", Namespace::Html, Vec::new());
+                self.record_synthetic(id);
+                let root = self.sink.document();
+                self.sink.append(root, id);
+                self.open.push(id);
+                self.reprocess_in(InsertionMode::BeforeHead, token);
+            }
+        }
+    }
+
+    fn before_head(&mut self, token: Token) {
+        match token {
+            Token::Character(ref s) if s.starts_with(is_whitespace_char) => {}
+            Token::Comment(data) => self.insert_comment(data, None),
+            Token::Doctype(_) => self.error(ErrorCode::UnexpectedDoctype),
+            Token::StartTag(ref tag) if tag.name == "html" => self.in_body(token),
+            Token::StartTag(tag) if tag.name == "head" => {
+                let id = self.insert_html_element(&tag);
+                self.head = Some(id);
+                self.mode = InsertionMode::InHead;
+            }
+            Token::EndTag(ref tag) if !matches!(tag.name.as_str(), "head" | "body" | "html" | "br") => {
+                self.error(ErrorCode::EndTagAfterImpliedRoot);
+            }
+            token => {
+                let id = self.insert_synthetic("head");
+                self.head = Some(id);
+                self.reprocess_in(InsertionMode::InHead, token);
+            }
+        }
+    }
+
+    fn in_head(&mut self, token: Token) {
+        match token {
+            Token::Character(ref s) if s.starts_with(is_whitespace_char) => self.insert_text(s),
+            Token::Comment(data) => self.insert_comment(data, None),
+            Token::Doctype(_) => self.error(ErrorCode::UnexpectedDoctype),
+            Token::StartTag(tag) => match tag.name.as_str() {
+                "html" => self.in_body(Token::StartTag(tag)),
+                "base" | "basefont" | "bgsound" | "link" => {
+                    self.insert_html_element(&tag);
+                    self.pop();
+                }
+                "meta" => {
+                    self.insert_html_element(&tag);
+                    self.pop();
+                    self.check_meta_charset(&tag);
+                }
+                "title" => self.parse_text_element(&tag, State::Rcdata),
+                "noscript" if self.opts.scripting_enabled => self.parse_text_element(&tag, State::Rawtext),
+                "noscript" => {
+                    self.insert_html_element(&tag);
+                    self.mode = InsertionMode::InHeadNoscript;
+                }
+                "noframes" | "style" => self.parse_text_element(&tag, State::Rawtext),
+                "script" => self.parse_text_element(&tag, State::ScriptData),
+                "template" => {
+                    self.insert_template(&tag);
+                    self.active_formatting.push(FormattingEntry::Marker);
+                    self.frameset_ok = false;
+                    self.mode = InsertionMode::InTemplate;
+                    self.template_modes.push(InsertionMode::InTemplate);
+                }
+                "head" => self.error(ErrorCode::TwoHeadsAreNotBetterThanOne),
+                _ => self.in_head_anything_else(Token::StartTag(tag)),
+            },
+            Token::EndTag(tag) => match tag.name.as_str() {
+                "head" => {
+                    self.pop();
+                    self.mode = InsertionMode::AfterHead;
+                }
+                "body" | "html" | "br" => self.in_head_anything_else(Token::EndTag(tag)),
+                "template" => {
+                    if !self.has_template_on_stack() {
+                        self.error(ErrorCode::UnexpectedEndTag);
+                        return;
+                    }
+                    self.generate_all_implied_end_tags();
+                    if !self.current_is("template") {
+                        self.error_unclosed(ErrorCode::EndTagTooEarly, self.current());
+                    }
+                    self.pop_until_named("template");
+                    self.clear_formatting_to_marker();
+                    self.template_modes.pop();
+                    self.reset_insertion_mode();
+                }
+                _ => self.error(ErrorCode::UnexpectedEndTag),
+            },
+            token => self.in_head_anything_else(token),
+        }
+    }
+
+    fn in_head_noscript(&mut self, token: Token) {
+        match token {
+            Token::Doctype(_) => self.error(ErrorCode::UnexpectedDoctype),
+            Token::StartTag(ref tag) if tag.name == "html" => self.in_body(token),
+            Token::EndTag(ref tag) if tag.name == "noscript" => {
+                self.pop();
+                self.mode = InsertionMode::InHead;
+            }
+            Token::Character(ref s) if s.starts_with(is_whitespace_char) => self.in_head(token),
+            Token::Comment(_) => self.in_head(token),
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "basefont" | "bgsound" | "link" | "meta" | "noframes" | "style"
+                ) =>
+            {
+                self.in_head(token);
+            }
+            Token::StartTag(ref tag) if matches!(tag.name.as_str(), "head" | "noscript") => {
+                self.error(ErrorCode::UnexpectedStartTag);
+            }
+            Token::EndTag(ref tag) if tag.name != "br" => self.error(ErrorCode::UnexpectedEndTag),
+            token => {
+                self.error(match &token {
+                    Token::Eof => ErrorCode::EofInHeadNoscript,
+                    Token::Character(_) => ErrorCode::CharInHeadNoscript,
+                    _ => ErrorCode::UnexpectedInheadNoscriptTag,
+                });
+                self.pop();
+                self.reprocess_in(InsertionMode::InHead, token);
+            }
+        }
+    }
+
+    fn check_meta_charset(&mut self, tag: &Tag) {
+        if self.confidence != Confidence::Tentative {
+            return;
+        }
+        let attr = |name: &str| tag.attrs.iter().find(|a| a.name == name).map(|a| a.value.as_str());
+        let declared = attr("charset").and_then(Encoding::for_label).or_else(|| {
+            if !attr("http-equiv")?.eq_ignore_ascii_case("content-type") {
+                return None;
+            }
+            let label = encoding::charset_from_content(attr("content")?.as_bytes())?;
+            Encoding::for_label(&String::from_utf8_lossy(&label))
+        });
+        if let Some(declared) = declared {
+            self.change_encoding(declared);
+        }
+    }
+
+    fn change_encoding(&mut self, declared: Encoding) {
+        let Some(current) = self.encoding else {
+            return;
+        };
+        self.confidence = Confidence::Certain;
+        if matches!(current, Encoding::Utf16Be | Encoding::Utf16Le) {
+            return;
+        }
+        let declared = match declared {
+            Encoding::Utf16Be | Encoding::Utf16Le => Encoding::Utf8,
+            Encoding::XUserDefined => Encoding::Windows1252,
+            other => other,
+        };
+        if declared != current {
+            self.encoding_change = Some(declared);
+        }
+    }
+
+    fn in_head_anything_else(&mut self, token: Token) {
+        self.pop();
+        self.reprocess_in(InsertionMode::AfterHead, token);
+    }
+
+    fn after_head(&mut self, token: Token) {
+        match token {
+            Token::Character(ref s) if s.starts_with(is_whitespace_char) => self.insert_text(s),
+            Token::Comment(data) => self.insert_comment(data, None),
+            Token::Doctype(_) => self.error(ErrorCode::UnexpectedDoctype),
+            Token::StartTag(tag) => match tag.name.as_str() {
+                "html" => self.in_body(Token::StartTag(tag)),
+                "body" => {
+                    self.insert_html_element(&tag);
+                    self.frameset_ok = false;
+                    self.mode = InsertionMode::InBody;
+                }
+                "frameset" => {
+                    self.insert_html_element(&tag);
+                    self.mode = InsertionMode::InFrameset;
+                }
+                "base" | "basefont" | "bgsound" | "link" | "meta" | "noframes" | "script" | "style" | "template"
+                | "title" => {
+                    self.error(ErrorCode::UnexpectedStartTagOutOfMyHead);
+                    let Some(head) = self.head else {
+                        return;
+                    };
+                    self.open.push(head);
+                    self.in_head(Token::StartTag(tag));
+                    self.remove_from_stack(head);
+                }
+                "head" => self.error(ErrorCode::UnexpectedStartTag),
+                _ => self.after_head_anything_else(Token::StartTag(tag)),
+            },
+            Token::EndTag(tag) => match tag.name.as_str() {
+                "body" | "html" | "br" => self.after_head_anything_else(Token::EndTag(tag)),
+                _ => self.error(ErrorCode::UnexpectedEndTag),
+            },
+            token => self.after_head_anything_else(token),
+        }
+    }
+
+    fn after_head_anything_else(&mut self, token: Token) {
+        self.insert_synthetic("body");
+        self.reprocess_in(InsertionMode::InBody, token);
+    }
+
+    fn in_body(&mut self, token: Token) {
+        match token {
+            Token::Character(s) => match s.chars().next().map(char_class) {
+                Some(CharClass::Null) => {
+                    for _ in s.chars() {
+                        self.error(ErrorCode::UnexpectedNullCharacter);
+                    }
+                }
+                Some(CharClass::Whitespace) => {
+                    self.reconstruct_formatting();
+                    self.insert_text(&s);
+                }
+                _ => {
+                    self.reconstruct_formatting();
+                    self.insert_text(&s);
+                    self.frameset_ok = false;
+                }
+            },
+            Token::Comment(data) => self.insert_comment(data, None),
+            Token::Doctype(_) => self.error(ErrorCode::UnexpectedDoctype),
+            Token::StartTag(tag) => self.in_body_start_tag(tag),
+            Token::EndTag(tag) => self.in_body_end_tag(tag),
+            Token::Eof if !self.template_modes.is_empty() => self.in_template(Token::Eof),
+            Token::Eof => {
+                if let Some(&id) = self.open.iter().find(|&&id| !self.is_html_one_of(id, BODY_END_ALLOWED_OPEN)) {
+                    self.error_unclosed(ErrorCode::ExpectedClosingTagButGotEof, id);
+                }
+                self.stop_parsing();
+            }
+        }
+    }
+
+    fn in_body_start_tag(&mut self, tag: Tag) {
+        match tag.name.as_str() {
+            "html" => {
+                self.error(ErrorCode::UnexpectedStartTag);
+                if self.has_template_on_stack() {
+                    return;
+                }
+                if let Some(&html) = self.open.first() {
+                    self.add_missing_attrs(html, &tag);
+                }
+            }
+            "base" | "basefont" | "bgsound" | "link" | "meta" | "noframes" | "script" | "style" | "template"
+            | "title" => self.in_head(Token::StartTag(tag)),
+            "body" => {
+                self.error(ErrorCode::UnexpectedStartTag);
+                if self.open.len() < 2 || !self.is_html_named(self.open[1], "body") || self.has_template_on_stack() {
+                    return;
+                }
+                self.frameset_ok = false;
+                let body = self.open[1];
+                self.add_missing_attrs(body, &tag);
+            }
+            "frameset" => {
+                self.error(ErrorCode::UnexpectedStartTag);
+                if self.open.len() < 2 || !self.is_html_named(self.open[1], "body") || !self.frameset_ok {
+                    return;
+                }
+                let body = self.open[1];
+                self.sink.remove_from_parent(body);
+                self.truncate_open(1);
+                self.insert_html_element(&tag);
+                self.mode = InsertionMode::InFrameset;
+            }
+            "address" | "article" | "aside" | "blockquote" | "center" | "details" | "dialog" | "dir" | "div" | "dl"
+            | "fieldset" | "figcaption" | "figure" | "footer" | "header" | "hgroup" | "main" | "menu" | "nav"
+            | "ol" | "p" | "search" | "section" | "summary" | "ul" => {
+                self.close_p_if_in_button_scope();
+                self.insert_html_element(&tag);
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.close_p_if_in_button_scope();
+                if self.current_is_one_of(HEADINGS) {
+                    self.error(ErrorCode::UnexpectedStartTag);
+                    self.pop();
+                }
+                self.insert_html_element(&tag);
+            }
+            "pre" | "listing" => {
+                self.close_p_if_in_button_scope();
+                self.insert_html_element(&tag);
+                self.ignore_lf = true;
+                self.frameset_ok = false;
+            }
+            "form" => {
+                let in_template = self.has_template_on_stack();
+                if self.form.is_some() && !in_template {
+                    self.error(ErrorCode::UnexpectedStartTag);
+                    return;
+                }
+                self.close_p_if_in_button_scope();
+                let id = self.insert_html_element(&tag);
+                if !in_template {
+                    self.form = Some(id);
+                }
+            }
+            "li" | "dd" | "dt" => {
+                self.frameset_ok = false;
+                let closes: &[&str] = if tag.name == "li" { &["li"] } else { &["dd", "dt"] };
+                for i in (0..self.open.len()).rev() {
+                    let node = self.open[i];
+                    if self.is_html_one_of(node, closes) {
+                        let local = self.name(node).map(|n| n.local.clone()).unwrap_or_default();
+                        self.generate_implied_end_tags(Some(&local));
+                        if !self.current_is(&local) {
+                            self.error(ErrorCode::UnexpectedStartTagImpliesEndTag);
+                        }
+                        self.pop_until_named(&local);
+                        break;
+                    }
+                    if self.is_special(node) && !self.is_html_one_of(node, &["address", "div", "p"]) {
+                        break;
+                    }
+                }
+                self.close_p_if_in_button_scope();
+                self.insert_html_element(&tag);
+            }
+            "plaintext" => {
+                self.close_p_if_in_button_scope();
+                self.insert_html_element(&tag);
+                self.tokenizer_state = Some(State::Plaintext);
+            }
+            "button" => {
+                if self.in_scope("button", Scope::Default) {
+                    self.error(ErrorCode::UnexpectedStartTagImpliesEndTag);
+                    self.generate_implied_end_tags(None);
+                    self.pop_until_named("button");
+                }
+                self.reconstruct_formatting();
+                self.insert_html_element(&tag);
+                self.frameset_ok = false;
+            }
+            "a" => {
+                if let Some(a) = self.formatting_after_marker("a") {
+                    self.error(ErrorCode::UnexpectedStartTagImpliesEndTag);
+                    self.adoption_agency("a");
+                    self.remove_formatting(a);
+                    self.remove_from_stack(a);
+                }
+                self.reconstruct_formatting();
+                let id = self.insert_html_element(&tag);
+                self.push_formatting(id);
+            }
+            "b" | "big" | "code" | "em" | "font" | "i" | "s" | "small" | "strike" | "strong" | "tt" | "u" => {
+                self.reconstruct_formatting();
+                let id = self.insert_html_element(&tag);
+                self.push_formatting(id);
+            }
+            "nobr" => {
+                self.reconstruct_formatting();
+                if self.in_scope("nobr", Scope::Default) {
+                    self.error(ErrorCode::UnexpectedStartTagImpliesEndTag);
+                    self.adoption_agency("nobr");
+                    self.reconstruct_formatting();
+                }
+                let id = self.insert_html_element(&tag);
+                self.push_formatting(id);
+            }
+            "applet" | "marquee" | "object" => {
+                self.reconstruct_formatting();
+                self.insert_html_element(&tag);
+                self.active_formatting.push(FormattingEntry::Marker);
+                self.frameset_ok = false;
+            }
+            "table" => {
+                if self.quirks_mode != QuirksMode::Quirks {
+                    self.close_p_if_in_button_scope();
+                }
+                self.insert_html_element(&tag);
+                self.frameset_ok = false;
+                self.mode = InsertionMode::InTable;
+            }
+            "area" | "br" | "embed" | "img" | "keygen" | "wbr" => {
+                self.reconstruct_formatting();
+                self.insert_html_element(&tag);
+                self.pop();
+                self.frameset_ok = false;
+            }
+            "input" => {
+                if self.opts.customizable_select {
+                    if self.context_is("select") {
+                        self.error(ErrorCode::UnexpectedInputInSelect);
+                        return;
+                    }
+                    if self.in_scope("select", Scope::Default) {
+                        self.error(ErrorCode::UnexpectedInputInSelect);
+                        self.pop_until_named("select");
+                    }
+                }
+                self.reconstruct_formatting();
+                self.insert_html_element(&tag);
+                self.pop();
+                let hidden = tag
+                    .attrs
+                    .iter()
+                    .any(|a| a.name == "type" && a.value.eq_ignore_ascii_case("hidden"));
+                if !hidden {
+                    self.frameset_ok = false;
+                }
+            }
+            "param" | "source" | "track" => {
+                self.insert_html_element(&tag);
+                self.pop();
+            }
+            "hr" => {
+                self.close_p_if_in_button_scope();
+                if self.opts.customizable_select && self.in_scope("select", Scope::Default) {
+                    self.generate_implied_end_tags(None);
+                    if self.in_scope("option", Scope::Default) || self.in_scope("optgroup", Scope::Default) {
+                        self.error(ErrorCode::UnexpectedStartTag);
+                    }
+                }
+                self.insert_html_element(&tag);
+                self.pop();
+                self.frameset_ok = false;
+            }
+            "image" => {
+                self.error(ErrorCode::UnexpectedStartTagTreatedAs);
+                self.dispatch(Token::StartTag(Tag {
+                    name: "img".to_string(),
+                    ..tag
+                }));
+            }
+            "textarea" => {
+                self.insert_html_element(&tag);
+                self.ignore_lf = true;
+                self.tokenizer_state = Some(State::Rcdata);
+                self.original_mode = self.mode;
+                self.frameset_ok = false;
+                self.mode = InsertionMode::Text;
+            }
+            "xmp" => {
+                self.close_p_if_in_button_scope();
+                self.reconstruct_formatting();
+                self.frameset_ok = false;
+                self.parse_text_element(&tag, State::Rawtext);
+            }
+            "iframe" => {
+                self.frameset_ok = false;
+                self.parse_text_element(&tag, State::Rawtext);
+            }
+            "noembed" => self.parse_text_element(&tag, State::Rawtext),
+            "noscript" if self.opts.scripting_enabled => self.parse_text_element(&tag, State::Rawtext),
+            "select" if self.opts.customizable_select => {
+                if self.context_is("select") {
+                    self.error(ErrorCode::UnexpectedSelectInSelect);
+                    return;
+                }
+                if self.in_scope("select", Scope::Default) {
+                    self.error(ErrorCode::UnexpectedSelectInSelect);
+                    self.end_select_early();
+                    return;
+                }
+                self.reconstruct_formatting();
+                self.insert_html_element(&tag);
+                self.frameset_ok = false;
+            }
+            "select" => {
+                self.reconstruct_formatting();
+                self.insert_html_element(&tag);
+                self.frameset_ok = false;
+                self.mode = match self.mode {
+                    InsertionMode::InTable
+                    | InsertionMode::InCaption
+                    | InsertionMode::InTableBody
+                    | InsertionMode::InRow
+                    | InsertionMode::InCell => InsertionMode::InSelectInTable,
+                    _ => InsertionMode::InSelect,
+                };
+            }
+            "optgroup" | "option" => {
+                let option = tag.name == "option";
+                if self.opts.customizable_select && self.in_scope("select", Scope::Default) {
+                    self.generate_implied_end_tags(option.then_some("optgroup"));
+                    if self.in_scope("option", Scope::Default) || !option && self.in_scope("optgroup", Scope::Default) {
+                        self.error(ErrorCode::UnexpectedStartTag);
+                    }
+                } else if self.current_is("option") {
+                    self.pop();
+                }
+                self.reconstruct_formatting();
+                let id = self.insert_html_element(&tag);
+                if option && self.opts.customizable_select {
+                    self.option_inserted(id, &tag);
+                }
+            }
+            "selectedcontent" if self.opts.customizable_select => {
+                self.reconstruct_formatting();
+                let id = self.insert_html_element(&tag);
+                self.selectedcontent_inserted(id);
+            }
+            "rb" | "rtc" => {
+                if self.in_scope("ruby", Scope::Default) {
+                    self.generate_implied_end_tags(None);
+                    if !self.current_is("ruby") {
+                        self.error(ErrorCode::UnexpectedStartTag);
+                    }
+                }
+                self.insert_html_element(&tag);
+            }
+            "rp" | "rt" => {
+                if self.in_scope("ruby", Scope::Default) {
+                    self.generate_implied_end_tags(Some("rtc"));
+                    if !self.current_is_one_of(&["ruby", "rtc"]) {
+                        self.error(ErrorCode::UnexpectedStartTag);
+                    }
+                }
+                self.insert_html_element(&tag);
+            }
+            "math" => {
+                self.reconstruct_formatting();
+                self.insert_foreign_element(&tag, Namespace::MathMl);
+            }
+            "svg" => {
+                self.reconstruct_formatting();
+                self.insert_foreign_element(&tag, Namespace::Svg);
+            }
+            "caption" | "col" | "colgroup" | "frame" | "head" | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr" => {
+                self.error(ErrorCode::UnexpectedStartTagIgnored);
+            }
+            _ => {
+                self.reconstruct_formatting();
+                self.insert_html_element(&tag);
+            }
+        }
+    }
+
+    fn in_body_end_tag(&mut self, tag: Tag) {
+        match tag.name.as_str() {
+            "body" | "html" => {
+                if !self.in_scope("body", Scope::Default) {
+                    self.error(ErrorCode::UnexpectedEndTag);
+                    return;
+                }
+                if self.open.iter().any(|&id| !self.is_html_one_of(id, BODY_END_ALLOWED_OPEN)) {
+                    self.error(ErrorCode::ExpectedOneEndTagButGotAnother);
+                }
+                self.mode = InsertionMode::AfterBody;
+                if tag.name == "body"
+                    && let Some(&body) = self.open.get(1)
+                {
+                    self.record_end_tag(body);
+                }
+                if tag.name == "html" {
+                    self.dispatch(Token::EndTag(tag));
+                }
+            }
+            "address" | "article" | "aside" | "blockquote" | "button" | "center" | "details" | "dialog" | "dir"
+            | "div" | "dl" | "fieldset" | "figcaption" | "figure" | "footer" | "header" | "hgroup" | "listing"
+            | "main" | "menu" | "nav" | "ol" | "pre" | "search" | "section" | "select" | "summary" | "ul"
+                if tag.name != "select" || self.opts.customizable_select =>
+            {
+                if !self.in_scope(&tag.name, Scope::Default) {
+                    self.error(ErrorCode::EndTagTooEarly);
+                    return;
+                }
+                self.generate_implied_end_tags(None);
+                if !self.current_is(&tag.name) {
+                    self.error_unclosed(ErrorCode::EndTagTooEarly, self.current());
+                }
+                self.pop_until_named(&tag.name);
+            }
+            "form" if self.has_template_on_stack() => {
+                if !self.in_scope("form", Scope::Default) {
+                    self.error(ErrorCode::UnexpectedEndTag);
+                    return;
+                }
+                self.generate_implied_end_tags(None);
+                if !self.current_is("form") {
+                    self.error(ErrorCode::EndTagTooEarlyIgnored);
+                }
+                self.pop_until_named("form");
+            }
+            "form" => {
+                let node = self.form.take();
+                match node {
+                    Some(node) if self.node_in_scope(node, Scope::Default) => {
+                        self.generate_implied_end_tags(None);
+                        if self.current() != node {
+                            self.error(ErrorCode::EndTagTooEarlyIgnored);
+                        }
+                        self.remove_from_stack(node);
+                    }
+                    _ => self.error(ErrorCode::UnexpectedEndTag),
+                }
+            }
+            "p" => {
+                if !self.in_scope("p", Scope::Button) {
+                    self.error(ErrorCode::UnexpectedEndTag);
+                    let p = self.insert_synthetic("p");
+                    self.record_end_tag(p);
+                }
+                self.close_p_element();
+            }
+            "li" => {
+                if !self.in_scope("li", Scope::ListItem) {
+                    self.error(ErrorCode::UnexpectedEndTag);
+                    return;
+                }
+                self.generate_implied_end_tags(Some("li"));
+                if !self.current_is("li") {
+                    self.error_unclosed(ErrorCode::EndTagTooEarly, self.current());
+                }
+                self.pop_until_named("li");
+            }
+            "dd" | "dt" => {
+                if !self.in_scope(&tag.name, Scope::Default) {
+                    self.error(ErrorCode::UnexpectedEndTag);
+                    return;
+                }
+                self.generate_implied_end_tags(Some(&tag.name));
+                if !self.current_is(&tag.name) {
+                    self.error_unclosed(ErrorCode::EndTagTooEarly, self.current());
+                }
+                self.pop_until_named(&tag.name);
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                if !self.in_scope_where(Scope::Default, |tb, id| tb.is_html_one_of(id, HEADINGS)) {
+                    self.error(ErrorCode::UnexpectedEndTag);
+                    return;
+                }
+                self.generate_implied_end_tags(None);
+                if !self.current_is(&tag.name) {
+                    self.error_unclosed(ErrorCode::EndTagTooEarly, self.current());
+                }
+                self.pop_until_one_of(HEADINGS);
+            }
+            "template" => self.in_head(Token::EndTag(tag)),
+            "applet" | "marquee" | "object" => {
+                if !self.in_scope(&tag.name, Scope::Default) {
+                    self.error(ErrorCode::UnexpectedEndTag);
+                    return;
+                }
+                self.generate_implied_end_tags(None);
+                if !self.current_is(&tag.name) {
+                    self.error_unclosed(ErrorCode::EndTagTooEarly, self.current());
+                }
+                self.pop_until_named(&tag.name);
+                self.clear_formatting_to_marker();
+            }
+            name if FORMATTING.contains(&name) => {
+                if !self.adoption_agency(name) {
+                    self.any_other_end_tag(name);
+                }
+            }
+            "br" => {
+                self.error(ErrorCode::UnexpectedEndTagTreatedAs);
+                self.in_body_start_tag(synthetic_tag("br"));
+            }
+            _ => self.any_other_end_tag(&tag.name),
+        }
+    }
+
+    fn any_other_end_tag(&mut self, local: &str) {
+        for i in (0..self.open.len()).rev() {
+            let node = self.open[i];
+            if self.is_html_named(node, local) {
+                self.generate_implied_end_tags(Some(local));
+                if self.current() != node {
+                    self.error_unclosed(ErrorCode::UnexpectedEndTag, self.current());
+                }
+                self.truncate_open(i);
+                return;
+            }
+            if self.is_special(node) {
+                self.error(ErrorCode::UnexpectedEndTag);
+                return;
+            }
+        }
+    }
+
+    fn text(&mut self, token: Token) {
+        match token {
+            Token::Character(s) => self.insert_text(&s),
+            Token::Eof => {
+                self.error_unclosed(ErrorCode::ExpectedNamedClosingTagButGotEof, self.current());
+                self.pop();
+                self.reprocess_in(self.original_mode, Token::Eof);
+            }
+            _ => {
+                self.pop();
+                self.mode = self.original_mode;
+            }
+        }
+    }
+
+    fn in_table(&mut self, token: Token) {
+        match token {
+            Token::Character(_) if self.current_is_one_of(&["table", "tbody", "template", "tfoot", "thead", "tr"]) => {
+                self.pending_table_text.clear();
+                self.pending_table_text_span = None;
+                self.original_mode = self.mode;
+                self.reprocess_in(InsertionMode::InTableText, token);
+            }
+            Token::Comment(data) => self.insert_comment(data, None),
+            Token::Doctype(_) => self.error(ErrorCode::UnexpectedDoctype),
+            Token::StartTag(tag) => match tag.name.as_str() {
+                "caption" => {
+                    self.clear_to_context(&["table"]);
+                    self.active_formatting.push(FormattingEntry::Marker);
+                    self.insert_html_element(&tag);
+                    self.mode = InsertionMode::InCaption;
+                }
+                "colgroup" => {
+                    self.clear_to_context(&["table"]);
+                    self.insert_html_element(&tag);
+                    self.mode = InsertionMode::InColumnGroup;
+                }
+                "col" => {
+                    self.clear_to_context(&["table"]);
+                    self.insert_synthetic("colgroup");
+                    self.reprocess_in(InsertionMode::InColumnGroup, Token::StartTag(tag));
+                }
+                "tbody" | "tfoot" | "thead" => {
+                    self.clear_to_context(&["table"]);
+                    self.insert_html_element(&tag);
+                    self.mode = InsertionMode::InTableBody;
+                }
+                "td" | "th" | "tr" => {
+                    self.clear_to_context(&["table"]);
+                    self.insert_synthetic("tbody");
+                    self.reprocess_in(InsertionMode::InTableBody, Token::StartTag(tag));
+                }
+                "table" => {
+                    self.error(ErrorCode::UnexpectedStartTagImpliesEndTag);
+                    if self.in_scope("table", Scope::Table) {
+                        self.pop_until_named("table");
+                        self.reset_insertion_mode();
+                        self.dispatch(Token::StartTag(tag));
+                    }
+                }
+                "style" | "script" | "template" => self.in_head(Token::StartTag(tag)),
+                "input"
+                    if tag
+                        .attrs
+                        .iter()
+                        .any(|a| a.name == "type" && a.value.eq_ignore_ascii_case("hidden")) =>
+                {
+                    self.error(ErrorCode::UnexpectedHiddenInputInTable);
+                    self.insert_html_element(&tag);
+                    self.pop();
+                }
+                "form" => {
+                    self.error(ErrorCode::UnexpectedFormInTable);
+                    if self.form.is_some() || self.open.iter().any(|&id| self.is_html_named(id, "template")) {
+                        return;
+                    }
+                    let id = self.insert_html_element(&tag);
+                    self.form = Some(id);
+                    self.pop();
+                }
+                _ => self.in_table_anything_else(Token::StartTag(tag)),
+            },
+            Token::EndTag(tag) => match tag.name.as_str() {
+                "table" => {
+                    if !self.in_scope("table", Scope::Table) {
+                        self.error(ErrorCode::UnexpectedEndTag);
+                        return;
+                    }
+                    self.pop_until_named("table");
+                    self.reset_insertion_mode();
+                }
+                "body" | "caption" | "col" | "colgroup" | "html" | "tbody" | "td" | "tfoot" | "th" | "thead"
+                | "tr" => self.error(ErrorCode::UnexpectedEndTag),
+                "template" => self.in_head(Token::EndTag(tag)),
+                _ => self.in_table_anything_else(Token::EndTag(tag)),
+            },
+            Token::Eof => self.in_body(Token::Eof),
+            token => self.in_table_anything_else(token),
+        }
+    }
+
+    fn in_table_anything_else(&mut self, token: Token) {
+        self.error(match &token {
+            Token::StartTag(_) => ErrorCode::UnexpectedStartTagImpliesTableVoodoo,
+            Token::EndTag(_) => ErrorCode::UnexpectedEndTagImpliesTableVoodoo,
+            _ => ErrorCode::UnexpectedCharImpliesTableVoodoo,
+        });
+        self.foster_parenting = true;
+        self.in_body(token);
+        self.foster_parenting = false;
+    }
+
+    fn in_table_text(&mut self, token: Token) {
+        match token {
+            Token::Character(s) if s.starts_with('\0') => {
+                for _ in s.chars() {
+                    self.error(ErrorCode::InvalidCodepoint);
+                }
+            }
+            Token::Character(s) => {
+                self.pending_table_text.push_str(&s);
+                let span = self.pending_table_text_span.map_or(self.token_span, |s| s.cover(self.token_span));
+                self.pending_table_text_span = Some(span);
+            }
+            token => {
+                let text = std::mem::take(&mut self.pending_table_text);
+                if text.chars().any(|c| !is_whitespace_char(c)) {
+                    self.error(ErrorCode::UnexpectedCharImpliesTableVoodoo);
+                    self.foster_parenting = true;
+                    for (_, run) in split_char_runs(&text) {
+                        self.in_body(Token::Character(run.to_string()));
+                    }
+                    self.foster_parenting = false;
+                } else if !text.is_empty() {
+                    self.insert_text(&text);
+                }
+                self.pending_table_text_span = None;
+                self.reprocess_in(self.original_mode, token);
+            }
+        }
+    }
+
+    fn close_caption(&mut self) -> bool {
+        if !self.in_scope("caption", Scope::Table) {
+            self.error(ErrorCode::UnexpectedEndTag);
+            return false;
+        }
+        self.generate_implied_end_tags(None);
+        if !self.current_is("caption") {
+            self.error(ErrorCode::ExpectedOneEndTagButGotAnother);
+        }
+        self.pop_until_named("caption");
+        self.clear_formatting_to_marker();
+        self.mode = InsertionMode::InTable;
+        true
+    }
+
+    fn in_caption(&mut self, token: Token) {
+        match token {
+            Token::EndTag(ref tag) if tag.name == "caption" => {
+                self.close_caption();
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "caption" | "col" | "colgroup" | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr"
+                ) =>
+            {
+                if self.close_caption() {
+                    self.dispatch(token);
+                }
+            }
+            Token::EndTag(ref tag) if tag.name == "table" => {
+                if self.close_caption() {
+                    self.dispatch(token);
+                }
+            }
+            Token::EndTag(ref tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "body" | "col" | "colgroup" | "html" | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr"
+                ) =>
+            {
+                self.error(ErrorCode::UnexpectedEndTag);
+            }
+            token => self.in_body(token),
+        }
+    }
+
+    fn in_column_group(&mut self, token: Token) {
+        match token {
+            Token::Character(ref s) if s.starts_with(is_whitespace_char) => self.insert_text(s),
+            Token::Comment(data) => self.insert_comment(data, None),
+            Token::Doctype(_) => self.error(ErrorCode::UnexpectedDoctype),
+            Token::StartTag(ref tag) if tag.name == "html" => self.in_body(token),
+            Token::StartTag(tag) if tag.name == "col" => {
+                self.insert_html_element(&tag);
+                self.pop();
+            }
+            Token::EndTag(ref tag) if tag.name == "colgroup" => {
+                if !self.current_is("colgroup") {
+                    self.error(ErrorCode::UnexpectedEndTag);
+                    return;
+                }
+                self.pop();
+                self.mode = InsertionMode::InTable;
+            }
+            Token::EndTag(ref tag) if tag.name == "col" => self.error(ErrorCode::UnexpectedEndTag),
+            Token::StartTag(ref tag) if tag.name == "template" => self.in_head(token),
+            Token::EndTag(ref tag) if tag.name == "template" => self.in_head(token),
+            Token::Eof => self.in_body(token),
+            token => {
+                if !self.current_is("colgroup") {
+                    self.error(ErrorCode::UnexpectedTokenInColumnGroup);
+                    return;
+                }
+                self.pop();
+                self.reprocess_in(InsertionMode::InTable, token);
+            }
+        }
+    }
+
+    fn in_table_body(&mut self, token: Token) {
+        match token {
+            Token::StartTag(tag) if tag.name == "tr" => {
+                self.clear_to_context(TABLE_SECTIONS);
+                self.insert_html_element(&tag);
+                self.mode = InsertionMode::InRow;
+            }
+            Token::StartTag(ref tag) if matches!(tag.name.as_str(), "th" | "td") => {
+                self.error(ErrorCode::UnexpectedCellInTableBody);
+                self.clear_to_context(TABLE_SECTIONS);
+                self.insert_synthetic("tr");
+                self.reprocess_in(InsertionMode::InRow, token);
+            }
+            Token::EndTag(ref tag) if TABLE_SECTIONS.contains(&tag.name.as_str()) => {
+                if !self.in_scope(&tag.name, Scope::Table) {
+                    self.error(ErrorCode::UnexpectedEndTagInTableBody);
+                    return;
+                }
+                self.clear_to_context(TABLE_SECTIONS);
+                self.pop();
+                self.mode = InsertionMode::InTable;
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "caption" | "col" | "colgroup" | "tbody" | "tfoot" | "thead"
+                ) =>
+            {
+                self.close_table_section(token);
+            }
+            Token::EndTag(ref tag) if tag.name == "table" => self.close_table_section(token),
+            Token::EndTag(ref tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "body" | "caption" | "col" | "colgroup" | "html" | "td" | "th" | "tr"
+                ) =>
+            {
+                self.error(ErrorCode::UnexpectedEndTagInTableBody);
+            }
+            token => self.in_table(token),
+        }
+    }
+
+    fn close_table_section(&mut self, token: Token) {
+        if !self.in_scope_where(Scope::Table, |tb, id| tb.is_html_one_of(id, TABLE_SECTIONS)) {
+            self.error(ErrorCode::UnexpectedStartTag);
+            return;
+        }
+        self.clear_to_context(TABLE_SECTIONS);
+        self.pop();
+        self.reprocess_in(InsertionMode::InTable, token);
+    }
+
+    fn close_row(&mut self) -> bool {
+        if !self.in_scope("tr", Scope::Table) {
+            self.error(ErrorCode::UnexpectedEndTag);
+            return false;
+        }
+        self.clear_to_context(&["tr"]);
+        self.pop();
+        self.mode = InsertionMode::InTableBody;
+        true
+    }
+
+    fn in_row(&mut self, token: Token) {
+        match token {
+            Token::StartTag(tag) if matches!(tag.name.as_str(), "th" | "td") => {
+                self.clear_to_context(&["tr"]);
+                self.insert_html_element(&tag);
+                self.mode = InsertionMode::InCell;
+                self.active_formatting.push(FormattingEntry::Marker);
+            }
+            Token::EndTag(ref tag) if tag.name == "tr" => {
+                self.close_row();
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "caption" | "col" | "colgroup" | "tbody" | "tfoot" | "thead" | "tr"
+                ) =>
+            {
+                if self.close_row() {
+                    self.dispatch(token);
+                }
+            }
+            Token::EndTag(ref tag) if tag.name == "table" => {
+                if self.close_row() {
+                    self.dispatch(token);
+                }
+            }
+            Token::EndTag(ref tag) if TABLE_SECTIONS.contains(&tag.name.as_str()) => {
+                if !self.in_scope(&tag.name, Scope::Table) {
+                    self.error(ErrorCode::UnexpectedEndTag);
+                    return;
+                }
+                if self.close_row() {
+                    self.dispatch(token);
+                }
+            }
+            Token::EndTag(ref tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "body" | "caption" | "col" | "colgroup" | "html" | "td" | "th"
+                ) =>
+            {
+                self.error(ErrorCode::UnexpectedEndTagInTableRow);
+            }
+            token => self.in_table(token),
+        }
+    }
+
+    fn in_cell(&mut self, token: Token) {
+        match token {
+            Token::EndTag(ref tag) if matches!(tag.name.as_str(), "td" | "th") => {
+                if !self.in_scope(&tag.name, Scope::Table) {
+                    self.error(ErrorCode::UnexpectedEndTag);
+                    return;
+                }
+                self.generate_implied_end_tags(None);
+                if !self.current_is(&tag.name) {
+                    self.error(ErrorCode::UnexpectedCellEndTag);
+                }
+                self.pop_until_named(&tag.name);
+                self.clear_formatting_to_marker();
+                self.mode = InsertionMode::InRow;
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "caption" | "col" | "colgroup" | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr"
+                ) =>
+            {
+                if !self.in_scope_where(Scope::Table, |tb, id| tb.is_html_one_of(id, &["td", "th"])) {
+                    self.error(ErrorCode::UnexpectedStartTag);
+                    return;
+                }
+                self.close_cell();
+                self.dispatch(token);
+            }
+            Token::EndTag(ref tag) if matches!(tag.name.as_str(), "body" | "caption" | "col" | "colgroup" | "html") => {
+                self.error(ErrorCode::UnexpectedEndTag);
+            }
+            Token::EndTag(ref tag)
+                if matches!(tag.name.as_str(), "table" | "tbody" | "tfoot" | "thead" | "tr") =>
+            {
+                if !self.in_scope(&tag.name, Scope::Table) {
+                    self.error(ErrorCode::UnexpectedEndTag);
+                    return;
+                }
+                self.close_cell();
+                self.dispatch(token);
+            }
+            token => self.in_body(token),
+        }
+    }
+
+    fn in_select(&mut self, token: Token) {
+        match token {
+            Token::Character(s) => {
+                if s.starts_with('\0') {
+                    self.error(ErrorCode::InvalidCodepoint);
+                } else {
+                    self.insert_text(&s);
+                }
+            }
+            Token::Comment(data) => self.insert_comment(data, None),
+            Token::Doctype(_) => self.error(ErrorCode::UnexpectedDoctype),
+            Token::StartTag(tag) => match tag.name.as_str() {
+                "html" => self.in_body(Token::StartTag(tag)),
+                "option" => {
+                    if self.current_is("option") {
+                        self.pop();
+                    }
+                    self.insert_html_element(&tag);
+                }
+                "optgroup" => {
+                    if self.current_is("option") {
+                        self.pop();
+                    }
+                    if self.current_is("optgroup") {
+                        self.pop();
+                    }
+                    self.insert_html_element(&tag);
+                }
+                "hr" => {
+                    if self.current_is("option") {
+                        self.pop();
+                    }
+                    if self.current_is("optgroup") {
+                        self.pop();
+                    }
+                    self.insert_html_element(&tag);
+                    self.pop();
+                }
+                "select" => {
+                    self.error(ErrorCode::UnexpectedSelectInSelect);
+                    if self.in_scope("select", Scope::Select) {
+                        self.end_select_early();
+                        self.reset_insertion_mode();
+                    }
+                }
+                "input" | "keygen" | "textarea" => {
+                    self.error(ErrorCode::UnexpectedInputInSelect);
+                    if self.in_scope("select", Scope::Select) {
+                        self.pop_until_named("select");
+                        self.reset_insertion_mode();
+                        self.dispatch(Token::StartTag(tag));
+                    }
+                }
+                "script" | "template" => self.in_head(Token::StartTag(tag)),
+                _ => self.error(ErrorCode::UnexpectedStartTagInSelect),
+            },
+            Token::EndTag(tag) => match tag.name.as_str() {
+                "optgroup" => {
+                    let n = self.open.len();
+                    if self.current_is("option") && n >= 2 && self.is_html_named(self.open[n - 2], "optgroup") {
+                        self.pop();
+                    }
+                    if self.current_is("optgroup") {
+                        self.pop();
+                    } else {
+                        self.error(ErrorCode::UnexpectedEndTagInSelect);
+                    }
+                }
+                "option" => {
+                    if self.current_is("option") {
+                        self.pop();
+                    } else {
+                        self.error(ErrorCode::UnexpectedEndTagInSelect);
+                    }
+                }
+                "select" => {
+                    if !self.in_scope("select", Scope::Select) {
+                        self.error(ErrorCode::UnexpectedEndTagInSelect);
+                        return;
+                    }
+                    self.pop_until_named("select");
+                    self.reset_insertion_mode();
+                }
+                "template" => self.in_head(Token::EndTag(tag)),
+                _ => self.error(ErrorCode::UnexpectedEndTagInSelect),
+            },
+            Token::Eof => self.in_body(Token::Eof),
+        }
+    }
+
+    fn in_select_in_table(&mut self, token: Token) {
+        const TABLE_TAGS: &[&str] = &["caption", "table", "tbody", "tfoot", "thead", "tr", "td", "th"];
+        match token {
+            Token::StartTag(ref tag) if TABLE_TAGS.contains(&tag.name.as_str()) => {
+                self.error(ErrorCode::UnexpectedTableElementStartTagInSelectInTable);
+                self.pop_until_named("select");
+                self.reset_insertion_mode();
+                self.dispatch(token);
+            }
+            Token::EndTag(ref tag) if TABLE_TAGS.contains(&tag.name.as_str()) => {
+                self.error(ErrorCode::UnexpectedTableElementEndTagInSelectInTable);
+                if !self.in_scope(&tag.name, Scope::Table) {
+                    return;
+                }
+                self.pop_until_named("select");
+                self.reset_insertion_mode();
+                self.dispatch(token);
+            }
+            token => self.in_select(token),
+        }
+    }
+
+    fn foreign_content(&mut self, token: Token) {
+        match token {
+            Token::Character(s) => match s.chars().next().map(char_class) {
+                Some(CharClass::Null) => {
+                    for _ in s.chars() {
+                        self.error(ErrorCode::InvalidCodepoint);
+                        self.insert_text("\u{FFFD}");
+                    }
+                }
+                Some(CharClass::Whitespace) => self.insert_text(&s),
+                _ => {
+                    self.insert_text(&s);
+                    self.frameset_ok = false;
+                }
+            },
+            Token::Comment(data) => self.insert_comment(data, None),
+            Token::Doctype(_) => self.error(ErrorCode::UnexpectedDoctype),
+            Token::StartTag(tag)
+                if self.context.is_none()
+                    && (BREAKOUT_TAGS.contains(&tag.name.as_str())
+                    || (tag.name == "font"
+                        && tag
+                            .attrs
+                            .iter()
+                            .any(|a| matches!(a.name.as_str(), "color" | "face" | "size")))) =>
+            {
+                self.error(ErrorCode::UnexpectedHtmlElementInForeignContent);
+                while let Some(&id) = self.open.last() {
+                    if self.is_mathml_text_integration_point(id)
+                        || self.is_html_integration_point(id)
+                        || self.name(id).is_some_and(|n| n.ns == Namespace::Html)
+                    {
+                        break;
+                    }
+                    self.pop();
+                }
+                self.process_in_mode(self.mode, Token::StartTag(tag));
+            }
+            Token::StartTag(tag) => {
+                let ns = self
+                    .adjusted_current()
+                    .and_then(|id| self.name(id))
+                    .map(|n| n.ns.clone())
+                    .unwrap_or(Namespace::Html);
+                self.insert_foreign_element(&tag, ns);
+            }
+            Token::EndTag(tag) => {
+                let mut index = self.open.len() - 1;
+                let matches_tag =
+                    |tb: &Self, id: NodeId| tb.name(id).is_some_and(|n| n.local.eq_ignore_ascii_case(&tag.name));
+                if !matches_tag(self, self.open[index]) {
+                    self.error(ErrorCode::UnexpectedEndTag);
+                }
+                loop {
+                    if index == 0 {
+                        return;
+                    }
+                    let node = self.open[index];
+                    if matches_tag(self, node) {
+                        self.truncate_open(index);
+                        return;
+                    }
+                    index -= 1;
+                    if self.name(self.open[index]).is_some_and(|n| n.ns == Namespace::Html) {
+                        self.process_in_mode(self.mode, Token::EndTag(tag));
+                        return;
+                    }
+                }
+            }
+            Token::Eof => self.process_in_mode(self.mode, Token::Eof),
+        }
+    }
+
+    fn in_template(&mut self, token: Token) {
+        match token {
+            Token::Character(_) | Token::Comment(_) | Token::Doctype(_) => self.in_body(token),
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "base"
+                        | "basefont"
+                        | "bgsound"
+                        | "link"
+                        | "meta"
+                        | "noframes"
+                        | "script"
+                        | "style"
+                        | "template"
+                        | "title"
+                ) =>
+            {
+                self.in_head(token);
+            }
+            Token::EndTag(ref tag) if tag.name == "template" => self.in_head(token),
+            Token::StartTag(ref tag) => {
+                let mode = match tag.name.as_str() {
+                    "caption" | "colgroup" | "tbody" | "tfoot" | "thead" => InsertionMode::InTable,
+                    "col" => InsertionMode::InColumnGroup,
+                    "tr" => InsertionMode::InTableBody,
+                    "td" | "th" => InsertionMode::InRow,
+                    _ => InsertionMode::InBody,
+                };
+                self.template_modes.pop();
+                self.template_modes.push(mode);
+                self.reprocess_in(mode, token);
+            }
+            Token::EndTag(_) => self.error(ErrorCode::UnexpectedEndTag),
+            Token::Eof => {
+                if !self.has_template_on_stack() {
+                    self.stop_parsing();
+                    return;
+                }
+                self.error_unclosed(ErrorCode::EofInTemplate, self.current());
+                self.pop_until_named("template");
+                self.clear_formatting_to_marker();
+                self.template_modes.pop();
+                self.reset_insertion_mode();
+                self.dispatch(Token::Eof);
+            }
+        }
+    }
+
+    fn after_body(&mut self, token: Token) {
+        match token {
+            Token::Character(ref s) if s.starts_with(is_whitespace_char) => self.in_body(token),
+            Token::Comment(data) => {
+                let html = self.open.first().copied().unwrap_or_else(|| self.sink.document());
+                self.insert_comment(data, Some((html, None)));
+            }
+            Token::Doctype(_) => self.error(ErrorCode::UnexpectedDoctype),
+            Token::StartTag(ref tag) if tag.name == "html" => self.in_body(token),
+            Token::EndTag(ref tag) if tag.name == "html" && self.context.is_some() => {
+                self.error(ErrorCode::UnexpectedEndTagAfterBodyInnerhtml);
+            }
+            Token::EndTag(ref tag) if tag.name == "html" => {
+                self.mode = InsertionMode::AfterAfterBody;
+                if let Some(&html) = self.open.first() {
+                    self.record_end_tag(html);
+                }
+            }
+            Token::Eof => self.stop_parsing(),
+            token => {
+                self.error(match &token {
+                    Token::StartTag(_) => ErrorCode::UnexpectedStartTagAfterBody,
+                    Token::EndTag(_) => ErrorCode::UnexpectedEndTagAfterBody,
+                    _ => ErrorCode::UnexpectedCharAfterBody,
+                });
+                self.reprocess_in(InsertionMode::InBody, token);
+            }
+        }
+    }
+
+    fn in_frameset(&mut self, token: Token) {
+        match token {
+            Token::Character(ref s) if s.starts_with(is_whitespace_char) => self.insert_text(s),
+            Token::Comment(data) => self.insert_comment(data, None),
+            Token::Doctype(_) => self.error(ErrorCode::UnexpectedDoctype),
+            Token::StartTag(tag) => match tag.name.as_str() {
+                "html" => self.in_body(Token::StartTag(tag)),
+                "frameset" => {
+                    self.insert_html_element(&tag);
+                }
+                "frame" => {
+                    self.insert_html_element(&tag);
+                    self.pop();
+                }
+                "noframes" => self.in_head(Token::StartTag(tag)),
+                _ => self.error(ErrorCode::UnexpectedStartTagInFrameset),
+            },
+            Token::EndTag(tag) if tag.name == "frameset" => {
+                if self.open.len() == 1 {
+                    self.error(ErrorCode::UnexpectedFramesetInFramesetInnerhtml);
+                    return;
+                }
+                self.pop();
+                if !self.current_is("frameset") {
+                    self.mode = InsertionMode::AfterFrameset;
+                }
+            }
+            Token::EndTag(_) => self.error(ErrorCode::UnexpectedEndTagInFrameset),
+            Token::Eof => {
+                if self.open.len() != 1 {
+                    self.error_unclosed(ErrorCode::EofInFrameset, self.current());
+                }
+                self.stop_parsing();
+            }
+            Token::Character(_) => self.error(ErrorCode::UnexpectedCharInFrameset),
+        }
+    }
+
+    fn after_frameset(&mut self, token: Token) {
+        match token {
+            Token::Character(ref s) if s.starts_with(is_whitespace_char) => self.insert_text(s),
+            Token::Comment(data) => self.insert_comment(data, None),
+            Token::Doctype(_) => self.error(ErrorCode::UnexpectedDoctype),
+            Token::StartTag(ref tag) if tag.name == "html" => self.in_body(token),
+            Token::StartTag(ref tag) if tag.name == "noframes" => self.in_head(token),
+            Token::EndTag(ref tag) if tag.name == "html" => self.mode = InsertionMode::AfterAfterFrameset,
+            Token::Eof => self.stop_parsing(),
+            Token::StartTag(_) => self.error(ErrorCode::UnexpectedStartTagAfterFrameset),
+            Token::EndTag(_) => self.error(ErrorCode::UnexpectedEndTagAfterFrameset),
+            Token::Character(_) => self.error(ErrorCode::UnexpectedCharAfterFrameset),
+        }
+    }
+
+    fn after_after_body(&mut self, token: Token) {
+        match token {
+            Token::Comment(data) => {
+                let root = self.sink.document();
+                self.insert_comment(data, Some((root, None)));
+            }
+            Token::Doctype(_) => self.in_body(token),
+            Token::Character(ref s) if s.starts_with(is_whitespace_char) => self.in_body(token),
+            Token::StartTag(ref tag) if tag.name == "html" => self.in_body(token),
+            Token::Eof => self.stop_parsing(),
+            token => {
+                self.error(match &token {
+                    Token::StartTag(_) => ErrorCode::ExpectedEofButGotStartTag,
+                    Token::EndTag(_) => ErrorCode::ExpectedEofButGotEndTag,
+                    _ => ErrorCode::ExpectedEofButGotChar,
+                });
+                self.reprocess_in(InsertionMode::InBody, token);
+            }
+        }
+    }
+
+    fn after_after_frameset(&mut self, token: Token) {
+        match token {
+            Token::Comment(data) => {
+                let root = self.sink.document();
+                self.insert_comment(data, Some((root, None)));
+            }
+            Token::Doctype(_) => self.in_body(token),
+            Token::Character(ref s) if s.starts_with(is_whitespace_char) => self.in_body(token),
+            Token::StartTag(ref tag) if tag.name == "html" => self.in_body(token),
+            Token::StartTag(ref tag) if tag.name == "noframes" => self.in_head(token),
+            Token::Eof => self.stop_parsing(),
+            Token::StartTag(_) => self.error(ErrorCode::ExpectedEofButGotStartTag),
+            Token::EndTag(_) => self.error(ErrorCode::ExpectedEofButGotEndTag),
+            Token::Character(_) => self.error(ErrorCode::ExpectedEofButGotChar),
+        }
+    }
+}
+
+impl TreeBuilder<Document> {
+    pub(crate) fn into_fragment(mut self) -> DocumentFragment {
+        let root = self.sink.root;
+        let html = self.sink.arena[root].children.first().copied();
+        if let Some(html) = html {
+            dom::detach(&mut self.sink.arena, html);
+            dom::reparent_children(&mut self.sink.arena, html, root);
+        }
+        self.sink.arena[root].data = NodeData::DocumentFragment;
+        DocumentFragment {
+            arena: self.sink.arena,
+            root,
+            context: self.context,
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use oxihtml::encoding::{self, prescan};
+use oxihtml::serialize::to_test_format;
+use oxihtml::{Confidence, Encoding, EncodingHint, ErrorCode, Options, Parser};
+
+fn parse_bytes(input: &[u8], hint: EncodingHint) -> (String, Option<Encoding>, Confidence) {
+    let mut parser = Parser::new(Options::default());
+    let parsed = parser.parse_document_bytes(input, hint);
+    (to_test_format(&parsed.value.arena, parsed.value.root), parsed.encoding, parsed.confidence)
+}
+
+#[test]
+fn resolves_labels_like_the_encoding_standard() {
+    assert_eq!(Encoding::for_label(" Latin1\n"), Some(Encoding::Windows1252));
+    assert_eq!(Encoding::for_label("utf8"), Some(Encoding::Utf8));
+    assert_eq!(Encoding::for_label("ISO-8859-9"), Some(Encoding::Windows1254));
+    assert_eq!(Encoding::for_label("iso-2022-kr"), Some(Encoding::Replacement));
+    assert_eq!(Encoding::for_label("shift_jis"), None);
+    assert_eq!(Encoding::for_label("bogus"), None);
+    assert_eq!(Encoding::Koi8U.name(), "KOI8-U");
+}
+
+#[test]
+fn decodes_single_byte_and_utf16_input() {
+    assert_eq!(Encoding::Windows1252.decode(b"\x80\x81\xE9"), "\u{20AC}\u{81}\u{E9}");
+    assert_eq!(Encoding::Iso8859_7.decode(b"\xE1\xAE"), "\u{3B1}\u{FFFD}");
+    assert_eq!(Encoding::Koi8U.decode(b"\xAE\xC1"), "\u{45E}\u{430}");
+    assert_eq!(Encoding::Utf16Le.decode(b"a\0=\xD8\0\xDEz"), "a\u{1F600}\u{FFFD}");
+    assert_eq!(Encoding::Utf16Be.decode(b"\xD8\x00"), "\u{FFFD}");
+    assert_eq!(Encoding::Utf8.decode(b"a\xF0\x9F\x98b"), "a\u{FFFD}b");
+    assert_eq!(Encoding::XUserDefined.decode(b"\x80"), "\u{F780}");
+
+    let parsed = Parser::new(Options {
+        collect_errors: true,
+        ..Options::default()
+    })
+    .parse_document_bytes(b"\xFF\xFE<\0!\0D\0O\0C\0T\0Y\0P\0E\0 \0h\0t\0m\0l\0>\0\0\xD8x\0", EncodingHint::default());
+    assert!(to_test_format(&parsed.value.arena, parsed.value.root).ends_with("\"\u{FFFD}x\""));
+    assert!(parsed.errors.iter().all(|e| e.code != ErrorCode::SurrogateInInputStream));
+}
+
+#[test]
+fn prescan_finds_meta_charset_and_pragma() {
+    assert_eq!(prescan(b"<!-- <meta charset=koi8-r> --><meta charset='ISO-8859-2'>"), Some(Encoding::Iso8859_2));
+    assert_eq!(
+        prescan(b"<meta content=\"text/html; charset=windows-1251\" http-equiv=Content-Type>"),
+        Some(Encoding::Windows1251)
+    );
+    assert_eq!(prescan(b"<meta content=\"text/html; charset=windows-1251\">"), None);
+    assert_eq!(prescan(b"<meta charset=utf-16le>"), Some(Encoding::Utf8));
+    assert_eq!(prescan(b"<title a='<meta charset=koi8-r>'></title>"), None);
+    assert_eq!(prescan(b"<meta charset=nope><meta charset=koi8-r>"), Some(Encoding::Koi8R));
+}
+
+#[test]
+fn sniffing_prefers_bom_then_transport_then_prescan() {
+    let doc = b"<meta charset=windows-1251><p>\xE0";
+    let (tree, encoding, confidence) = parse_bytes(doc, EncodingHint::default());
+    assert!(tree.contains("\"\u{430}\""));
+    assert_eq!((encoding, confidence), (Some(Encoding::Windows1251), Confidence::Tentative));
+
+    let hint = EncodingHint {
+        transport: Some("iso-8859-5".into()),
+        fallback: None,
+    };
+    let (tree, encoding, confidence) = parse_bytes(doc, hint);
+    assert!(tree.contains("\"\u{440}\""));
+    assert_eq!((encoding, confidence), (Some(Encoding::Iso8859_5), Confidence::Certain));
+
+    let (tree, encoding, _) = parse_bytes(b"\xEF\xBB\xBF<p>\xC3\xA9", EncodingHint::default());
+    assert!(tree.contains("\"\u{E9}\""));
+    assert_eq!(encoding, Some(Encoding::Utf8));
+
+    let (_, encoding, confidence) = parse_bytes(b"<p>\xE9", EncodingHint::default());
+    assert_eq!((encoding, confidence), (Some(Encoding::Windows1252), Confidence::Tentative));
+    let (text, _, _) = encoding::decode(b"\xFE\xFF\0a", &EncodingHint::default());
+    assert_eq!(text, "a");
+}
+
+#[test]
+fn late_meta_charset_restarts_the_parse() {
+    let mut doc = b"<!DOCTYPE html><head><title>".to_vec();
+    doc.extend(std::iter::repeat_n(b'x', 1100));
+    doc.extend(b"</title><meta charset=koi8-r></head><p>\xC1");
+    let mut parser = Parser::new(Options::default());
+    let parsed = parser.parse_document_bytes(&doc, EncodingHint::default());
+    let change = parsed.encoding_change.expect("restart recorded");
+    assert_eq!((change.from, change.to), (Encoding::Windows1252, Encoding::Koi8R));
+    assert_eq!((parsed.encoding, parsed.confidence), (Some(Encoding::Koi8R), Confidence::Certain));
+    assert!(to_test_format(&parsed.value.arena, parsed.value.root).contains("\"\u{430}\""));
+
+    let hint = EncodingHint {
+        transport: Some("windows-1252".into()),
+        fallback: None,
+    };
+    let parsed = parser.parse_document_bytes(&doc, hint);
+    assert_eq!(parsed.encoding_change, None);
+    assert_eq!(parsed.encoding, Some(Encoding::Windows1252));
+
+    let parsed = parser.parse_document_bytes(b"<meta charset=koi8-r><meta charset=iso-8859-2>", EncodingHint::default());
+    assert_eq!(parsed.encoding_change, None);
+}
@@ -0,0 +1,27 @@
+use oxihtml::decode_character_references;
+use oxihtml::entities::{lookup_named_character_reference, NAMED_CHARACTER_REFERENCES};
+
+#[test]
+fn table_has_every_whatwg_entry() {
+    assert_eq!(NAMED_CHARACTER_REFERENCES.len(), 2231);
+    assert_eq!(lookup_named_character_reference("amp;"), Some("&"));
+    assert_eq!(lookup_named_character_reference("amp"), Some("&"));
+    assert_eq!(lookup_named_character_reference("NotEqualTilde;"), Some("\u{2242}\u{338}"));
+    assert_eq!(lookup_named_character_reference("notanentity;"), None);
+}
+
+#[test]
+fn decodes_named_references_with_longest_match() {
+    assert_eq!(decode_character_references("a &amp; b"), "a & b");
+    assert_eq!(decode_character_references("&notit;"), "\u{AC}it;");
+    assert_eq!(decode_character_references("&notin;"), "\u{2209}");
+    assert_eq!(decode_character_references("&bogus; &"), "&bogus; &");
+}
+
+#[test]
+fn decodes_numeric_references_with_spec_replacements() {
+    assert_eq!(decode_character_references("&#65;&#x42;&#X43"), "ABC");
+    assert_eq!(decode_character_references("&#x80;"), "\u{20AC}");
+    assert_eq!(decode_character_references("&#0;&#xD800;&#x110000;"), "\u{FFFD}\u{FFFD}\u{FFFD}");
+    assert_eq!(decode_character_references("&#;&#x;"), "&#;&#x;");
+}
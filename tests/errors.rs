@@ -0,0 +1,29 @@
+use oxihtml::{ErrorCode, Location, ParseError, Span};
+
+#[test]
+fn error_codes_round_trip_through_strings() {
+    for &code in ErrorCode::ALL {
+        assert_eq!(code.as_str().parse::<ErrorCode>(), Ok(code));
+    }
+    assert_eq!(ErrorCode::EofInTag.as_str(), "eof-in-tag");
+    assert_eq!(ErrorCode::AdoptionAgency1_1.as_str(), "adoption-agency-1.1");
+    assert!("not-a-real-error".parse::<ErrorCode>().is_err());
+}
+
+#[test]
+fn parse_error_displays_location_and_code() {
+    let err = ParseError {
+        code: ErrorCode::DuplicateAttribute,
+        location: Location {
+            line: 3,
+            col: 7,
+            col_utf16: 7,
+            offset: 20,
+        },
+        span: Span::new(19, 20),
+        secondary: None,
+    };
+    assert_eq!(err.to_string(), "3:7: duplicate-attribute");
+    let boxed: Box<dyn std::error::Error> = Box::new(err);
+    assert_eq!(boxed.to_string(), "3:7: duplicate-attribute");
+}
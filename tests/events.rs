@@ -0,0 +1,191 @@
+use oxihtml::dom::{self, Document, Namespace, NodeId};
+use oxihtml::serialize::to_test_format;
+use oxihtml::{Event, EventSink, Options, Parser};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+// Counts the heap bytes held by the current thread, so a test can see
+// everything the parser keeps and not only what the sink reports.
+struct CountingAlloc;
+
+thread_local! {
+    static HELD: Cell<isize> = const { Cell::new(0) };
+}
+
+fn count(delta: isize) {
+    let _ = HELD.try_with(|held| held.set(held.get() + delta));
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count(layout.size() as isize);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        count(-(layout.size() as isize));
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+fn held_bytes() -> isize {
+    HELD.with(Cell::get)
+}
+
+// Rebuilds a document from events so it can be compared with the tree
+// `parse_document` returns.
+fn rebuild(events: Vec<Event>) -> Document {
+    let mut doc = Document::new_empty();
+    let mut open: Vec<NodeId> = vec![doc.root];
+    for event in events {
+        let parent = *open.last().unwrap();
+        match event {
+            Event::StartElement { name, attrs } => {
+                let template = name.ns == Namespace::Html && name.local == "template";
+                let id = doc.create_element(name);
+                for attr in attrs {
+                    dom::set_attr(&mut doc.arena, id, attr);
+                }
+                dom::append_child(&mut doc.arena, parent, id);
+                open.push(if template {
+                    dom::ensure_template_contents(&mut doc.arena, id)
+                } else {
+                    id
+                });
+            }
+            Event::EndElement(_) => {
+                open.pop();
+            }
+            Event::Text(text) => {
+                let id = doc.create_text(text);
+                dom::append_child(&mut doc.arena, parent, id);
+            }
+            Event::Comment(data) => {
+                let id = doc.create_comment(data);
+                dom::append_child(&mut doc.arena, parent, id);
+            }
+            Event::Doctype(doctype) => {
+                let id = doc.create_doctype(doctype);
+                dom::append_child(&mut doc.arena, parent, id);
+            }
+            Event::ExtraAttributes { name, attrs } => {
+                let target = open.iter().copied().find(|&id| match &doc.arena[id].data {
+                    dom::NodeData::Element { name: n, .. } => *n == name,
+                    _ => false,
+                });
+                for attr in attrs {
+                    dom::set_attr(&mut doc.arena, target.unwrap(), attr);
+                }
+            }
+        }
+    }
+    assert_eq!(open, vec![doc.root], "unbalanced events");
+    doc
+}
+
+fn events(input: &str) -> Vec<Event> {
+    let mut events = Vec::new();
+    Parser::new(Options::default()).parse_document_into(EventSink::new(|e| events.push(e)), input);
+    events
+}
+
+fn assert_same_tree(input: &str) {
+    let expected = Parser::new(Options::default()).parse_document(input).value;
+    let rebuilt = rebuild(events(input));
+    assert_eq!(
+        to_test_format(&rebuilt.arena, rebuilt.root),
+        to_test_format(&expected.arena, expected.root),
+        "input: {input:?}"
+    );
+}
+
+#[test]
+fn events_describe_the_final_tree() {
+    for input in [
+        "<!DOCTYPE html><title>t</title><p>one<p>two",
+        "<b>1<p>2</b>3</p>4",
+        "<a><div><a>x</a></div>y",
+        "<table><tr><td>a</td>b<b>c</table>d",
+        "<table>x<tr>y</table>z",
+        "<template><tr><td>cell</template><p>after",
+        "<html a=1><body b=2><p>x<html c=3><body d=4 b=5>",
+        "<p>text<frameset><frame>",
+        "<!-- c --><html><p>x</body><!-- in html --></html><!-- after -->y",
+        "<svg><foreignObject><p>html</foreignObject><circle/></svg>",
+        "<select><option>a<option>b</select><i>c<b>d</i>e",
+    ] {
+        assert_same_tree(input);
+    }
+}
+
+#[test]
+fn start_events_carry_resolved_namespaces() {
+    let names: Vec<(Namespace, String)> = events("<p><svg><title>x</title></svg><math><mi>")
+        .into_iter()
+        .filter_map(|e| match e {
+            Event::StartElement { name, .. } => Some((name.ns, name.local)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        names[3..],
+        [
+            (Namespace::Html, "p".to_string()),
+            (Namespace::Svg, "svg".to_string()),
+            (Namespace::Svg, "title".to_string()),
+            (Namespace::MathMl, "math".to_string()),
+            (Namespace::MathMl, "mi".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn memory_is_bounded_by_open_elements() {
+    let input = "<ul>".to_string() + &"<li><b>item</b> <i>text</i>\n".repeat(5_000) + "</ul>";
+    let mut count = 0;
+    let mut peak = 0;
+    let mut parser = Parser::new(Options::default()).begin_document_into(EventSink::new(|_| count += 1));
+    for chunk in input.as_bytes().chunks(1000) {
+        parser.feed_bytes(chunk);
+        peak = peak.max(parser.sink().live_nodes());
+    }
+    parser.finish();
+    assert!(peak < 100, "peak of {peak} live nodes");
+    assert!(count > 40_000);
+}
+
+#[test]
+fn selectedcontent_stays_empty_when_streamed() {
+    let input = "<select><button><selectedcontent></selectedcontent></button><option>A</option></select>";
+    let opts = Options {
+        customizable_select: true,
+        ..Options::default()
+    };
+    let mut streamed = Vec::new();
+    Parser::new(opts.clone()).parse_document_into(EventSink::new(|e| streamed.push(e)), input);
+    let rebuilt = rebuild(streamed);
+    let expected = Parser::new(opts).parse_document(input).value;
+    let expected = to_test_format(&expected.arena, expected.root);
+    let without_copy = expected.replacen("|           \"A\"\n", "", 1);
+    assert_ne!(without_copy, expected);
+    assert_eq!(to_test_format(&rebuilt.arena, rebuilt.root), without_copy);
+}
+
+#[test]
+fn parser_state_does_not_grow_with_the_input() {
+    let chunk = "<p>x</p>".repeat(500);
+    let mut parser = Parser::new(Options::default()).begin_document_into(EventSink::new(|_| {}));
+    let mut held = Vec::new();
+    for round in 0..200 {
+        parser.feed(&chunk);
+        if round % 50 == 49 {
+            held.push(held_bytes());
+        }
+    }
+    parser.finish();
+    let growth = held[3] - held[0];
+    assert!(growth < 64 * 1024, "grew by {growth} bytes over {held:?}");
+}
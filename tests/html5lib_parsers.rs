@@ -82,3 +82,18 @@ svg svg
     assert_eq!(ctx.tag_name, "svg");
 }
 
+
+#[test]
+fn tree_construction_dat_accepts_script_directive_before_errors() {
+    let dat = "#data\n<noscript><p></noscript>\n#script-off\n#errors\n1:1: some-error\n#document\n| <html>\n";
+
+    let path = temp_path("tc-script.dat");
+    fs::write(&path, dat).unwrap();
+    let cases = parse_tree_construction_dat(&path).unwrap();
+    fs::remove_file(&path).ok();
+
+    assert_eq!(cases.len(), 1);
+    assert_eq!(cases[0].data, "<noscript><p></noscript>");
+    assert_eq!(cases[0].script_directive, ScriptDirective::Off);
+    assert_eq!(cases[0].error_count, 1);
+}
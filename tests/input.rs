@@ -0,0 +1,87 @@
+use oxihtml::input::InputStream;
+use oxihtml::{ErrorCode, Location, Options, Parser, Span};
+
+fn drain(input: &mut InputStream) -> (String, Vec<ErrorCode>) {
+    let mut out = String::new();
+    let mut errors = Vec::new();
+    while let Some(c) = input.next_char() {
+        out.push(c);
+        errors.extend(input.take_error());
+    }
+    (out, errors)
+}
+
+#[test]
+fn normalizes_cr_and_crlf_and_tracks_lines() {
+    let mut input = InputStream::new("a\r\nb\rc\nd");
+    let (text, errors) = drain(&mut input);
+    assert_eq!(text, "a\nb\nc\nd");
+    assert!(errors.is_empty());
+    assert_eq!(
+        input.location(),
+        Location {
+            line: 4,
+            col: 1,
+            col_utf16: 1,
+            offset: 8
+        }
+    );
+}
+
+#[test]
+fn reports_control_characters_and_noncharacters_once() {
+    let mut input = InputStream::new("a\u{1}\u{FFFF}\t\0");
+    assert_eq!(input.next_char(), Some('a'));
+    assert_eq!(input.next_char(), Some('\u{1}'));
+    assert_eq!(input.take_error(), Some(ErrorCode::ControlCharacterInInputStream));
+    input.back();
+    assert_eq!(input.next_char(), Some('\u{1}'));
+    assert_eq!(input.take_error(), None);
+    let (_, errors) = drain(&mut input);
+    assert_eq!(errors, vec![ErrorCode::NoncharacterInInputStream]);
+}
+
+#[test]
+fn parse_errors_carry_real_positions() {
+    let mut parser = Parser::new(Options {
+        collect_errors: true,
+        ..Options::default()
+    });
+    let parsed = parser.parse_document("<!DOCTYPE html><p>\r\n  <a b=1 b=2></a>");
+    assert_eq!(parsed.errors.len(), 1);
+    assert_eq!(
+        parsed.errors[0].code,
+        ErrorCode::DuplicateAttribute
+    );
+    assert_eq!(parsed.errors[0].location.line, 2);
+    assert_eq!(parsed.errors[0].location.col, 11);
+}
+
+#[test]
+fn parse_error_spans_map_back_into_the_input() {
+    let mut parser = Parser::new(Options {
+        collect_errors: true,
+        ..Options::default()
+    });
+    let input = "<!DOCTYPE html><p>\r\n  <a b=1 b=2></a>";
+    let parsed = parser.parse_document(input);
+    let err = &parsed.errors[0];
+    assert_eq!(&input[err.span.start..err.span.end], "=");
+    assert_eq!(err.location.offset, err.span.end);
+    assert_eq!(Location::at(input, err.span.end), err.location);
+
+    let input = "<div><span></div>";
+    let parsed = parser.parse_document(input);
+    let err = parsed.errors.iter().find(|e| e.code == ErrorCode::EndTagTooEarly).unwrap();
+    assert_eq!(&input[err.span.start..err.span.end], "</div>");
+    assert_eq!(err.secondary, Some(Span::new(5, 11)));
+}
+
+#[test]
+fn utf16_columns_count_surrogate_pairs() {
+    let mut input = InputStream::new("\u{1F600}é<");
+    drain(&mut input);
+    let loc = input.location();
+    assert_eq!((loc.col, loc.col_utf16, loc.offset), (3, 4, 7));
+    assert_eq!(Location::at("a\r\n\u{1F600}b", 7).col_utf16, 2);
+}
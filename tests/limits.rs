@@ -0,0 +1,285 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use oxihtml::dom::{Document, NodeData, NodeId};
+use oxihtml::serialize::to_test_format;
+use oxihtml::{Budget, ErrorCode, FragmentContext, Interruption, Limit, LimitAction, Limits, Options, Parsed, Parser};
+
+fn parse(limits: Limits, input: &str) -> Parsed<Document> {
+    Parser::new(Options {
+        collect_errors: true,
+        limits,
+        ..Options::default()
+    })
+    .parse_document(input)
+}
+
+fn codes(parsed: &Parsed<Document>) -> Vec<ErrorCode> {
+    parsed.errors.iter().map(|e| e.code).collect()
+}
+
+fn depth(doc: &Document, node: NodeId) -> usize {
+    let mut depth = 0;
+    let mut stack = vec![(node, 0)];
+    while let Some((id, d)) = stack.pop() {
+        depth = depth.max(d);
+        stack.extend(doc.arena[id].children.iter().map(|&c| (c, d + 1)));
+    }
+    depth
+}
+
+fn text_lengths(doc: &Document) -> Vec<usize> {
+    doc.arena
+        .iter()
+        .filter_map(|n| match &n.data {
+            NodeData::Text(t) => Some(t.len()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn deep_nesting_is_flattened_at_max_depth() {
+    let limits = Limits {
+        max_depth: Some(8),
+        ..Limits::default()
+    };
+    let input = "<div>x".repeat(20_000) + "<br><a><b><table><tr><td>y";
+    let parsed = parse(limits, &input);
+    assert!(depth(&parsed.value, parsed.value.root) <= 8 + 1);
+    assert!(codes(&parsed).contains(&ErrorCode::TreeTooDeep));
+    assert_eq!(text_lengths(&parsed.value).iter().sum::<usize>(), 20_001);
+    assert!(to_test_format(&parsed.value.arena, parsed.value.root).contains("<br>"));
+}
+
+#[test]
+fn adoption_agency_stays_within_max_depth() {
+    let limits = Limits {
+        max_depth: Some(16),
+        ..Limits::default()
+    };
+    let input = "<a><div>".repeat(5_000) + &"</a>x".repeat(5_000);
+    let parsed = parse(limits, &input);
+    assert!(depth(&parsed.value, parsed.value.root) <= 16 + 1);
+}
+
+#[test]
+fn node_count_stops_the_tree_growing() {
+    let limits = Limits {
+        max_nodes: Some(50),
+        ..Limits::default()
+    };
+    for input in ["<p>a<!--c-->".repeat(1_000), "<b><i><p>x".repeat(1_000), "<a><b><div>x</a>".repeat(1_000)] {
+        let parsed = parse(limits, &input);
+        let nodes = to_test_format(&parsed.value.arena, parsed.value.root).lines().count();
+        assert!(nodes <= 50, "{nodes} nodes from {}", &input[..12]);
+        assert!(nodes >= 40, "{nodes} nodes from {}", &input[..12]);
+        assert_eq!(codes(&parsed).iter().filter(|&&c| c == ErrorCode::TooManyNodes).count(), 1);
+    }
+}
+
+#[test]
+fn end_tags_of_dropped_start_tags_are_dropped_too() {
+    let limits = Limits {
+        max_depth: Some(3),
+        ..Limits::default()
+    };
+    let parsed = parse(limits, "<div><div>a</div>b</div>c");
+    assert_eq!(
+        to_test_format(&parsed.value.arena, parsed.value.root),
+        "| <html>\n|   <head>\n|   <body>\n|     <div>\n|       \"ab\"\n|     \"c\""
+    );
+}
+
+#[test]
+fn selectedcontent_copies_stay_within_max_nodes() {
+    let opts = |max_nodes| Options {
+        collect_errors: true,
+        customizable_select: true,
+        limits: Limits {
+            max_nodes: Some(max_nodes),
+            ..Limits::default()
+        },
+        ..Options::default()
+    };
+    let input = "<select><button><selectedcontent></selectedcontent></button><option>".to_string()
+        + &"<span>x</span>".repeat(20)
+        + "</option></select>";
+    let parsed = Parser::new(opts(100)).parse_document(&input);
+    assert!(parsed.value.arena.len() > 80);
+    assert!(!codes(&parsed).contains(&ErrorCode::TooManyNodes));
+
+    let parsed = Parser::new(opts(60)).parse_document(&input);
+    assert!(parsed.value.arena.len() <= 60, "{} nodes", parsed.value.arena.len());
+    assert!(codes(&parsed).contains(&ErrorCode::TooManyNodes));
+    assert!(!to_test_format(&parsed.value.arena, parsed.value.root).contains("<selectedcontent>\n|             <span>"));
+}
+
+// Inputs that used to take time cubic in their length, and ones that lean on
+// the moves tree construction makes.
+#[test]
+fn hostile_markup_parses_in_bounded_time() {
+    let opts = Options {
+        customizable_select: true,
+        limits: Limits {
+            max_depth: Some(256),
+            max_nodes: Some(50_000),
+            ..Limits::default()
+        },
+        ..Options::default()
+    };
+    let units = [
+        "<p><b id={i}></p>",
+        "<option><span>",
+        "<select><option selected><b>x",
+        "<table><b>x",
+        "<a><div>",
+    ];
+    for unit in units {
+        let input: String = (0..5_000).map(|i| unit.replace("{i}", &i.to_string())).collect();
+        let start = Instant::now();
+        Parser::new(opts.clone()).parse_document(&input);
+        let elapsed = start.elapsed();
+        assert!(elapsed < Duration::from_secs(10), "{unit} took {elapsed:?}");
+    }
+}
+
+#[test]
+fn attributes_and_text_are_trimmed() {
+    let limits = Limits {
+        max_attributes: Some(2),
+        max_attribute_length: Some(4),
+        max_text_length: Some(6),
+        ..Limits::default()
+    };
+    let parsed = parse(limits, "<p a=1 b=23456 c=3>abc</x>def</x>ghi<!--long comment--></p><html d=1 e=2 f=3>");
+    assert_eq!(
+        to_test_format(&parsed.value.arena, parsed.value.root),
+        "| <html>\n|   d=\"1\"\n|   e=\"2\"\n|   <head>\n|   <body>\n|     <p>\n|       a=\"1\"\n|       b=\"2345\"\n|       \"abcdef\"\n|       <!-- long c -->"
+    );
+    let codes = codes(&parsed);
+    for code in [
+        ErrorCode::TooManyAttributes,
+        ErrorCode::AttributeTooLong,
+        ErrorCode::TextTooLong,
+    ] {
+        assert!(codes.contains(&code), "{code} missing from {codes:?}");
+    }
+}
+
+#[test]
+fn oversized_input_is_cut_at_a_char_boundary() {
+    let limits = Limits {
+        max_input_bytes: Some(10),
+        ..Limits::default()
+    };
+    let input = "<p>\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}</p>";
+    let parsed = parse(limits, input);
+    assert_eq!(text_lengths(&parsed.value), [6]);
+    let error = parsed.errors.last().unwrap();
+    assert_eq!(error.code, ErrorCode::InputTooLarge);
+    assert_eq!((error.span.start, error.span.end), (9, input.len()));
+    assert_eq!(error.location.col, 6);
+
+    let mut parser = Parser::new(Options {
+        collect_errors: true,
+        limits,
+        ..Options::default()
+    })
+    .begin_document();
+    for chunk in input.as_bytes().chunks(3) {
+        parser.feed_bytes(chunk);
+    }
+    let streamed = parser.finish();
+    assert_eq!(
+        to_test_format(&streamed.value.arena, streamed.value.root),
+        to_test_format(&parsed.value.arena, parsed.value.root)
+    );
+    assert_eq!(streamed.errors.last().unwrap().location, error.location);
+}
+
+#[test]
+fn abort_stops_at_the_first_limit() {
+    let limits = Limits {
+        max_depth: Some(4),
+        on_exceeded: LimitAction::Abort,
+        ..Limits::default()
+    };
+    let parsed = parse(limits, "<p>one<span>two<b>three</b></span>four");
+    let aborted = parsed.aborted.as_ref().expect("parse should abort");
+    assert_eq!(aborted.limit, Limit::Depth);
+    assert_eq!(aborted.span.start, "<p>one<span>two".len());
+    assert!(!codes(&parsed).contains(&ErrorCode::TreeTooDeep));
+    assert_eq!(text_lengths(&parsed.value), [3, 3]);
+
+    let limits = Limits {
+        max_input_bytes: Some(4),
+        on_exceeded: LimitAction::Abort,
+        ..Limits::default()
+    };
+    let parsed = parse(limits, "<p>too long");
+    assert_eq!(parsed.aborted.map(|a| a.limit), Some(Limit::InputSize));
+    assert!(parsed.value.arena[parsed.value.root].children.is_empty());
+    assert!(parse(Limits::default(), "<p>too long").aborted.is_none());
+}
+
+fn budget_parser(budget: Budget) -> Parser {
+    Parser::new(Options {
+        budget,
+        ..Options::default()
+    })
+}
+
+#[test]
+fn step_budget_leaves_a_complete_prefix() {
+    let input = "<table><tr><td>".to_string() + &"cell ".repeat(20_000) + "</table><p>after";
+    let budget = Budget {
+        max_steps: Some(10_000),
+        ..Budget::default()
+    };
+    let parsed = budget_parser(budget).parse_document(&input);
+    assert_eq!(parsed.interrupted, Some(Interruption::OutOfSteps));
+    let tree = to_test_format(&parsed.value.arena, parsed.value.root);
+    assert!(tree.starts_with("| <html>\n|   <head>\n|   <body>\n|     <table>\n|       <tbody>\n|         <tr>\n|           <td>\n|             \"cell cell"));
+    assert!(!tree.contains("after"));
+    let text: usize = text_lengths(&parsed.value).iter().sum();
+    assert!((9_000..10_000).contains(&text), "{text} bytes of text");
+
+    let budget = Budget {
+        max_steps: Some(1_000_000),
+        ..Budget::default()
+    };
+    assert_eq!(budget_parser(budget).parse_document(&input).interrupted, None);
+}
+
+#[test]
+fn cancel_flag_stops_document_and_fragment_parses() {
+    let cancel = Arc::new(AtomicBool::new(true));
+    let budget = Budget {
+        cancel: Some(cancel.clone()),
+        ..Budget::default()
+    };
+    let mut parser = budget_parser(budget);
+    let parsed = parser.parse_document("<p>never parsed");
+    assert_eq!(parsed.interrupted, Some(Interruption::Cancelled));
+    assert_eq!(
+        to_test_format(&parsed.value.arena, parsed.value.root),
+        "| <html>\n|   <head>\n|   <body>"
+    );
+    let ctx = FragmentContext {
+        namespace: None,
+        tag_name: "div".to_string(),
+    };
+    assert_eq!(parser.parse_fragment(ctx, "<p>x").interrupted, Some(Interruption::Cancelled));
+
+    cancel.store(false, Ordering::Relaxed);
+    let mut streaming = parser.begin_document();
+    streaming.feed(&"<p>first ".repeat(100));
+    cancel.store(true, Ordering::Relaxed);
+    streaming.feed("<p>second");
+    let parsed = streaming.finish();
+    assert_eq!(parsed.interrupted, Some(Interruption::Cancelled));
+    let tree = to_test_format(&parsed.value.arena, parsed.value.root);
+    assert!(tree.contains("first") && !tree.contains("second"));
+}
@@ -0,0 +1,235 @@
+use oxihtml::dom::{append_child, detach, set_attr, Attr, Document, Namespace, NodeData, NodeId, QualName};
+use oxihtml::serialize::{to_html, to_html_preserving};
+use oxihtml::{Options, Parser};
+
+fn html(local: &str) -> QualName {
+    QualName {
+        ns: Namespace::Html,
+        local: local.to_string(),
+    }
+}
+
+fn parse(input: &str, preserve_source: bool) -> Document {
+    Parser::new(Options {
+        preserve_source,
+        ..Options::default()
+    })
+    .parse_document(input)
+    .value
+}
+
+fn first(doc: &Document, local: &str) -> NodeId {
+    (0..doc.arena.len())
+        .find(|&id| matches!(&doc.arena[id].data, NodeData::Element { name, .. } if name.local == local))
+        .unwrap()
+}
+
+#[test]
+fn to_html_follows_the_fragment_serialization_algorithm() {
+    let doc = parse(
+        "<!DOCTYPE html><title>a&amp;b</title><p class='x\"y'>1 &lt; 2&nbsp;<br><script>if (a < b) {}</script>\
+         <template><i>t</i></template><svg xlink:href=u><circle/></svg>",
+        false,
+    );
+    assert_eq!(
+        to_html(&doc.arena, doc.root),
+        "<!DOCTYPE html><html><head><title>a&amp;b</title></head><body><p class=\"x&quot;y\">1 &lt; 2&nbsp;<br>\
+         <script>if (a < b) {}</script><template><i>t</i></template>\
+         <svg xlink:href=\"u\"><circle></circle></svg></p></body></html>"
+    );
+    let p = first(&doc, "p");
+    assert!(to_html(&doc.arena, p).starts_with("1 &lt; 2&nbsp;<br>"));
+}
+
+const MESSY: &str = "<!doctype html>\n<HTML lang=en>\n<Title>Page</TITLE>\n<body>\n\
+    <div CLASS='card'  data-id=7 hidden>\n  <p>caf&eacute; &amp co</p>\n  <img src=a.png alt=\"\"/>\n\
+    </div></span>\n<ul><li>one<li id=two>two</ul>\n";
+
+#[test]
+fn untouched_documents_come_back_byte_for_byte() {
+    let doc = parse(MESSY, true);
+    assert_eq!(to_html_preserving(&doc), MESSY);
+}
+
+#[test]
+fn edits_only_rewrite_the_nodes_they_touch() {
+    let mut doc = parse(MESSY, true);
+    let div = first(&doc, "div");
+    set_attr(
+        &mut doc.arena,
+        div,
+        Attr {
+            name: html("data-id"),
+            value: "8".to_string(),
+        },
+    );
+    assert_eq!(
+        to_html_preserving(&doc),
+        MESSY.replace("<div CLASS='card'  data-id=7 hidden>", "<div CLASS='card' data-id=\"8\" hidden>")
+    );
+
+    let mut doc = parse(MESSY, true);
+    let li = doc.create_element(html("li"));
+    let text = doc.create_text("three & more");
+    append_child(&mut doc.arena, li, text);
+    let ul = first(&doc, "ul");
+    append_child(&mut doc.arena, ul, li);
+    let img = first(&doc, "img");
+    detach(&mut doc.arena, img);
+    let expected = MESSY
+        .replace("two</ul>", "two<li>three &amp; more</li></ul>")
+        .replace("<img src=a.png alt=\"\"/>", "");
+    assert_eq!(to_html_preserving(&doc), expected);
+}
+
+#[test]
+fn misnested_markup_survives_an_edit() {
+    let input = "<p>1<b>2<p>3</b>4";
+    let mut doc = parse(input, true);
+    let body = first(&doc, "body");
+    set_attr(
+        &mut doc.arena,
+        body,
+        Attr {
+            name: html("class"),
+            value: "x".to_string(),
+        },
+    );
+    let out = to_html_preserving(&doc);
+    let reparsed = parse(&out, false);
+    assert_eq!(to_html(&reparsed.arena, reparsed.root), to_html(&doc.arena, doc.root), "{out}");
+}
+
+#[test]
+fn without_preserve_source_it_is_plain_serialization() {
+    let doc = parse("<P class=a>x", false);
+    assert!(doc.original.is_none());
+    assert_eq!(to_html_preserving(&doc), to_html(&doc.arena, doc.root));
+}
+
+#[test]
+fn selectedcontent_copies_are_left_to_the_parser() {
+    let input = "<select><button><selectedcontent></selectedcontent></button><option>One<option selected><b>Two</b></select><p>x";
+    let opts = Options {
+        preserve_source: true,
+        customizable_select: true,
+        ..Options::default()
+    };
+    let doc = Parser::new(opts.clone()).parse_document(input).value;
+    assert_eq!(to_html_preserving(&doc), input);
+
+    let mut doc = Parser::new(opts).parse_document(input).value;
+    let p = first(&doc, "p");
+    set_attr(
+        &mut doc.arena,
+        p,
+        Attr {
+            name: html("id"),
+            value: "y".to_string(),
+        },
+    );
+    assert_eq!(to_html_preserving(&doc), input.replace("<p>", "<p id=\"y\">"));
+}
+
+// Edits `input` parsed with its source kept, and checks that the preserving
+// output parses back into the tree the edit made.
+fn assert_round_trips(input: &str, edit: impl Fn(&mut Document)) -> String {
+    let mut doc = parse(input, true);
+    edit(&mut doc);
+    let out = to_html_preserving(&doc);
+    let reparsed = parse(&out, false);
+    assert_eq!(
+        to_html(&reparsed.arena, reparsed.root),
+        to_html(&doc.arena, doc.root),
+        "{input:?} came out as {out:?}"
+    );
+    out
+}
+
+fn append(doc: &mut Document, parent: &str, local: Option<&str>) {
+    let parent = first(doc, parent);
+    let text = doc.create_text("new");
+    let child = match local {
+        Some(local) => {
+            let element = doc.create_element(html(local));
+            append_child(&mut doc.arena, element, text);
+            element
+        }
+        None => text,
+    };
+    append_child(&mut doc.arena, parent, child);
+}
+
+fn mark(doc: &mut Document, local: &str) {
+    let node = first(doc, local);
+    set_attr(
+        &mut doc.arena,
+        node,
+        Attr {
+            name: html("class"),
+            value: "x".to_string(),
+        },
+    );
+}
+
+#[test]
+fn markup_left_unclosed_is_closed_ahead_of_new_children() {
+    let cases = [
+        ("<ul><li>a<li>b<li>c", "ul", Some("li")),
+        ("<ul><li>a<li>b<li>c", "ul", None),
+        ("<ul><li>a<li><b>c", "ul", Some("li")),
+        ("<dl><dt>a<dd>b", "dl", Some("dt")),
+        ("<dl><dt>a<dd>b", "dl", None),
+        ("<select><option>a<option>b", "select", Some("option")),
+        ("<select><option>a<option>b", "select", None),
+        ("<p>text", "body", None),
+        ("<p>text", "body", Some("div")),
+        ("<p>x<!--c", "body", None),
+        ("text<!--unterminated", "body", None),
+        ("text<!--unterminated", "body", Some("p")),
+        ("<template><template><style>var i", "body", None),
+    ];
+    for (input, parent, local) in cases {
+        assert_round_trips(input, |doc| append(doc, parent, local));
+    }
+    let out = assert_round_trips("<ul><li>a<li>b<li>c", |doc| append(doc, "ul", Some("li")));
+    assert_eq!(out, "<ul><li>a<li>b<li>c<li>new</li>");
+    let out = assert_round_trips("<ul><li>a<li>b<li>c", |doc| append(doc, "ul", None));
+    assert_eq!(out, "<ul><li>a<li>b<li>c</li>new");
+}
+
+#[test]
+fn clones_made_by_the_parser_are_left_to_it() {
+    for input in ["<p><i>x</p>italic", "<ul><li><i>x</li><li>a</ul>", "<i>a<p>b<p>c"] {
+        for local in ["html", "body", "i"] {
+            assert_round_trips(input, |doc| mark(doc, local));
+        }
+        assert_round_trips(input, |doc| append(doc, "body", None));
+    }
+    let out = assert_round_trips("<p><i>x</p>italic", |doc| mark(doc, "html"));
+    assert_eq!(out, "<p><i>x</p>italic".replace("<p>", "<html class=\"x\"><p>"));
+}
+
+#[test]
+fn implied_elements_follow_edits() {
+    assert_round_trips("a</p>b", |doc| {
+        let p = first(doc, "p");
+        detach(&mut doc.arena, p);
+    });
+    assert_round_trips("<div></p>x</div>", |doc| {
+        let p = first(doc, "p");
+        detach(&mut doc.arena, p);
+    });
+    assert_round_trips("<table><tr><td>x</table>", |doc| {
+        let tr = first(doc, "tr");
+        detach(&mut doc.arena, tr);
+    });
+    assert_round_trips("<table><tr><td>x</table>", |doc| mark(doc, "table"));
+    // Where these attach depends on the markup ahead of them.
+    for local in ["html", "head", "body"] {
+        assert_round_trips("<head></head><template>", |doc| mark(doc, local));
+        assert_round_trips("<head></html><meta><p>", |doc| mark(doc, local));
+    }
+    let out = assert_round_trips("<head></head><template>", |doc| mark(doc, "body"));
+    assert_eq!(out, "<head></head><template></template><body class=\"x\">");
+}
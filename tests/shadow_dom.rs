@@ -0,0 +1,210 @@
+use oxihtml::dom::{set_attr, Attr, Document, Namespace, NodeData, NodeId, QualName, ShadowRoot, ShadowRootMode};
+use oxihtml::serialize::{to_html, to_html_preserving, to_html_with, to_test_format, HtmlOptions};
+use oxihtml::{FragmentContext, Options, Parser};
+
+fn parse_with(input: &str, opts: Options) -> Document {
+    Parser::new(Options {
+        declarative_shadow_roots: true,
+        ..opts
+    })
+    .parse_document(input)
+    .value
+}
+
+fn parse(input: &str) -> Document {
+    parse_with(input, Options::default())
+}
+
+fn first(doc: &Document, local: &str) -> NodeId {
+    (0..doc.arena.len())
+        .find(|&id| matches!(&doc.arena[id].data, NodeData::Element { name, .. } if name.local == local))
+        .unwrap()
+}
+
+fn shadow_root(doc: &Document, host: NodeId) -> Option<(NodeId, ShadowRoot)> {
+    let NodeData::Element {
+        shadow_root: Some(root),
+        ..
+    } = doc.arena[host].data
+    else {
+        return None;
+    };
+    match doc.arena[root].data {
+        NodeData::ShadowRoot(shadow) => Some((root, shadow)),
+        _ => None,
+    }
+}
+
+#[test]
+fn templates_with_shadowrootmode_attach_to_their_parent() {
+    let doc = parse(
+        "<x-card><template shadowrootmode=OPEN shadowrootdelegatesfocus shadowrootclonable>\
+         <slot></slot><template><b>inner</b></template></template><i>light</i></x-card>",
+    );
+    let host = first(&doc, "x-card");
+    let (root, shadow) = shadow_root(&doc, host).unwrap();
+    assert_eq!(
+        shadow,
+        ShadowRoot {
+            mode: ShadowRootMode::Open,
+            delegates_focus: true,
+            clonable: true,
+            serializable: false,
+        }
+    );
+    assert_eq!(doc.arena[root].parent, None);
+    assert_eq!(doc.arena[host].children, [first(&doc, "i")]);
+    assert_eq!(
+        to_test_format(&doc.arena, doc.root),
+        "| <html>\n\
+         |   <head>\n\
+         |   <body>\n\
+         |     <x-card>\n\
+         |       #shadow-root (open)\n\
+         |         <slot>\n\
+         |         <template>\n\
+         |           content\n\
+         |             <b>\n\
+         |               \"inner\"\n\
+         |       <i>\n\
+         |         \"light\""
+    );
+}
+
+#[test]
+fn other_templates_stay_templates() {
+    let cases = [
+        "<div><template shadowrootmode=opened></template></div>",
+        "<a><template shadowrootmode=open></template></a>",
+        "<template shadowrootmode=open></template>",
+        "<div><template shadowrootmode=open></template><template shadowrootmode=closed></template></div>",
+    ];
+    for input in cases {
+        let doc = parse(input);
+        let template = (0..doc.arena.len())
+            .rev()
+            .find(|&id| matches!(&doc.arena[id].data, NodeData::Element { name, .. } if name.local == "template"))
+            .unwrap();
+        assert!(doc.arena[template].parent.is_some(), "{input}");
+    }
+    let doc = Parser::new(Options::default())
+        .parse_document("<div><template shadowrootmode=open></template></div>")
+        .value;
+    assert_eq!(shadow_root(&doc, first(&doc, "div")), None);
+}
+
+#[test]
+fn fragments_can_declare_a_shadow_root_for_the_context_element() {
+    let fragment = |tag_name: &str| {
+        let ctx = FragmentContext {
+            namespace: None,
+            tag_name: tag_name.to_string(),
+        };
+        Parser::new(Options {
+            declarative_shadow_roots: true,
+            ..Options::default()
+        })
+        .parse_fragment(
+            ctx,
+            "<template shadowrootmode=closed><b>shadow</b></template>\
+             <template shadowrootmode=open>second</template><i>light</i>",
+        )
+        .value
+    };
+    let frag = fragment("div");
+    let context = frag.context.unwrap();
+    let NodeData::Element {
+        shadow_root: Some(root),
+        ..
+    } = frag.arena[context].data
+    else {
+        panic!("no shadow root on the context element");
+    };
+    assert!(matches!(
+        frag.arena[root].data,
+        NodeData::ShadowRoot(ShadowRoot {
+            mode: ShadowRootMode::Closed,
+            ..
+        })
+    ));
+    assert_eq!(to_test_format(&frag.arena, root), "| <b>\n|   \"shadow\"");
+    assert_eq!(
+        to_test_format(&frag.arena, frag.root),
+        "| <template>\n|   shadowrootmode=\"open\"\n|   content\n|     \"second\"\n| <i>\n|   \"light\""
+    );
+
+    let frag = fragment("td");
+    assert!(matches!(
+        frag.arena[frag.context.unwrap()].data,
+        NodeData::Element { shadow_root: None, .. }
+    ));
+    assert_eq!(frag.arena[frag.root].children.len(), 3);
+}
+
+#[test]
+fn shadow_roots_serialize_like_get_html() {
+    let doc = parse(
+        "<div id=a><template shadowrootmode=open shadowrootserializable>A</template>a</div>\
+         <span><template shadowrootmode=closed shadowrootclonable>B</template>b</span>",
+    );
+    let body = first(&doc, "body");
+    assert_eq!(to_html(&doc.arena, body), "<div id=\"a\">a</div><span>b</span>");
+
+    let serializable = HtmlOptions {
+        serializable_shadow_roots: true,
+        ..HtmlOptions::default()
+    };
+    assert_eq!(
+        to_html_with(&doc.arena, body, &serializable),
+        "<div id=\"a\"><template shadowrootmode=\"open\" shadowrootserializable=\"\">A</template>a</div><span>b</span>"
+    );
+    let div = first(&doc, "div");
+    assert_eq!(
+        to_html_with(&doc.arena, div, &serializable),
+        "<template shadowrootmode=\"open\" shadowrootserializable=\"\">A</template>a"
+    );
+
+    let (closed, _) = shadow_root(&doc, first(&doc, "span")).unwrap();
+    let listed = HtmlOptions {
+        shadow_roots: vec![closed],
+        ..HtmlOptions::default()
+    };
+    let out = to_html_with(&doc.arena, body, &listed);
+    assert_eq!(
+        out,
+        "<div id=\"a\">a</div><span><template shadowrootmode=\"closed\" shadowrootclonable=\"\">B</template>b</span>"
+    );
+    let reparsed = parse(&out);
+    let (closed, _) = shadow_root(&reparsed, first(&reparsed, "span")).unwrap();
+    let listed = HtmlOptions {
+        shadow_roots: vec![closed],
+        ..HtmlOptions::default()
+    };
+    assert_eq!(to_html_with(&reparsed.arena, first(&reparsed, "body"), &listed), out);
+}
+
+#[test]
+fn preserved_sources_keep_declarative_templates() {
+    let input = "<my-el><Template ShadowRootMode='open'><p>shadow</template>\n  <p>light</p>\n</my-el>";
+    let preserving = Options {
+        preserve_source: true,
+        ..Options::default()
+    };
+    let doc = parse_with(input, preserving.clone());
+    assert_eq!(to_html_preserving(&doc), input);
+
+    let mut doc = parse_with(input, preserving);
+    let host = first(&doc, "my-el");
+    set_attr(
+        &mut doc.arena,
+        host,
+        Attr {
+            name: QualName {
+                ns: Namespace::Html,
+                local: "hidden".to_string(),
+            },
+            value: String::new(),
+        },
+    );
+    assert_eq!(to_html_preserving(&doc), input.replace("<my-el>", "<my-el hidden=\"\">"));
+}
@@ -0,0 +1,202 @@
+use oxihtml::dom::{Attr, Doctype, Namespace, NodeId, QualName, QuirksMode};
+use oxihtml::{Options, ParseError, Parser, TreeSink};
+
+// A sink with its own node store that renders an indented outline.
+#[derive(Default)]
+struct Outline {
+    nodes: Vec<OutlineNode>,
+    quirks: Option<QuirksMode>,
+    errors: usize,
+}
+
+struct OutlineNode {
+    label: String,
+    name: Option<QualName>,
+    attrs: Vec<Attr>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    contents: Option<NodeId>,
+}
+
+impl Outline {
+    fn new() -> Self {
+        let mut sink = Self::default();
+        sink.push("#document".into(), None, Vec::new());
+        sink
+    }
+
+    fn push(&mut self, label: String, name: Option<QualName>, attrs: Vec<Attr>) -> NodeId {
+        self.nodes.push(OutlineNode {
+            label,
+            name,
+            attrs,
+            parent: None,
+            children: Vec::new(),
+            contents: None,
+        });
+        self.nodes.len() - 1
+    }
+
+    fn preceding(&self, parent: NodeId, reference: Option<NodeId>) -> Option<NodeId> {
+        let siblings = &self.nodes[parent].children;
+        let index = reference.and_then(|r| siblings.iter().position(|&c| c == r)).unwrap_or(siblings.len());
+        index.checked_sub(1).and_then(|i| siblings.get(i)).copied()
+    }
+
+    fn render(&self, node: NodeId, depth: usize, out: &mut String) {
+        for &child in &self.nodes[node].children {
+            let n = &self.nodes[child];
+            out.push_str(&format!("{}{}\n", "  ".repeat(depth), n.label));
+            if let Some(contents) = n.contents {
+                out.push_str(&format!("{}content\n", "  ".repeat(depth + 1)));
+                self.render(contents, depth + 2, out);
+            }
+            self.render(child, depth + 1, out);
+        }
+    }
+}
+
+impl TreeSink for Outline {
+    fn document(&self) -> NodeId {
+        0
+    }
+
+    fn element_name(&self, node: NodeId) -> Option<&QualName> {
+        self.nodes[node].name.as_ref()
+    }
+
+    fn element_attrs(&self, element: NodeId) -> &[Attr] {
+        &self.nodes[element].attrs
+    }
+
+    fn parent(&self, node: NodeId) -> Option<NodeId> {
+        self.nodes[node].parent
+    }
+
+    fn create_element(&mut self, name: QualName, attrs: Vec<Attr>) -> NodeId {
+        let label = match name.ns {
+            Namespace::Html => format!("<{}>", name.local),
+            _ => format!("<{:?} {}>", name.ns, name.local),
+        };
+        self.push(label, Some(name), attrs)
+    }
+
+    fn create_comment(&mut self, data: String) -> NodeId {
+        self.push(format!("<!-- {data} -->"), None, Vec::new())
+    }
+
+    fn create_doctype(&mut self, doctype: Doctype) -> NodeId {
+        self.push(format!("<!DOCTYPE {}>", doctype.name), None, Vec::new())
+    }
+
+    fn append(&mut self, parent: NodeId, child: NodeId) {
+        self.insert_before(parent, child, None);
+    }
+
+    fn insert_before(&mut self, parent: NodeId, child: NodeId, reference: Option<NodeId>) {
+        self.remove_from_parent(child);
+        let siblings = &mut self.nodes[parent].children;
+        let index = reference.and_then(|r| siblings.iter().position(|&c| c == r)).unwrap_or(siblings.len());
+        siblings.insert(index, child);
+        self.nodes[child].parent = Some(parent);
+    }
+
+    fn insert_text(&mut self, parent: NodeId, reference: Option<NodeId>, text: &str) -> NodeId {
+        if let Some(prev) = self.preceding(parent, reference)
+            && self.nodes[prev].label.starts_with('"')
+        {
+            let label = &mut self.nodes[prev].label;
+            label.pop();
+            label.push_str(text);
+            label.push('"');
+            return prev;
+        }
+        let id = self.push(format!("\"{text}\""), None, Vec::new());
+        self.insert_before(parent, id, reference);
+        id
+    }
+
+    fn preceding_text(&self, parent: NodeId, reference: Option<NodeId>) -> Option<&str> {
+        let label = &self.nodes[self.preceding(parent, reference)?].label;
+        label.strip_prefix('"')?.strip_suffix('"')
+    }
+
+    fn remove_from_parent(&mut self, node: NodeId) {
+        if let Some(parent) = self.nodes[node].parent.take() {
+            self.nodes[parent].children.retain(|&c| c != node);
+        }
+    }
+
+    fn reparent_children(&mut self, from: NodeId, to: NodeId) {
+        for child in std::mem::take(&mut self.nodes[from].children) {
+            self.nodes[child].parent = Some(to);
+            self.nodes[to].children.push(child);
+        }
+    }
+
+    fn template_contents(&mut self, template: NodeId) -> NodeId {
+        if let Some(contents) = self.nodes[template].contents {
+            return contents;
+        }
+        let id = self.push("content".into(), None, Vec::new());
+        self.nodes[template].contents = Some(id);
+        id
+    }
+
+    fn add_attrs_if_missing(&mut self, element: NodeId, attrs: Vec<Attr>) {
+        for attr in attrs {
+            if !self.nodes[element].attrs.iter().any(|a| a.name == attr.name) {
+                self.nodes[element].attrs.push(attr);
+            }
+        }
+    }
+
+    fn set_quirks_mode(&mut self, mode: QuirksMode) {
+        self.quirks = Some(mode);
+    }
+
+    fn parse_error(&mut self, _error: &ParseError) {
+        self.errors += 1;
+    }
+}
+
+#[test]
+fn builds_into_a_custom_sink() {
+    let mut parser = Parser::new(Options {
+        collect_errors: true,
+        ..Options::default()
+    });
+    let input = "<p>1<b>2<i>3</b>4</i>5<table>x<tr><td><template>t</template></table><svg><path/></svg>";
+    let parsed = parser.parse_document_into(Outline::new(), input);
+    let sink = &parsed.value;
+    assert_eq!(sink.quirks, Some(QuirksMode::Quirks));
+    assert_eq!(sink.errors, parsed.errors.len());
+
+    let mut outline = String::new();
+    sink.render(0, 0, &mut outline);
+    assert_eq!(
+        outline,
+        r#"<html>
+  <head>
+  <body>
+    <p>
+      "1"
+      <b>
+        "2"
+        <i>
+          "3"
+      <i>
+        "4"
+      "5x"
+      <table>
+        <tbody>
+          <tr>
+            <td>
+              <template>
+                content
+                  "t"
+      <Svg svg>
+        <Svg path>
+"#
+    );
+}
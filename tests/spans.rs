@@ -0,0 +1,162 @@
+use oxihtml::dom::{Document, NodeData, NodeId, NodeSource};
+use oxihtml::{Options, Parser, Span};
+
+fn parse(input: &str) -> Document {
+    Parser::new(Options {
+        source_spans: true,
+        ..Options::default()
+    })
+    .parse_document(input)
+    .value
+}
+
+// Elements named `local` in document order.
+fn elements(doc: &Document, local: &str) -> Vec<NodeId> {
+    let mut found = Vec::new();
+    let mut stack = vec![doc.root];
+    while let Some(id) = stack.pop() {
+        if let NodeData::Element {
+            name,
+            template_contents,
+            ..
+        } = &doc.arena[id].data
+        {
+            if name.local == local {
+                found.push(id);
+            }
+            stack.extend(template_contents);
+        }
+        stack.extend(doc.arena[id].children.iter().rev());
+    }
+    found
+}
+
+fn text(input: &str, span: Option<Span>) -> Option<&str> {
+    span.map(|s| &input[s.start..s.end])
+}
+
+fn source(doc: &Document, node: NodeId) -> &NodeSource {
+    doc.source(node).expect("no source recorded")
+}
+
+#[test]
+fn nodes_point_back_at_their_markup() {
+    let input = "<!DOCTYPE html><p class=\"a b\" hidden>one &amp; two</p><!--note--><table><tr><td>x</table>";
+    let doc = parse(input);
+
+    let p = elements(&doc, "p")[0];
+    let p_source = source(&doc, p);
+    assert_eq!(text(input, p_source.start_tag), Some("<p class=\"a b\" hidden>"));
+    assert_eq!(text(input, p_source.end_tag), Some("</p>"));
+    let attrs: Vec<_> = p_source
+        .attributes
+        .iter()
+        .map(|a| (text(input, Some(a.name)), text(input, a.value)))
+        .collect();
+    assert_eq!(attrs, [(Some("class"), Some("a b")), (Some("hidden"), None)]);
+    assert!(!p_source.synthetic);
+
+    let text_node = doc.arena[p].children[0];
+    assert_eq!(text(input, source(&doc, text_node).data), Some("one &amp; two"));
+    let comment = doc.arena[doc.arena[p].parent.unwrap()].children[1];
+    assert_eq!(text(input, source(&doc, comment).data), Some("<!--note-->"));
+    let doctype = doc.arena[doc.root].children[0];
+    assert_eq!(text(input, source(&doc, doctype).data), Some("<!DOCTYPE html>"));
+
+    for implied in ["html", "head", "body", "tbody"] {
+        let node = elements(&doc, implied)[0];
+        assert!(source(&doc, node).synthetic, "{implied} should be synthetic");
+        assert_eq!(source(&doc, node).start_tag, None);
+    }
+    let td = elements(&doc, "td")[0];
+    assert_eq!(text(input, source(&doc, td).start_tag), Some("<td>"));
+    assert_eq!(source(&doc, td).end_tag, None);
+    assert_eq!(text(input, source(&doc, elements(&doc, "table")[0]).end_tag), Some("</table>"));
+}
+
+#[test]
+fn adoption_agency_clones_are_synthetic() {
+    let input = "<b>1<p>2</b>3</p>";
+    let doc = parse(input);
+    let bs = elements(&doc, "b");
+    assert_eq!(bs.len(), 2);
+    assert!(!source(&doc, bs[0]).synthetic);
+    assert_eq!(text(input, source(&doc, bs[0]).end_tag), Some("</b>"));
+    assert!(source(&doc, bs[1]).synthetic);
+    assert_eq!(text(input, source(&doc, elements(&doc, "p")[0]).end_tag), Some("</p>"));
+}
+
+#[test]
+fn merged_attributes_point_into_the_later_tag() {
+    let input = "<html lang=en><body><p>x<html lang=fr dir=rtl></body></html>";
+    let doc = parse(input);
+    let html = elements(&doc, "html")[0];
+    let names: Vec<_> = source(&doc, html).attributes.iter().map(|a| text(input, Some(a.name))).collect();
+    assert_eq!(names, [Some("lang"), Some("dir")]);
+    assert_eq!(source(&doc, html).attributes[1].name.start, input.find("dir").unwrap());
+    assert_eq!(text(input, source(&doc, html).end_tag), Some("</html>"));
+    assert_eq!(text(input, source(&doc, elements(&doc, "body")[0]).end_tag), Some("</body>"));
+}
+
+#[test]
+fn table_text_covers_its_tokens() {
+    let input = "<table>a<!--c-->b<tr><td>c</td></tr></table>";
+    let doc = parse(input);
+    let body = elements(&doc, "body")[0];
+    let fostered = doc.arena[body].children[0];
+    assert_eq!(doc.arena[fostered].data, NodeData::Text("ab".to_string()));
+    assert_eq!(text(input, source(&doc, fostered).data), Some("a<!--c-->b"));
+}
+
+#[test]
+fn offsets_map_to_the_innermost_node() {
+    let input = "<div id=outer><p>hello <b>world</b></p><template><i>t</i></template></div>";
+    let doc = parse(input);
+    let at = |needle: &str| doc.node_at_offset(input.find(needle).unwrap());
+    let name = |id: Option<NodeId>| match id.map(|id| &doc.arena[id].data) {
+        Some(NodeData::Element { name, .. }) => name.local.clone(),
+        Some(NodeData::Text(text)) => format!("{text:?}"),
+        other => format!("{other:?}"),
+    };
+    assert_eq!(name(at("id=outer")), "div");
+    assert_eq!(name(at("llo")), "\"hello \"");
+    assert_eq!(name(at("<b>")), "b");
+    assert_eq!(name(at("orld")), "\"world\"");
+    assert_eq!(name(at("</b>")), "b");
+    assert_eq!(name(at("</p>")), "p");
+    assert_eq!(name(at(">t<")), "i");
+    assert_eq!(name(at("</div>")), "div");
+    assert_eq!(doc.node_at_offset(input.len()), None);
+
+    // Text foster-parented out of a table overlaps the table's source.
+    let input = "A<table><tr><td>x</td> B</tr></table>";
+    let doc = parse(input);
+    let index = doc.offset_index();
+    let at = |needle: &str| index.node_at(input.find(needle).unwrap());
+    let text = |id: Option<NodeId>| match id.map(|id| &doc.arena[id].data) {
+        Some(NodeData::Text(text)) => text.clone(),
+        other => format!("{other:?}"),
+    };
+    assert_eq!(text(at("A")), "A B");
+    assert_eq!(text(at("B")), "A B");
+    assert_eq!(index.node_at(input.len()), None);
+}
+
+#[test]
+fn spans_are_opt_in_and_stream_identically() {
+    let input = "<ul><li>one<li>two</ul>";
+    let plain = Parser::new(Options::default()).parse_document(input).value;
+    assert!(plain.sources.is_empty());
+
+    let whole = parse(input);
+    let mut parser = Parser::new(Options {
+        source_spans: true,
+        ..Options::default()
+    })
+    .begin_document();
+    for chunk in input.as_bytes().chunks(3) {
+        parser.feed_bytes(chunk);
+    }
+    let streamed = parser.finish().value;
+    assert_eq!(streamed.sources, whole.sources);
+}
@@ -0,0 +1,127 @@
+use std::io::Read;
+
+use oxihtml::dom::Document;
+use oxihtml::serialize::to_test_format;
+use oxihtml::{Confidence, Encoding, EncodingHint, Options, Parsed, Parser};
+
+const INPUTS: &[&str] = &[
+    "<!DOCTYPE html PUBLIC \"-//W3C//DTD HTML 4.01//EN\">\r\n<title>a&amp;b&notit; &notin;</title>\r\r\n<p a='&lt;x' b=&copy c>x&#x1F600;y",
+    "<svg><![CDATA[ <b> ]]><foreignObject><p>caf\u{E9} \u{1F600}</svg><!-- c --!><script><!--<script></script>--></script>",
+    "<table><tr>x<td>&#0;\0<textarea>\r\nz</textarea><select><option>&AMP<frameset>",
+];
+
+fn options() -> Options {
+    Options {
+        collect_errors: true,
+        ..Options::default()
+    }
+}
+
+fn summary(parsed: &Parsed<Document>) -> (String, Vec<String>) {
+    let tree = to_test_format(&parsed.value.arena, parsed.value.root);
+    let errors = parsed.errors.iter().map(|e| format!("{} {:?}", e, e.span)).collect();
+    (tree, errors)
+}
+
+#[test]
+fn chunked_input_matches_one_shot_parse() {
+    let mut parser = Parser::new(options());
+    for input in INPUTS {
+        let expected = summary(&parser.parse_document(input));
+        let boundaries: Vec<usize> = (0..input.len()).filter(|&i| input.is_char_boundary(i)).collect();
+        for size in 1..8 {
+            let cuts: Vec<usize> = boundaries.iter().copied().step_by(size).chain([input.len()]).collect();
+            let mut stream = parser.begin_document();
+            for pair in cuts.windows(2) {
+                stream.feed(&input[pair[0]..pair[1]]);
+            }
+            assert_eq!(summary(&stream.finish()), expected, "chunk size {size} for {input:?}");
+        }
+    }
+}
+
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.0.len().min(buf.len()).min(3);
+        buf[..n].copy_from_slice(&self.0[..n]);
+        self.0 = &self.0[n..];
+        Ok(n)
+    }
+}
+
+#[test]
+fn reads_utf8_from_any_reader() {
+    let mut parser = Parser::new(options());
+    for input in INPUTS {
+        let expected = summary(&parser.parse_document(input));
+        let mut stream = parser.begin_document();
+        stream.read_from(Trickle(input.as_bytes())).unwrap();
+        assert_eq!(summary(&stream.finish()), expected);
+    }
+
+    let mut stream = parser.begin_document();
+    stream.feed_bytes(b"<p>\xC3");
+    stream.feed_bytes(b"\xA9\xFF");
+    stream.feed_bytes(b"\xE2\x82");
+    let (tree, _) = summary(&stream.finish());
+    assert!(tree.contains("\"\u{E9}\u{FFFD}\u{FFFD}\""));
+}
+
+#[test]
+fn sniffs_the_encoding_of_streamed_bytes() {
+    let mut parser = Parser::new(options());
+    let mut utf16 = b"\xFF\xFE".to_vec();
+    for unit in "<p>caf\u{E9} \u{1F600}</p>".encode_utf16() {
+        utf16.extend(unit.to_le_bytes());
+    }
+    let legacy = [&b"<meta charset=windows-1252>"[..], &b"<p>caf\xE9".repeat(300)].concat();
+    for input in [utf16, legacy] {
+        let expected = parser.parse_document_bytes(&input, EncodingHint::default());
+        let mut stream = parser.begin_document();
+        stream.read_from(Trickle(&input)).unwrap();
+        let streamed = stream.finish();
+        assert_eq!(summary(&streamed), summary(&expected));
+        assert!(summary(&streamed).0.contains("caf\u{E9}"));
+        assert_eq!((streamed.encoding, streamed.confidence), (expected.encoding, expected.confidence));
+    }
+
+    let hint = EncodingHint {
+        transport: Some("koi8-r".to_string()),
+        fallback: None,
+    };
+    let mut stream = parser.begin_document_bytes(hint);
+    stream.feed_bytes(b"<p>\xC1");
+    let streamed = stream.finish();
+    assert!(summary(&streamed).0.contains("\"\u{430}\""));
+    assert_eq!((streamed.encoding, streamed.confidence), (Some(Encoding::Koi8R), Confidence::Certain));
+
+    let mut stream = parser.begin_document();
+    stream.feed("<p>");
+    stream.feed_bytes(b"\xFF\xFEx");
+    let streamed = stream.finish();
+    assert!(summary(&streamed).0.contains("\"\u{FFFD}\u{FFFD}x\""));
+    assert_eq!(streamed.encoding, None);
+}
+
+#[test]
+fn text_and_bytes_can_be_mixed() {
+    let mut parser = Parser::new(options());
+    let mut stream = parser.begin_document();
+    stream.feed_bytes(b"<p>caf\xC3");
+    stream.feed("!");
+    stream.feed_bytes(b"\xA9a\xE2\x82");
+    stream.feed("b");
+    let streamed = stream.finish();
+    assert!(summary(&streamed).0.contains("\"caf\u{FFFD}!\u{FFFD}a\u{FFFD}b\""));
+    assert_eq!(streamed.encoding, Some(Encoding::Utf8));
+
+    let mut stream = parser.begin_document();
+    stream.feed_bytes(b"<meta charset=windows-1252><p>\xE9");
+    stream.feed("\u{E9}");
+    stream.feed_bytes(b"\xE9");
+    let streamed = stream.finish();
+    assert!(summary(&streamed).0.contains("\"\u{E9}\u{E9}\u{E9}\""));
+    assert_eq!(streamed.encoding, Some(Encoding::Windows1252));
+}
@@ -0,0 +1,161 @@
+use oxihtml::tokenizer::{tokenize, Attribute, DoctypeToken, State, Tag, Token, Tokenizer, TokenizerOpts};
+
+fn start(name: &str, attrs: &[(&str, &str)], self_closing: bool) -> Token {
+    Token::StartTag(Tag {
+        name: name.to_string(),
+        attrs: attrs
+            .iter()
+            .map(|(n, v)| Attribute {
+                name: n.to_string(),
+                value: v.to_string(),
+            })
+            .collect(),
+        self_closing,
+    })
+}
+
+fn end(name: &str) -> Token {
+    Token::EndTag(Tag {
+        name: name.to_string(),
+        attrs: Vec::new(),
+        self_closing: false,
+    })
+}
+
+#[test]
+fn tokenizes_tags_text_and_comments() {
+    assert_eq!(
+        tokenize("<P Class=a id='b' data-x=\"c\">Hi<!-- c --></p><br/>"),
+        vec![
+            start("p", &[("class", "a"), ("id", "b"), ("data-x", "c")], false),
+            Token::Character("Hi".to_string()),
+            Token::Comment(" c ".to_string()),
+            end("p"),
+            start("br", &[], true),
+            Token::Eof,
+        ]
+    );
+}
+
+#[test]
+fn tokenizes_doctype_with_identifiers() {
+    let tokens = tokenize("<!DOCTYPE html PUBLIC \"-//W3C//DTD HTML 4.01//EN\" 'x'>");
+    assert_eq!(
+        tokens[0],
+        Token::Doctype(DoctypeToken {
+            name: Some("html".to_string()),
+            public_id: Some("-//W3C//DTD HTML 4.01//EN".to_string()),
+            system_id: Some("x".to_string()),
+            force_quirks: false,
+        })
+    );
+}
+
+#[test]
+fn drops_duplicate_attributes_and_reports_error() {
+    let mut tokenizer = Tokenizer::new("<a x=1 x=2>");
+    assert_eq!(tokenizer.next(), Some(start("a", &[("x", "1")], false)));
+    assert_eq!(tokenizer.errors().len(), 1);
+}
+
+#[test]
+fn rawtext_state_only_ends_on_appropriate_end_tag() {
+    let mut tokenizer = Tokenizer::new("<style>a</b>c</style>");
+    assert_eq!(tokenizer.next(), Some(start("style", &[], false)));
+    tokenizer.set_state(State::Rawtext);
+    assert_eq!(tokenizer.next(), Some(Token::Character("a</b>c".to_string())));
+    assert_eq!(tokenizer.next(), Some(end("style")));
+    assert_eq!(tokenizer.next(), Some(Token::Eof));
+    assert_eq!(tokenizer.next(), None);
+}
+
+#[test]
+fn opts_choose_initial_state_and_last_start_tag() {
+    let opts = TokenizerOpts {
+        initial_state: State::Rcdata,
+        last_start_tag: Some("textarea".to_string()),
+        cdata_allowed: false,
+    };
+    assert_eq!(
+        Tokenizer::with_opts("<b>&amp;</title></textarea>x", opts).collect::<Vec<_>>(),
+        vec![
+            Token::Character("<b>&</title>".to_string()),
+            end("textarea"),
+            Token::Character("x".to_string()),
+            Token::Eof,
+        ]
+    );
+    let opts = TokenizerOpts {
+        cdata_allowed: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        Tokenizer::with_opts("<![CDATA[<x>]]>", opts).collect::<Vec<_>>(),
+        vec![Token::Character("<x>".to_string()), Token::Eof]
+    );
+}
+
+#[test]
+fn normalizes_newlines() {
+    assert_eq!(
+        tokenize("a\r\nb\rc"),
+        vec![Token::Character("a\nb\nc".to_string()), Token::Eof]
+    );
+}
+
+#[test]
+fn legacy_references_without_semicolon_are_literal_in_attributes() {
+    assert_eq!(
+        tokenize("<a href=\"?x=1&copy=2&not;\">&copy=2</a>"),
+        vec![
+            start("a", &[("href", "?x=1&copy=2\u{AC}")], false),
+            Token::Character("\u{A9}=2".to_string()),
+            end("a"),
+            Token::Eof,
+        ]
+    );
+}
+
+#[test]
+fn cdata_sections_only_in_foreign_content() {
+    assert_eq!(
+        tokenize("<![CDATA[x]]>"),
+        vec![Token::Comment("[CDATA[x]]".to_string()), Token::Eof]
+    );
+    let mut tokenizer = Tokenizer::new("<![CDATA[<x>]]>");
+    tokenizer.set_cdata_allowed(true);
+    assert_eq!(tokenizer.next(), Some(Token::Character("<x>".to_string())));
+}
+
+#[test]
+fn token_spans_tile_the_input() {
+    let input = "a&amp;<b x='<'>\r\n<!-- c --></b><3";
+    let mut tokenizer = Tokenizer::new(input);
+    let mut pieces = Vec::new();
+    while let Some(token) = tokenizer.next() {
+        let span = tokenizer.token_span();
+        pieces.push((token, &input[span.start..span.end]));
+    }
+    let sources: Vec<&str> = pieces.iter().map(|(_, s)| *s).collect();
+    assert_eq!(sources, ["a&amp;", "<b x='<'>", "\r\n", "<!-- c -->", "</b>", "<3", ""]);
+    assert_eq!(pieces.last().unwrap().0, Token::Eof);
+}
+
+#[test]
+fn attribute_spans_follow_the_kept_attributes() {
+    let input = "<p a=\"x&amp;y\" B c=d a=dup e='' f=g>text</p>";
+    let mut tokenizer = Tokenizer::new(input);
+    tokenizer.next();
+    let slice = |span: oxihtml::Span| &input[span.start..span.end];
+    let spans: Vec<(&str, Option<&str>)> = tokenizer
+        .attribute_spans()
+        .iter()
+        .map(|a| (slice(a.name), a.value.map(slice)))
+        .collect();
+    assert_eq!(
+        spans,
+        [("a", Some("x&amp;y")), ("B", None), ("c", Some("d")), ("e", Some("")), ("f", Some("g"))]
+    );
+    tokenizer.next();
+    assert!(tokenizer.attribute_spans().is_empty());
+}
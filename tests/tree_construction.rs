@@ -0,0 +1,282 @@
+use oxihtml::dom::QuirksMode;
+use oxihtml::serialize::to_test_format;
+use oxihtml::{ErrorCode, FragmentContext, Options, Parser};
+
+fn parse(input: &str) -> String {
+    parse_with(Options::default(), input)
+}
+
+fn parse_with(options: Options, input: &str) -> String {
+    let doc = Parser::new(options).parse_document(input).value;
+    to_test_format(&doc.arena, doc.root)
+}
+
+fn error_codes(input: &str) -> Vec<ErrorCode> {
+    let mut parser = Parser::new(Options {
+        collect_errors: true,
+        ..Options::default()
+    });
+    parser
+        .parse_document(input)
+        .errors
+        .into_iter()
+        .map(|e| e.code)
+        .collect()
+}
+
+#[test]
+fn implies_html_head_and_body() {
+    assert_eq!(
+        parse("<!DOCTYPE html><title>x</title>Hi"),
+        "| <!DOCTYPE html>\n| <html>\n|   <head>\n|     <title>\n|       \"x\"\n|   <body>\n|     \"Hi\""
+    );
+}
+
+#[test]
+fn closes_paragraphs_and_list_items_implicitly() {
+    assert_eq!(
+        parse("<!DOCTYPE html><p>a<div>b</div><ul><li>1<li>2</ul>"),
+        "| <!DOCTYPE html>\n| <html>\n|   <head>\n|   <body>\n|     <p>\n|       \"a\"\n|     <div>\n|       \"b\"\n|     <ul>\n|       <li>\n|         \"1\"\n|       <li>\n|         \"2\""
+    );
+}
+
+#[test]
+fn drops_leading_newline_in_pre_and_textarea() {
+    assert_eq!(
+        parse("<!DOCTYPE html><pre>\nx</pre><textarea>\n<b></textarea>"),
+        "| <!DOCTYPE html>\n| <html>\n|   <head>\n|   <body>\n|     <pre>\n|       \"x\"\n|     <textarea>\n|       \"<b>\""
+    );
+}
+
+#[test]
+fn frameset_replaces_body_when_allowed() {
+    assert_eq!(
+        parse("<!DOCTYPE html><frameset><frame></frameset><!--x-->"),
+        "| <!DOCTYPE html>\n| <html>\n|   <head>\n|   <frameset>\n|     <frame>\n|   <!-- x -->"
+    );
+}
+
+#[test]
+fn reports_tree_construction_errors() {
+    assert_eq!(
+        error_codes("<p></div>"),
+        vec![ErrorCode::ExpectedDoctypeButGotStartTag, ErrorCode::EndTagTooEarly]
+    );
+}
+
+#[test]
+fn adoption_agency_fixes_misnested_formatting() {
+    assert_eq!(
+        parse("<!DOCTYPE html><b>1<i>2</b>3</i>4"),
+        "| <!DOCTYPE html>\n| <html>\n|   <head>\n|   <body>\n|     <b>\n|       \"1\"\n|       <i>\n|         \"2\"\n|     <i>\n|       \"3\"\n|     \"4\""
+    );
+}
+
+#[test]
+fn adoption_agency_moves_furthest_block() {
+    assert_eq!(
+        parse("<!DOCTYPE html><a href=x>1<div>2</a>3</div>"),
+        "| <!DOCTYPE html>\n| <html>\n|   <head>\n|   <body>\n|     <a>\n|       href=\"x\"\n|       \"1\"\n|     <div>\n|       <a>\n|         href=\"x\"\n|         \"2\"\n|       \"3\""
+    );
+}
+
+#[test]
+fn noahs_ark_limits_identical_formatting_elements() {
+    assert_eq!(
+        parse("<!DOCTYPE html><p><b><b><b><b>x</p>y"),
+        "| <!DOCTYPE html>\n| <html>\n|   <head>\n|   <body>\n|     <p>\n|       <b>\n|         <b>\n|           <b>\n|             <b>\n|               \"x\"\n|     <b>\n|       <b>\n|         <b>\n|           \"y\""
+    );
+}
+
+#[test]
+fn foster_parents_misplaced_table_content() {
+    assert_eq!(
+        parse("<!DOCTYPE html><table>a<b>b</b><tr><td>c</table>"),
+        "| <!DOCTYPE html>\n| <html>\n|   <head>\n|   <body>\n|     \"a\"\n|     <b>\n|       \"b\"\n|     <table>\n|       <tbody>\n|         <tr>\n|           <td>\n|             \"c\""
+    );
+}
+
+#[test]
+fn implies_table_sections_and_closes_cells() {
+    assert_eq!(
+        parse("<!DOCTYPE html><table><col><td>1<td>2<tr><th>3</table>"),
+        "| <!DOCTYPE html>\n| <html>\n|   <head>\n|   <body>\n|     <table>\n|       <colgroup>\n|         <col>\n|       <tbody>\n|         <tr>\n|           <td>\n|             \"1\"\n|           <td>\n|             \"2\"\n|         <tr>\n|           <th>\n|             \"3\""
+    );
+}
+
+#[test]
+fn adjusts_svg_names_and_foreign_attributes() {
+    assert_eq!(
+        parse("<!DOCTYPE html><svg viewbox='0 0 1 1'><clippath><a xlink:href=#x></a></clippath></svg>"),
+        "| <!DOCTYPE html>\n| <html>\n|   <head>\n|   <body>\n|     <svg svg>\n|       viewBox=\"0 0 1 1\"\n|       <svg clipPath>\n|         <svg a>\n|           xlink href=\"#x\""
+    );
+}
+
+#[test]
+fn mathml_text_integration_points_and_cdata() {
+    assert_eq!(
+        parse("<!DOCTYPE html><math definitionurl=u><mi><b>x</b></mi><![CDATA[<y>]]></math>"),
+        "| <!DOCTYPE html>\n| <html>\n|   <head>\n|   <body>\n|     <math math>\n|       definitionURL=\"u\"\n|       <math mi>\n|         <b>\n|           \"x\"\n|       \"<y>\""
+    );
+}
+
+#[test]
+fn html_start_tags_break_out_of_foreign_content() {
+    assert_eq!(
+        parse("<!DOCTYPE html><svg><g><p>x"),
+        "| <!DOCTYPE html>\n| <html>\n|   <head>\n|   <body>\n|     <svg svg>\n|       <svg g>\n|     <p>\n|       \"x\""
+    );
+}
+
+#[test]
+fn template_children_go_into_contents() {
+    assert_eq!(
+        parse("<!DOCTYPE html><template><tr><td>x</td></tr></template><p>y"),
+        "| <!DOCTYPE html>\n| <html>\n|   <head>\n|     <template>\n|       content\n|         <tr>\n|           <td>\n|             \"x\"\n|   <body>\n|     <p>\n|       \"y\""
+    );
+}
+
+#[test]
+fn template_inside_table_keeps_table_context() {
+    assert_eq!(
+        parse("<!DOCTYPE html><table><template><col></template></table>"),
+        "| <!DOCTYPE html>\n| <html>\n|   <head>\n|   <body>\n|     <table>\n|       <template>\n|         content\n|           <col>"
+    );
+}
+
+fn parse_fragment(namespace: Option<&str>, tag_name: &str, input: &str) -> String {
+    let frag = Parser::new(Options::default())
+        .parse_fragment(
+            FragmentContext {
+                namespace: namespace.map(str::to_string),
+                tag_name: tag_name.to_string(),
+            },
+            input,
+        )
+        .value;
+    to_test_format(&frag.arena, frag.root)
+}
+
+#[test]
+fn fragment_uses_context_for_tokenizer_state() {
+    assert_eq!(parse_fragment(None, "textarea", "<b>x</textarea>y"), "| \"<b>xy\"");
+}
+
+#[test]
+fn fragment_resets_insertion_mode_from_context() {
+    assert_eq!(
+        parse_fragment(None, "tr", "<td>a<tr>b"),
+        "| <td>\n|   \"a\"\n| \"b\""
+    );
+}
+
+#[test]
+fn fragment_in_foreign_context() {
+    assert_eq!(
+        parse_fragment(Some("svg"), "svg", "<foreignobject><p>x</p></foreignobject><rect/>"),
+        "| <svg foreignObject>\n|   <p>\n|     \"x\"\n| <svg rect>"
+    );
+}
+
+fn quirks_mode(input: &str, iframe_srcdoc: bool) -> QuirksMode {
+    Parser::new(Options {
+        iframe_srcdoc,
+        ..Options::default()
+    })
+    .parse_document(input)
+    .value
+    .quirks_mode
+}
+
+#[test]
+fn detects_quirks_mode_from_doctype() {
+    assert_eq!(quirks_mode("<!DOCTYPE html>", false), QuirksMode::NoQuirks);
+    assert_eq!(quirks_mode("<p>", false), QuirksMode::Quirks);
+    assert_eq!(
+        quirks_mode("<!DOCTYPE html PUBLIC \"-//W3C//DTD HTML 4.01 Transitional//EN\">", false),
+        QuirksMode::Quirks
+    );
+    assert_eq!(
+        quirks_mode(
+            "<!DOCTYPE html PUBLIC \"-//W3C//DTD HTML 4.01 Transitional//EN\" \"http://www.w3.org/TR/html4/loose.dtd\">",
+            false
+        ),
+        QuirksMode::LimitedQuirks
+    );
+    assert_eq!(quirks_mode("<p>", true), QuirksMode::NoQuirks);
+}
+
+#[test]
+fn table_does_not_close_paragraph_in_quirks_mode() {
+    assert_eq!(
+        parse("<p><table>"),
+        "| <html>\n|   <head>\n|   <body>\n|     <p>\n|       <table>"
+    );
+}
+
+#[test]
+fn noscript_in_head_depends_on_scripting() {
+    let input = "<!DOCTYPE html><head><noscript><link><p>x</noscript>";
+    let scripting = |scripting_enabled| Options {
+        scripting_enabled,
+        ..Options::default()
+    };
+    assert_eq!(
+        parse_with(scripting(true), input),
+        "| <!DOCTYPE html>\n| <html>\n|   <head>\n|     <noscript>\n|       \"<link><p>x\"\n|   <body>"
+    );
+    assert_eq!(
+        parse_with(scripting(false), input),
+        "| <!DOCTYPE html>\n| <html>\n|   <head>\n|     <noscript>\n|       <link>\n|   <body>\n|     <p>\n|       \"x\""
+    );
+}
+
+#[test]
+fn select_content_depends_on_customizable_select() {
+    let input = "<select><button><b>Pick</b></button><option><img src=a.png>One<option>Two</select>";
+    let customizable = |customizable_select| Options {
+        customizable_select,
+        ..Options::default()
+    };
+    assert_eq!(
+        parse_with(customizable(false), input),
+        "| <html>\n|   <head>\n|   <body>\n|     <select>\n|       \"Pick\"\n|       <option>\n|         \"One\"\n\
+         |       <option>\n|         \"Two\""
+    );
+    assert_eq!(
+        parse_with(customizable(true), input),
+        "| <html>\n|   <head>\n|   <body>\n|     <select>\n|       <button>\n|         <b>\n|           \"Pick\"\n\
+         |       <option>\n|         <img>\n|           src=\"a.png\"\n|         \"One\"\n|       <option>\n|         \"Two\""
+    );
+    assert_eq!(
+        parse_with(customizable(true), "<select><option>a<select>b<input>"),
+        "| <html>\n|   <head>\n|   <body>\n|     <select>\n|       <option>\n|         \"a\"\n|     \"b\"\n|     <input>"
+    );
+}
+
+#[test]
+fn selected_option_is_copied_into_selectedcontent() {
+    let body = |tree: String| tree["| <html>\n|   <head>\n|   <body>\n".len()..].to_string();
+    let customizable = || Options {
+        customizable_select: true,
+        ..Options::default()
+    };
+    assert_eq!(
+        body(parse_with(
+            customizable(),
+            "<select><button><selectedcontent>stale</selectedcontent></button>\
+             <option>One<option selected><b>Two</b></select>"
+        )),
+        "|     <select>\n|       <button>\n|         <selectedcontent>\n|           <b>\n|             \"Two\"\n\
+         |       <option>\n|         \"One\"\n|       <option>\n|         selected=\"\"\n|         <b>\n|           \"Two\""
+    );
+    assert_eq!(
+        body(parse_with(customizable(), "<select><option disabled>A<option>B</option><button><selectedcontent>")),
+        "|     <select>\n|       <option>\n|         disabled=\"\"\n|         \"A\"\n|       <option>\n|         \"B\"\n\
+         |       <button>\n|         <selectedcontent>\n|           \"B\""
+    );
+    assert_eq!(
+        body(parse_with(customizable(), "<select multiple><button><selectedcontent></button><option>A</select>")),
+        "|     <select>\n|       multiple=\"\"\n|       <button>\n|         <selectedcontent>\n|       <option>\n|         \"A\""
+    );
+}